@@ -17,6 +17,12 @@ fi
 mkdir -p "$CLAUDE_HOME/.claude"
 touch "$CLAUDE_HOME/.claude/remote-settings.json"
 
+# keep session transcripts on the mounted volume so a paused job can resume them
+if [ -d /storage/sessions ]; then
+    rm -rf "$CLAUDE_HOME/.claude/projects"
+    ln -s /storage/sessions "$CLAUDE_HOME/.claude/projects"
+fi
+
 PROMPT_PATH="${YUI_PROMPT_PATH:-/workspace/prompt.txt}"
 if [ ! -f "$PROMPT_PATH" ]; then
     echo '{"type":"error","message":"prompt file not found","retryable":false}'
@@ -37,12 +43,87 @@ echo "{\"type\":\"session\",\"session_id\":\"${SESSION_ID}\"}"
 # snapshot workspace before claude runs
 find /workspace -type f | sort > /tmp/before_files.txt 2>/dev/null || true
 
-RESULT=$(claude --print \
-    --output-format json \
+if [ "${YUI_RESUME_SESSION:-}" = "1" ]; then
+    SESSION_ARGS=(--resume "$SESSION_ID")
+else
+    SESSION_ARGS=(--session-id "$SESSION_ID")
+fi
+
+# with the callback API available, tell claude how to reach the user mid-task
+EXTRA_ARGS=()
+if [ -n "${YUI_API_URL:-}" ] && [ -n "${YUI_API_TOKEN:-}" ]; then
+    EXTRA_ARGS=(--append-system-prompt "You are running a task for a user who talks to you over chat. The \`yui\` command reaches them while you work: \`yui ask <question>\` asks them and prints their answer (if it times out, run \`yui answer\` to keep waiting instead of asking again), \`yui progress <message>\` sends a short update, \`yui send-file <path> [caption]\` sends a file right away, \`yui history\` prints recent chat messages, \`yui job <prompt>\` starts a follow-up task and \`yui cron <name> <schedule> <prompt>\` schedules a recurring one.")
+fi
+
+# stream events so every tool call and model turn is reported as a step frame
+# while claude runs; the closing result event is kept for the final frame
+claude --print \
+    --output-format stream-json \
+    --verbose \
     --dangerously-skip-permissions \
-    --no-session-persistence \
+    "${SESSION_ARGS[@]}" \
+    "${EXTRA_ARGS[@]}" \
     --max-turns "$MAX_TURNS" \
-    -p "$PROMPT" 2>/tmp/claude-stderr) || true
+    -p "$PROMPT" 2>/tmp/claude-stderr | python3 -u -c "
+import json, sys, time
+
+def clip(value, limit=500):
+    if value is None:
+        return None
+    if not isinstance(value, str):
+        value = json.dumps(value)
+    return value if len(value) <= limit else value[:limit] + '...'
+
+def step(tool, summary, output, started):
+    print(json.dumps({
+        'type': 'step',
+        'tool': tool,
+        'input': summary,
+        'output': clip(output),
+        'duration_ms': int((time.monotonic() - started) * 1000),
+    }), flush=True)
+
+pending = {}
+turn_started = time.monotonic()
+for raw in sys.stdin:
+    try:
+        event = json.loads(raw)
+    except ValueError:
+        continue
+    content = (event.get('message') or {}).get('content')
+    if not isinstance(content, list):
+        content = []
+    if event.get('type') == 'assistant':
+        narration = None
+        uses_tools = False
+        for block in content:
+            if block.get('type') == 'tool_use':
+                uses_tools = True
+                pending[block.get('id')] = (block.get('name'), clip(block.get('input')), time.monotonic())
+            elif block.get('type') == 'text' and (block.get('text') or '').strip():
+                narration = narration or block['text'].strip().splitlines()[0]
+                step(None, None, block['text'], turn_started)
+        # text that announces the next tool call reads as a progress update;
+        # runtime decides how often the user actually sees one
+        if narration and uses_tools:
+            print(json.dumps({'type': 'progress', 'message': clip(narration, 200)}), flush=True)
+        turn_started = time.monotonic()
+    elif event.get('type') == 'user':
+        for block in content:
+            if block.get('type') != 'tool_result':
+                continue
+            tool, summary, started = pending.pop(block.get('tool_use_id'), (None, None, turn_started))
+            output = block.get('content')
+            if isinstance(output, list):
+                output = ' '.join(part.get('text', '') for part in output if isinstance(part, dict))
+            step(tool, summary, output, started)
+        turn_started = time.monotonic()
+    elif event.get('type') == 'result':
+        with open('/tmp/claude-result.json', 'w') as f:
+            json.dump(event, f)
+" || true
+
+RESULT=$(cat /tmp/claude-result.json 2>/dev/null || true)
 
 STDERR_CONTENT=$(cat /tmp/claude-stderr 2>/dev/null || true)
 if [ -n "$STDERR_CONTENT" ]; then
@@ -30,17 +30,69 @@ export const listCrons = (args: { limit?: number } = {}) =>
 export const listMessages = (args: { chat_id?: string; limit?: number } = {}) =>
   rpc<Message[]>("list_messages", args);
 
+export const listJobLogs = (args: {
+  job_id: string;
+  after?: string;
+  limit?: number;
+}) => rpc<LogEntry[]>("list_job_logs", args);
+
+export const listJobSteps = (args: { job_id: string }) =>
+  rpc<AgentStep[]>("list_job_steps", args);
+
+export const listArtifacts = (args: { job_id?: string; limit?: number } = {}) =>
+  rpc<Artifact[]>("list_artifacts", args);
+
+export const artifactUrl = (id: string) => `${API}/artifacts/${id}`;
+
 export const getTrace = (args: { trace_id: string }) =>
   rpc<TraceView>("get_trace", args);
 
 export const cancelJob = (args: { job_id: string; reason?: string }) =>
   rpc<{ cancelled: boolean }>("cancel_job", args);
 
+export const retryJob = (args: { job_id: string }) =>
+  rpc<{ retried: boolean }>("retry_job", args);
+
 export const toggleCron = (args: { cron_id: string; enabled: boolean }) =>
   rpc<{ updated: boolean }>("toggle_cron", args);
 
+export const setCronNotifyPolicy = (args: {
+  cron_id: string;
+  notify_policy: CronNotifyPolicy;
+}) => rpc<{ updated: boolean }>("set_cron_notify_policy", args);
+
+export const setCronEgressPolicy = (args: {
+  cron_id: string;
+  egress_policy: EgressPolicy | null;
+  egress_allow: string[];
+}) => rpc<{ updated: boolean }>("set_cron_egress_policy", args);
+
+export const listSecrets = () => rpc<Secret[]>("list_secrets", {});
+
+export const setSecret = (args: {
+  name: string;
+  description?: string | null;
+  value?: string | null;
+  inject_as: SecretInjection;
+  allowed_kinds: string[];
+  allowed_crons: string[];
+}) => rpc<{ id: string }>("set_secret", args);
+
+export const deleteSecret = (args: { secret_id: string }) =>
+  rpc<{ deleted: boolean }>("delete_secret", args);
+
+export const listWorkspaces = () => rpc<Workspace[]>("list_workspaces", {});
+
+export const resetWorkspace = (args: { workspace_id: string }) =>
+  rpc<{ requested: boolean }>("reset_workspace", args);
+
+export const listQueue = () => rpc<QueueEntry[]>("list_queue", {});
+
 export const getHealth = () => rpc<Health>("get_health", {});
 
+export const getStageLatency = (args: { days?: number; kind?: string } = {}) =>
+  rpc<StageLatency[]>("get_stage_latency", args);
+
 export interface Health {
   pending_jobs: number;
   running_jobs: number;
@@ -48,6 +100,39 @@ export interface Health {
   pending_outbox: number;
   dead_letter_outbox: number;
   stuck_jobs: number;
+  stale_daemons: number;
+  daemons: DaemonStatus[];
+}
+
+export interface DaemonStatus {
+  daemon: string;
+  instance_id: string;
+  host: string | null;
+  version: string;
+  started_at: string;
+  last_tick_at: string;
+  last_error: string | null;
+  last_error_at: string | null;
+  items_processed: number;
+  stale: boolean;
+}
+
+export type Stage =
+  | "buffer_wait"
+  | "triage"
+  | "enrichment"
+  | "queue_wait"
+  | "execution"
+  | "rewrite"
+  | "delivery";
+
+export interface StageLatency {
+  day: string;
+  kind: string;
+  stage: Stage;
+  samples: number;
+  p50_ms: number;
+  p95_ms: number;
 }
 
 export interface EventRow {
@@ -67,10 +152,17 @@ export interface Job {
   prompt: string | null;
   enriched_prompt: string | null;
   source_ids: string[];
+  depends_on: string[];
+  workspace_id: string | null;
   resume_input: string | null;
   output: string | null;
   error: string | null;
   cancel_reason: string | null;
+  cron_id: string | null;
+  priority: number;
+  attempt_count: number;
+  max_attempts: number;
+  retry_at: string | null;
   started_at: string | null;
   finished_at: string | null;
   trace_id: string | null;
@@ -78,6 +170,15 @@ export interface Job {
   updated_at: string;
 }
 
+export interface QueueEntry {
+  job_id: string;
+  chat_id: string;
+  priority: number;
+  chat_slot: number;
+  position: number;
+  created_at: string;
+}
+
 export interface Outbox {
   id: string;
   chat_id: string;
@@ -92,6 +193,10 @@ export interface Outbox {
   updated_at: string;
 }
 
+export type CronNotifyPolicy = "always" | "on_change" | "noteworthy";
+
+export type EgressPolicy = "none" | "allowlist" | "full";
+
 export interface Cron {
   id: string;
   name: string;
@@ -100,12 +205,73 @@ export interface Cron {
   chat_id: string;
   prompt: string;
   enabled: boolean;
+  notify_policy: CronNotifyPolicy;
+  egress_policy: EgressPolicy | null;
+  egress_allow: string[];
   last_run_at: string | null;
   next_run_at: string | null;
   created_at: string;
   updated_at: string;
 }
 
+export type SecretInjection = "env" | "file";
+
+export interface Secret {
+  id: string;
+  name: string;
+  description: string | null;
+  inject_as: SecretInjection;
+  allowed_kinds: string[];
+  allowed_crons: string[];
+  created_at: string;
+  updated_at: string;
+}
+
+export interface Workspace {
+  id: string;
+  chat_id: string;
+  name: string;
+  quota_mb: number;
+  size_bytes: number;
+  locked_by: string | null;
+  reset_requested_at: string | null;
+  last_used_at: string | null;
+  created_at: string;
+}
+
+export interface LogEntry {
+  id: string;
+  job_id: string;
+  stream: "stdout" | "stderr";
+  line: string;
+  created_at: string;
+}
+
+export interface AgentStep {
+  id: string;
+  job_id: string;
+  step_number: number;
+  tool_name: string | null;
+  input_summary: string | null;
+  output_summary: string | null;
+  duration_ms: number | null;
+  created_at: string;
+}
+
+export interface Artifact {
+  id: string;
+  job_id: string;
+  chat_id: string;
+  name: string;
+  kind: string;
+  mime: string;
+  size_bytes: number;
+  sha256: string;
+  path: string;
+  trace_id: string | null;
+  created_at: string;
+}
+
 export interface Message {
   id: string;
   platform_id: string | null;
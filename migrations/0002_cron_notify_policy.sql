@@ -0,0 +1,14 @@
+-- @up
+
+ALTER TABLE crons ADD COLUMN IF NOT EXISTS notify_policy text NOT NULL DEFAULT 'always'
+    CHECK (notify_policy IN ('always', 'on_change', 'noteworthy'));
+
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS cron_id uuid;
+
+CREATE INDEX IF NOT EXISTS idx_jobs_cron_finished ON jobs (cron_id, finished_at DESC) WHERE cron_id IS NOT NULL;
+
+-- @down
+
+DROP INDEX IF EXISTS idx_jobs_cron_finished;
+ALTER TABLE jobs DROP COLUMN IF EXISTS cron_id;
+ALTER TABLE crons DROP COLUMN IF EXISTS notify_policy;
@@ -0,0 +1,24 @@
+-- @up
+
+ALTER TABLE crons ADD COLUMN IF NOT EXISTS claimed_by text;
+ALTER TABLE crons ADD COLUMN IF NOT EXISTS lease_until timestamptz;
+
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS claimed_by text;
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS lease_until timestamptz;
+
+ALTER TABLE outbox ADD COLUMN IF NOT EXISTS claimed_by text;
+ALTER TABLE outbox ADD COLUMN IF NOT EXISTS lease_until timestamptz;
+
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS claimed_by text;
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS lease_until timestamptz;
+
+-- @down
+
+ALTER TABLE messages DROP COLUMN IF EXISTS lease_until;
+ALTER TABLE messages DROP COLUMN IF EXISTS claimed_by;
+ALTER TABLE outbox DROP COLUMN IF EXISTS lease_until;
+ALTER TABLE outbox DROP COLUMN IF EXISTS claimed_by;
+ALTER TABLE jobs DROP COLUMN IF EXISTS lease_until;
+ALTER TABLE jobs DROP COLUMN IF EXISTS claimed_by;
+ALTER TABLE crons DROP COLUMN IF EXISTS lease_until;
+ALTER TABLE crons DROP COLUMN IF EXISTS claimed_by;
@@ -0,0 +1,40 @@
+-- @up
+
+-- Loops LISTEN on yui_<table> and wake as soon as their input table changes.
+-- Triggers are scoped to the columns loops actually wait on so that
+-- heartbeats and lease stamps do not wake anything.
+
+CREATE OR REPLACE FUNCTION yui_notify_table_change()
+RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM pg_notify('yui_' || TG_TABLE_NAME, TG_OP);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DO $$ BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_trigger WHERE tgname = 'messages_notify') THEN
+        CREATE TRIGGER messages_notify AFTER INSERT OR UPDATE OF content_version, is_deleted ON messages
+            FOR EACH STATEMENT EXECUTE FUNCTION yui_notify_table_change();
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM pg_trigger WHERE tgname = 'jobs_notify') THEN
+        CREATE TRIGGER jobs_notify AFTER INSERT OR UPDATE OF status ON jobs
+            FOR EACH STATEMENT EXECUTE FUNCTION yui_notify_table_change();
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM pg_trigger WHERE tgname = 'outbox_notify') THEN
+        CREATE TRIGGER outbox_notify AFTER INSERT OR UPDATE OF rewritten_at ON outbox
+            FOR EACH STATEMENT EXECUTE FUNCTION yui_notify_table_change();
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM pg_trigger WHERE tgname = 'crons_notify') THEN
+        CREATE TRIGGER crons_notify AFTER INSERT OR UPDATE OF enabled, next_run_at ON crons
+            FOR EACH STATEMENT EXECUTE FUNCTION yui_notify_table_change();
+    END IF;
+END $$;
+
+-- @down
+
+DROP TRIGGER IF EXISTS crons_notify ON crons;
+DROP TRIGGER IF EXISTS outbox_notify ON outbox;
+DROP TRIGGER IF EXISTS jobs_notify ON jobs;
+DROP TRIGGER IF EXISTS messages_notify ON messages;
+DROP FUNCTION IF EXISTS yui_notify_table_change();
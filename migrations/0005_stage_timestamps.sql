@@ -0,0 +1,16 @@
+-- @up
+
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS received_at timestamptz;
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS enriched_at timestamptz;
+
+CREATE INDEX IF NOT EXISTS idx_messages_created ON messages (created_at);
+CREATE INDEX IF NOT EXISTS idx_jobs_created ON jobs (created_at);
+CREATE INDEX IF NOT EXISTS idx_outbox_created ON outbox (created_at);
+
+-- @down
+
+DROP INDEX IF EXISTS idx_outbox_created;
+DROP INDEX IF EXISTS idx_jobs_created;
+DROP INDEX IF EXISTS idx_messages_created;
+ALTER TABLE jobs DROP COLUMN IF EXISTS enriched_at;
+ALTER TABLE messages DROP COLUMN IF EXISTS received_at;
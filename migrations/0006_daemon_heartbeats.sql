@@ -0,0 +1,21 @@
+-- @up
+
+CREATE TABLE IF NOT EXISTS daemon_heartbeats (
+    daemon          text NOT NULL,
+    instance_id     text NOT NULL,
+    host            text,
+    version         text NOT NULL,
+    started_at      timestamptz NOT NULL DEFAULT now(),
+    last_tick_at    timestamptz NOT NULL DEFAULT now(),
+    last_error      text,
+    last_error_at   timestamptz,
+    items_processed bigint NOT NULL DEFAULT 0,
+    PRIMARY KEY (daemon, instance_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_daemon_heartbeats_tick ON daemon_heartbeats (last_tick_at DESC);
+
+-- @down
+
+DROP INDEX IF EXISTS idx_daemon_heartbeats_tick;
+DROP TABLE IF EXISTS daemon_heartbeats;
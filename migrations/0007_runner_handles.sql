@@ -0,0 +1,9 @@
+-- @up
+
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS runner_backend text;
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS runner_run_id uuid;
+
+-- @down
+
+ALTER TABLE jobs DROP COLUMN IF EXISTS runner_run_id;
+ALTER TABLE jobs DROP COLUMN IF EXISTS runner_backend;
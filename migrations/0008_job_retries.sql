@@ -0,0 +1,11 @@
+-- @up
+
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS attempt_count int NOT NULL DEFAULT 0;
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS max_attempts int NOT NULL DEFAULT 3;
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS retry_at timestamptz;
+
+-- @down
+
+ALTER TABLE jobs DROP COLUMN IF EXISTS retry_at;
+ALTER TABLE jobs DROP COLUMN IF EXISTS max_attempts;
+ALTER TABLE jobs DROP COLUMN IF EXISTS attempt_count;
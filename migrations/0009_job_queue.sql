@@ -0,0 +1,38 @@
+-- @up
+
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS priority int NOT NULL DEFAULT 0;
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS queue_notified_at timestamptz;
+
+UPDATE jobs SET priority = -10 WHERE kind = 'schedule' AND status IN ('draft', 'pending');
+
+CREATE INDEX IF NOT EXISTS idx_jobs_queue ON jobs (priority DESC, created_at) WHERE status = 'pending';
+
+-- Startable pending jobs in the order the runtime launches them. chat_slot is
+-- the run slot the job would take in its chat, counting runs already in flight,
+-- so interleaving by slot keeps one busy chat from starving the others.
+CREATE OR REPLACE VIEW job_queue AS
+WITH occupied AS (
+    SELECT chat_id, count(*) AS runs
+    FROM jobs
+    WHERE status = 'running' OR (status = 'pending' AND lease_until > now())
+    GROUP BY chat_id
+), ranked AS (
+    SELECT j.id, j.chat_id, j.kind, j.priority, j.created_at,
+           row_number() OVER (PARTITION BY j.chat_id ORDER BY j.priority DESC, j.created_at)
+               + COALESCE(o.runs, 0) AS chat_slot
+    FROM jobs j
+    LEFT JOIN occupied o USING (chat_id)
+    WHERE j.status = 'pending'
+      AND (j.lease_until IS NULL OR j.lease_until < now())
+      AND (j.retry_at IS NULL OR j.retry_at <= now())
+)
+SELECT id, chat_id, kind, priority, created_at, chat_slot,
+       row_number() OVER (ORDER BY priority DESC, chat_slot, created_at) AS position
+FROM ranked;
+
+-- @down
+
+DROP VIEW IF EXISTS job_queue;
+DROP INDEX IF EXISTS idx_jobs_queue;
+ALTER TABLE jobs DROP COLUMN IF EXISTS queue_notified_at;
+ALTER TABLE jobs DROP COLUMN IF EXISTS priority;
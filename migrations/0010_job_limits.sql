@@ -0,0 +1,17 @@
+-- @up
+
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS resource_profile text;
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS max_wall_secs int;
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS memory_mb int;
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS cpus double precision;
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS disk_mb int;
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS max_turns int;
+
+-- @down
+
+ALTER TABLE jobs DROP COLUMN IF EXISTS max_turns;
+ALTER TABLE jobs DROP COLUMN IF EXISTS disk_mb;
+ALTER TABLE jobs DROP COLUMN IF EXISTS cpus;
+ALTER TABLE jobs DROP COLUMN IF EXISTS memory_mb;
+ALTER TABLE jobs DROP COLUMN IF EXISTS max_wall_secs;
+ALTER TABLE jobs DROP COLUMN IF EXISTS resource_profile;
@@ -0,0 +1,13 @@
+-- @up
+
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS progress text;
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS progress_at timestamptz;
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS progress_notified_at timestamptz;
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS progress_messages int NOT NULL DEFAULT 0;
+
+-- @down
+
+ALTER TABLE jobs DROP COLUMN IF EXISTS progress_messages;
+ALTER TABLE jobs DROP COLUMN IF EXISTS progress_notified_at;
+ALTER TABLE jobs DROP COLUMN IF EXISTS progress_at;
+ALTER TABLE jobs DROP COLUMN IF EXISTS progress;
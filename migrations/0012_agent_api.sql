@@ -0,0 +1,15 @@
+-- @up
+
+-- sha256 of the bearer token a running agent uses to call back into Yui
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS agent_token_hash bytea;
+-- the user's answer to a question the agent asked while still running
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS agent_answer text;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_jobs_agent_token ON jobs (agent_token_hash)
+    WHERE agent_token_hash IS NOT NULL;
+
+-- @down
+
+DROP INDEX IF EXISTS idx_jobs_agent_token;
+ALTER TABLE jobs DROP COLUMN IF EXISTS agent_answer;
+ALTER TABLE jobs DROP COLUMN IF EXISTS agent_token_hash;
@@ -0,0 +1,29 @@
+-- @up
+
+CREATE EXTENSION IF NOT EXISTS pgcrypto;
+
+-- values are encrypted with pgp_sym_encrypt under YUI_SECRETS_KEY, which never
+-- touches the database
+CREATE TABLE IF NOT EXISTS secrets (
+    id              uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    name            text NOT NULL UNIQUE CHECK (name ~ '^[A-Za-z_][A-Za-z0-9_]*$'),
+    description     text,
+    value_encrypted bytea NOT NULL,
+    inject_as       text NOT NULL DEFAULT 'env' CHECK (inject_as IN ('env', 'file')),
+    -- a job may use the secret when its kind or its cron's name is listed
+    allowed_kinds   text[] NOT NULL DEFAULT '{}',
+    allowed_crons   text[] NOT NULL DEFAULT '{}',
+    created_at      timestamptz NOT NULL DEFAULT now(),
+    updated_at      timestamptz NOT NULL DEFAULT now()
+);
+
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_trigger WHERE tgname = 'secrets_updated_at') THEN
+        CREATE TRIGGER secrets_updated_at BEFORE UPDATE ON secrets FOR EACH ROW EXECUTE FUNCTION touch_updated_at();
+    END IF;
+END $$;
+
+-- @down
+
+DROP TABLE IF EXISTS secrets;
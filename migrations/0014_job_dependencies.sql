@@ -0,0 +1,58 @@
+-- @up
+
+-- jobs that have to finish (status 'done') before this one may start
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS depends_on uuid[] NOT NULL DEFAULT '{}';
+
+CREATE INDEX IF NOT EXISTS idx_jobs_depends_on ON jobs USING gin (depends_on)
+    WHERE depends_on <> '{}';
+
+-- same as 0009, except a job only becomes startable once every job it depends
+-- on is done
+CREATE OR REPLACE VIEW job_queue AS
+WITH occupied AS (
+    SELECT chat_id, count(*) AS runs
+    FROM jobs
+    WHERE status = 'running' OR (status = 'pending' AND lease_until > now())
+    GROUP BY chat_id
+), ranked AS (
+    SELECT j.id, j.chat_id, j.kind, j.priority, j.created_at,
+           row_number() OVER (PARTITION BY j.chat_id ORDER BY j.priority DESC, j.created_at)
+               + COALESCE(o.runs, 0) AS chat_slot
+    FROM jobs j
+    LEFT JOIN occupied o USING (chat_id)
+    WHERE j.status = 'pending'
+      AND (j.lease_until IS NULL OR j.lease_until < now())
+      AND (j.retry_at IS NULL OR j.retry_at <= now())
+      AND NOT EXISTS (
+          SELECT 1 FROM jobs d
+          WHERE d.id = ANY(j.depends_on) AND d.status <> 'done'
+      )
+)
+SELECT id, chat_id, kind, priority, created_at, chat_slot,
+       row_number() OVER (ORDER BY priority DESC, chat_slot, created_at) AS position
+FROM ranked;
+
+-- @down
+
+CREATE OR REPLACE VIEW job_queue AS
+WITH occupied AS (
+    SELECT chat_id, count(*) AS runs
+    FROM jobs
+    WHERE status = 'running' OR (status = 'pending' AND lease_until > now())
+    GROUP BY chat_id
+), ranked AS (
+    SELECT j.id, j.chat_id, j.kind, j.priority, j.created_at,
+           row_number() OVER (PARTITION BY j.chat_id ORDER BY j.priority DESC, j.created_at)
+               + COALESCE(o.runs, 0) AS chat_slot
+    FROM jobs j
+    LEFT JOIN occupied o USING (chat_id)
+    WHERE j.status = 'pending'
+      AND (j.lease_until IS NULL OR j.lease_until < now())
+      AND (j.retry_at IS NULL OR j.retry_at <= now())
+)
+SELECT id, chat_id, kind, priority, created_at, chat_slot,
+       row_number() OVER (ORDER BY priority DESC, chat_slot, created_at) AS position
+FROM ranked;
+
+DROP INDEX IF EXISTS idx_jobs_depends_on;
+ALTER TABLE jobs DROP COLUMN IF EXISTS depends_on;
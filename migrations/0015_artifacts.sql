@@ -0,0 +1,26 @@
+-- @up
+
+-- every file a job produced, kept after delivery so it can be listed,
+-- downloaded from the dashboard and sent to the chat again
+CREATE TABLE IF NOT EXISTS artifacts (
+    id          uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    job_id      uuid NOT NULL REFERENCES jobs(id) ON DELETE CASCADE,
+    chat_id     text NOT NULL,
+    name        text NOT NULL,
+    kind        text NOT NULL DEFAULT 'document',
+    mime        text NOT NULL,
+    size_bytes  bigint NOT NULL,
+    sha256      text NOT NULL,
+    -- host path under the media dir
+    path        text NOT NULL,
+    trace_id    uuid,
+    created_at  timestamptz NOT NULL DEFAULT now(),
+    UNIQUE (job_id, path)
+);
+
+CREATE INDEX IF NOT EXISTS idx_artifacts_job ON artifacts (job_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_artifacts_chat ON artifacts (chat_id, created_at DESC);
+
+-- @down
+
+DROP TABLE IF EXISTS artifacts;
@@ -0,0 +1,87 @@
+-- @up
+
+-- persistent per-chat project directories that jobs opt into; a job's own
+-- workspace stays fresh and links the project in as ./project
+CREATE TABLE IF NOT EXISTS workspaces (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    chat_id text NOT NULL,
+    name text NOT NULL,
+    quota_mb integer NOT NULL,
+    size_bytes bigint NOT NULL DEFAULT 0,
+    -- the job using it; only counts while that job is running or holds a claim
+    locked_by uuid REFERENCES jobs(id) ON DELETE SET NULL,
+    reset_requested_at timestamptz,
+    last_used_at timestamptz,
+    created_at timestamptz NOT NULL DEFAULT now(),
+    UNIQUE (chat_id, name)
+);
+
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS workspace_id uuid
+    REFERENCES workspaces(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_jobs_workspace ON jobs (workspace_id)
+    WHERE workspace_id IS NOT NULL;
+
+-- same as 0014, except a job also waits while another job holds its
+-- workspace or a reset of it is pending
+CREATE OR REPLACE VIEW job_queue AS
+WITH occupied AS (
+    SELECT chat_id, count(*) AS runs
+    FROM jobs
+    WHERE status = 'running' OR (status = 'pending' AND lease_until > now())
+    GROUP BY chat_id
+), ranked AS (
+    SELECT j.id, j.chat_id, j.kind, j.priority, j.created_at,
+           row_number() OVER (PARTITION BY j.chat_id ORDER BY j.priority DESC, j.created_at)
+               + COALESCE(o.runs, 0) AS chat_slot
+    FROM jobs j
+    LEFT JOIN occupied o USING (chat_id)
+    WHERE j.status = 'pending'
+      AND (j.lease_until IS NULL OR j.lease_until < now())
+      AND (j.retry_at IS NULL OR j.retry_at <= now())
+      AND NOT EXISTS (
+          SELECT 1 FROM jobs d
+          WHERE d.id = ANY(j.depends_on) AND d.status <> 'done'
+      )
+      AND NOT EXISTS (
+          SELECT 1 FROM workspaces w
+          LEFT JOIN jobs l ON l.id = w.locked_by AND l.id <> j.id
+          WHERE w.id = j.workspace_id
+            AND (w.reset_requested_at IS NOT NULL
+                 OR l.status = 'running'
+                 OR (l.status = 'pending' AND l.lease_until > now()))
+      )
+)
+SELECT id, chat_id, kind, priority, created_at, chat_slot,
+       row_number() OVER (ORDER BY priority DESC, chat_slot, created_at) AS position
+FROM ranked;
+
+-- @down
+
+CREATE OR REPLACE VIEW job_queue AS
+WITH occupied AS (
+    SELECT chat_id, count(*) AS runs
+    FROM jobs
+    WHERE status = 'running' OR (status = 'pending' AND lease_until > now())
+    GROUP BY chat_id
+), ranked AS (
+    SELECT j.id, j.chat_id, j.kind, j.priority, j.created_at,
+           row_number() OVER (PARTITION BY j.chat_id ORDER BY j.priority DESC, j.created_at)
+               + COALESCE(o.runs, 0) AS chat_slot
+    FROM jobs j
+    LEFT JOIN occupied o USING (chat_id)
+    WHERE j.status = 'pending'
+      AND (j.lease_until IS NULL OR j.lease_until < now())
+      AND (j.retry_at IS NULL OR j.retry_at <= now())
+      AND NOT EXISTS (
+          SELECT 1 FROM jobs d
+          WHERE d.id = ANY(j.depends_on) AND d.status <> 'done'
+      )
+)
+SELECT id, chat_id, kind, priority, created_at, chat_slot,
+       row_number() OVER (ORDER BY priority DESC, chat_slot, created_at) AS position
+FROM ranked;
+
+DROP INDEX IF EXISTS idx_jobs_workspace;
+ALTER TABLE jobs DROP COLUMN IF EXISTS workspace_id;
+DROP TABLE IF EXISTS workspaces;
@@ -0,0 +1,20 @@
+-- @up
+
+-- network access for agent runs: a cron can pin its own policy and domains,
+-- otherwise the job kind's default applies
+ALTER TABLE crons ADD COLUMN IF NOT EXISTS egress_policy text
+    CHECK (egress_policy IN ('none', 'allowlist', 'full'));
+ALTER TABLE crons ADD COLUMN IF NOT EXISTS egress_allow text[] NOT NULL DEFAULT '{}';
+
+-- what a run actually got, written before it starts so the egress proxy can
+-- check its requests
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS egress_policy text
+    CHECK (egress_policy IN ('none', 'allowlist', 'full'));
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS egress_allow text[] NOT NULL DEFAULT '{}';
+
+-- @down
+
+ALTER TABLE jobs DROP COLUMN IF EXISTS egress_allow;
+ALTER TABLE jobs DROP COLUMN IF EXISTS egress_policy;
+ALTER TABLE crons DROP COLUMN IF EXISTS egress_allow;
+ALTER TABLE crons DROP COLUMN IF EXISTS egress_policy;
@@ -0,0 +1,31 @@
+-- @up
+
+-- jobs that need the user's go-ahead wait in awaiting_approval between
+-- enrichment and the queue
+ALTER TABLE jobs DROP CONSTRAINT IF EXISTS jobs_status_check;
+ALTER TABLE jobs ADD CONSTRAINT jobs_status_check CHECK (status IN (
+    'draft', 'pending', 'awaiting_approval', 'running', 'paused', 'done', 'failed', 'cancelled'
+));
+
+-- set by triage; the rule set is applied again when the job is enriched
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS requires_approval boolean NOT NULL DEFAULT false;
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS approval_reason text;
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS approval_expires_at timestamptz;
+
+CREATE INDEX IF NOT EXISTS idx_jobs_awaiting_approval ON jobs (approval_expires_at)
+    WHERE status = 'awaiting_approval';
+
+-- @down
+
+UPDATE jobs SET status = 'cancelled', cancel_reason = 'approval gate removed', finished_at = now()
+WHERE status = 'awaiting_approval';
+
+DROP INDEX IF EXISTS idx_jobs_awaiting_approval;
+ALTER TABLE jobs DROP COLUMN IF EXISTS approval_expires_at;
+ALTER TABLE jobs DROP COLUMN IF EXISTS approval_reason;
+ALTER TABLE jobs DROP COLUMN IF EXISTS requires_approval;
+
+ALTER TABLE jobs DROP CONSTRAINT IF EXISTS jobs_status_check;
+ALTER TABLE jobs ADD CONSTRAINT jobs_status_check CHECK (status IN (
+    'draft', 'pending', 'running', 'paused', 'done', 'failed', 'cancelled'
+));
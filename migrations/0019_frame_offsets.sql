@@ -0,0 +1,11 @@
+-- @up
+
+-- how much of the current run's output is stored, so a reattached run can
+-- replay its container log from the start and skip what was already handled
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS stdout_frames_seen bigint NOT NULL DEFAULT 0;
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS stderr_lines_seen bigint NOT NULL DEFAULT 0;
+
+-- @down
+
+ALTER TABLE jobs DROP COLUMN IF EXISTS stderr_lines_seen;
+ALTER TABLE jobs DROP COLUMN IF EXISTS stdout_frames_seen;
@@ -0,0 +1,10 @@
+-- @up
+
+-- who set a cron up: the user agreed to a cron of their own when creating it,
+-- while one an agent created gets no such pass at the approval rules
+ALTER TABLE crons ADD COLUMN IF NOT EXISTS created_by text NOT NULL DEFAULT 'user'
+    CHECK (created_by IN ('user', 'agent'));
+
+-- @down
+
+ALTER TABLE crons DROP COLUMN IF EXISTS created_by;
@@ -0,0 +1,18 @@
+-- @up
+
+-- values are sealed by the runtime with AES-256-GCM before they are stored,
+-- so YUI_SECRETS_KEY is never sent to the database as a query parameter;
+-- rows still under pgp_sym_encrypt are resealed once when the runtime starts
+ALTER TABLE secrets ADD COLUMN IF NOT EXISTS value_sealed bytea;
+ALTER TABLE secrets ALTER COLUMN value_encrypted DROP NOT NULL;
+ALTER TABLE secrets ADD CONSTRAINT secrets_value_present
+    CHECK (value_sealed IS NOT NULL OR value_encrypted IS NOT NULL);
+
+-- @down
+
+-- a sealed value cannot be turned back into a pgcrypto one inside the
+-- database, so those secrets have to be set again
+DELETE FROM secrets WHERE value_encrypted IS NULL;
+ALTER TABLE secrets DROP CONSTRAINT IF EXISTS secrets_value_present;
+ALTER TABLE secrets ALTER COLUMN value_encrypted SET NOT NULL;
+ALTER TABLE secrets DROP COLUMN IF EXISTS value_sealed;
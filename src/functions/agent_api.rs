@@ -0,0 +1,531 @@
+use crate::functions::artifacts::record_artifacts;
+use crate::functions::clock::compute_next_run_at;
+use crate::functions::runtime::{
+    insert_outbox_text, insert_outbox_with_attachments, job_secrets, record_progress,
+};
+use crate::functions::triage::parse_notify_policy;
+use crate::services::agent_tools::resolve_in_workspace;
+use crate::services::{Redactor, copy_agent_file, project_path};
+use axum::{
+    Json,
+    body::Body,
+    http::{HeaderMap, Method, Request, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use uuid::Uuid;
+
+/// How long `ask` and `answer` hold the request open before telling the agent
+/// to ask again.
+const ANSWER_WAIT: Duration = Duration::from_secs(25);
+const ANSWER_POLL: Duration = Duration::from_secs(1);
+const MAX_BODY_BYTES: usize = 64 * 1024;
+const MAX_HISTORY: i64 = 100;
+
+static DB: OnceLock<PgPool> = OnceLock::new();
+
+/// Starts the API on a listener of its own, apart from the dashboard, so a
+/// container gets a route to these endpoints and nothing else. For Docker runs
+/// `YUI_AGENT_API_ADDR` is the bridge address; called by the runtime loop.
+pub(crate) async fn spawn_listener(db: &PgPool) {
+    let _ = DB.set(db.clone());
+    let addr = std::env::var("YUI_AGENT_API_ADDR").unwrap_or_else(|_| "127.0.0.1:8090".to_string());
+    let listener = match TcpListener::bind(&addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            tracing::error!(addr = %addr, error = %e, "agent api failed to bind");
+            return;
+        }
+    };
+    tracing::info!(addr = %addr, "agent api listening");
+    let app = axum::Router::new().fallback(serve);
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, app).await {
+            tracing::error!(error = %e, "agent api listener stopped");
+        }
+    });
+}
+
+/// Issues the token for a job's next run, replacing any earlier one. Only its
+/// hash is stored.
+pub async fn issue_token(db: &PgPool, job_id: Uuid) -> sqlx::Result<String> {
+    let token = format!(
+        "{}{}",
+        Uuid::new_v4().as_simple(),
+        Uuid::new_v4().as_simple()
+    );
+    sqlx::query!(
+        "UPDATE jobs SET agent_token_hash = sha256(convert_to($2, 'UTF8')) WHERE id = $1",
+        job_id,
+        token
+    )
+    .execute(db)
+    .await?;
+    Ok(token)
+}
+
+struct AgentJob {
+    id: Uuid,
+    chat_id: String,
+    trace_id: Uuid,
+    workspace_id: Option<Uuid>,
+}
+
+type ApiResult = Result<serde_json::Value, (StatusCode, String)>;
+
+fn internal(e: impl std::fmt::Display) -> (StatusCode, String) {
+    tracing::warn!(error = %e, "agent api request failed");
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "internal error".to_string(),
+    )
+}
+
+fn bad_request(message: impl Into<String>) -> (StatusCode, String) {
+    (StatusCode::BAD_REQUEST, message.into())
+}
+
+/// Entry point for `/agent/v1/*` on the API's own listener.
+pub fn serve(req: Request<Body>) -> Pin<Box<dyn Future<Output = Response> + Send>> {
+    Box::pin(async move {
+        match handle(req).await {
+            Ok(value) => Json(value).into_response(),
+            Err((status, message)) => {
+                (status, Json(serde_json::json!({ "error": message }))).into_response()
+            }
+        }
+    })
+}
+
+async fn handle(req: Request<Body>) -> ApiResult {
+    let Some(db) = DB.get() else {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "agent api is not running on this instance".to_string(),
+        ));
+    };
+    let job = authenticate(db, req.headers()).await?.ok_or((
+        StatusCode::UNAUTHORIZED,
+        "invalid or expired token".to_string(),
+    ))?;
+
+    let (parts, body) = req.into_parts();
+    let body = axum::body::to_bytes(body, MAX_BODY_BYTES)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "request body too large".to_string(),
+            )
+        })?;
+    let path = parts.uri.path().trim_start_matches("/agent/v1");
+
+    match (parts.method, path) {
+        (Method::POST, "/progress") => progress(db, &job, parse(&body)?).await,
+        (Method::POST, "/files") => send_file(db, &job, parse(&body)?).await,
+        (Method::POST, "/ask") => ask(db, &job, parse(&body)?).await,
+        (Method::GET, "/answer") => wait_for_answer(db, &job).await,
+        (Method::GET, "/history") => history(db, &job, parts.uri.query()).await,
+        (Method::POST, "/jobs") => create_job(db, &job, parse(&body)?).await,
+        (Method::POST, "/crons") => create_cron(db, &job, parse(&body)?).await,
+        _ => Err((StatusCode::NOT_FOUND, format!("no such endpoint {path}"))),
+    }
+}
+
+/// Tokens only work while their job is starting or running.
+async fn authenticate(
+    db: &PgPool,
+    headers: &HeaderMap,
+) -> Result<Option<AgentJob>, (StatusCode, String)> {
+    let Some(token) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+    else {
+        return Ok(None);
+    };
+    let row = sqlx::query!(
+        r#"
+        SELECT id, chat_id, trace_id, workspace_id FROM jobs
+        WHERE agent_token_hash = sha256(convert_to($1, 'UTF8'))
+          AND status IN ('pending', 'running')
+        "#,
+        token.trim()
+    )
+    .fetch_optional(db)
+    .await
+    .map_err(internal)?;
+    Ok(row.map(|r| AgentJob {
+        id: r.id,
+        chat_id: r.chat_id,
+        trace_id: r.trace_id.unwrap_or_else(Uuid::new_v4),
+        workspace_id: r.workspace_id,
+    }))
+}
+
+fn parse<T: serde::de::DeserializeOwned>(body: &[u8]) -> Result<T, (StatusCode, String)> {
+    serde_json::from_slice(body).map_err(|e| bad_request(format!("invalid body: {e}")))
+}
+
+async fn insert_event(
+    db: &PgPool,
+    trace_id: Uuid,
+    action: &str,
+    payload: serde_json::Value,
+) -> ApiResult {
+    sqlx::query!(
+        r#"
+        INSERT INTO events (trace_id, source, action, payload)
+        VALUES ($1, 'agent_api', $2, $3)
+        "#,
+        trace_id,
+        action,
+        payload
+    )
+    .execute(db)
+    .await
+    .map_err(internal)?;
+    Ok(serde_json::json!({ "ok": true }))
+}
+
+#[derive(Deserialize)]
+struct ProgressBody {
+    message: String,
+}
+
+/// Same redaction the runtime applies to the run's own output, since these
+/// calls reach the chat without passing through it.
+async fn redactor(db: &PgPool, job: &AgentJob) -> Result<Redactor, (StatusCode, String)> {
+    Ok(Redactor::new(
+        &job_secrets(db, job.id).await.map_err(internal)?,
+    ))
+}
+
+async fn progress(db: &PgPool, job: &AgentJob, body: ProgressBody) -> ApiResult {
+    let message = redactor(db, job).await?.redact(body.message.trim());
+    record_progress(db, job.id, &message)
+        .await
+        .map_err(internal)?;
+    Ok(serde_json::json!({ "ok": true }))
+}
+
+#[derive(Deserialize)]
+struct FileBody {
+    /// Relative to the workspace, or under `/workspace`.
+    path: String,
+    #[serde(default)]
+    caption: Option<String>,
+    #[serde(default)]
+    mime: Option<String>,
+}
+
+async fn send_file(db: &PgPool, job: &AgentJob, body: FileBody) -> ApiResult {
+    let workspace_dir =
+        std::env::var("YUI_WORKSPACE_DIR").unwrap_or_else(|_| "storage/workspaces".to_string());
+    let media_dir = std::env::var("YUI_MEDIA_DIR").unwrap_or_else(|_| "storage/media".to_string());
+    let workspace = PathBuf::from(workspace_dir).join(job.id.to_string());
+    let project = job.workspace_id.map(project_path);
+    let source = resolve_in_workspace(&workspace, project.as_deref(), &body.path)
+        .ok_or_else(|| bad_request(format!("{} is outside the workspace", body.path)))?;
+    let name = source
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "file".to_string());
+
+    tokio::fs::create_dir_all(&media_dir)
+        .await
+        .map_err(internal)?;
+    let dest = format!("{media_dir}/{}_{name}", Uuid::new_v4().as_simple());
+    // the path is checked on disk too, a symlink could still point anywhere
+    let roots: Vec<&Path> = std::iter::once(workspace.as_path())
+        .chain(project.as_deref())
+        .collect();
+    copy_agent_file(&roots, &source, Path::new(&dest))
+        .await
+        .map_err(|e| bad_request(format!("cannot send {}: {e}", body.path)))?;
+
+    let mime = body
+        .mime
+        .unwrap_or_else(|| "application/octet-stream".to_string());
+    let kind = match mime.split('/').next() {
+        Some(kind @ ("image" | "video" | "audio")) => kind.to_string(),
+        _ => "document".to_string(),
+    };
+    let caption = redactor(db, job)
+        .await?
+        .redact(body.caption.as_deref().unwrap_or_default());
+    let attachments = vec![serde_json::json!({
+        "type": kind,
+        "path": dest,
+        "name": name,
+        "mime": mime,
+    })];
+    record_artifacts(db, job.id, &attachments)
+        .await
+        .map_err(internal)?;
+    insert_outbox_with_attachments(
+        db,
+        &job.chat_id,
+        &caption,
+        attachments,
+        job.id,
+        job.trace_id,
+    )
+    .await
+    .map_err(internal)?;
+    insert_event(
+        db,
+        job.trace_id,
+        "agent_file_sent",
+        serde_json::json!({ "job_id": job.id, "name": name }),
+    )
+    .await
+}
+
+#[derive(Deserialize)]
+struct AskBody {
+    question: String,
+}
+
+/// Sends the question and waits briefly for the answer. The job stays running;
+/// triage routes the user's reply into `agent_answer` instead of resuming it.
+async fn ask(db: &PgPool, job: &AgentJob, body: AskBody) -> ApiResult {
+    let question = redactor(db, job).await?.redact(body.question.trim());
+    if question.is_empty() {
+        return Err(bad_request("question is empty"));
+    }
+    sqlx::query!(
+        r#"
+        UPDATE jobs SET question_pending = $2, agent_answer = NULL
+        WHERE id = $1 AND status = 'running'
+        "#,
+        job.id,
+        question
+    )
+    .execute(db)
+    .await
+    .map_err(internal)?;
+    insert_outbox_text(
+        db,
+        &job.chat_id,
+        &format!("question: {question}"),
+        job.id,
+        job.trace_id,
+    )
+    .await
+    .map_err(internal)?;
+    insert_event(
+        db,
+        job.trace_id,
+        "agent_question",
+        serde_json::json!({ "job_id": job.id, "question": question }),
+    )
+    .await?;
+
+    wait_for_answer(db, job).await
+}
+
+/// Hands the answer over once; `waiting` means ask `/answer` again.
+async fn wait_for_answer(db: &PgPool, job: &AgentJob) -> ApiResult {
+    let deadline = tokio::time::Instant::now() + ANSWER_WAIT;
+    loop {
+        let answer = sqlx::query_scalar!(
+            r#"
+            UPDATE jobs SET agent_answer = NULL
+            FROM (
+                SELECT id, agent_answer FROM jobs
+                WHERE id = $1 AND agent_answer IS NOT NULL
+                FOR UPDATE
+            ) prev
+            WHERE jobs.id = prev.id
+            RETURNING prev.agent_answer as "answer!"
+            "#,
+            job.id
+        )
+        .fetch_optional(db)
+        .await
+        .map_err(internal)?;
+
+        if let Some(answer) = answer {
+            return Ok(serde_json::json!({ "status": "answered", "answer": answer }));
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return Ok(serde_json::json!({ "status": "waiting" }));
+        }
+        tokio::time::sleep(ANSWER_POLL).await;
+    }
+}
+
+fn history_limit(query: Option<&str>) -> i64 {
+    query
+        .into_iter()
+        .flat_map(|q| q.split('&'))
+        .find_map(|pair| pair.strip_prefix("limit="))
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(20)
+        .clamp(1, MAX_HISTORY)
+}
+
+async fn history(db: &PgPool, job: &AgentJob, query: Option<&str>) -> ApiResult {
+    let limit = history_limit(query);
+
+    let mut rows = sqlx::query!(
+        r#"
+        SELECT direction, content, created_at FROM messages
+        WHERE platform_chat_id = $1 AND is_deleted = false AND content IS NOT NULL
+        ORDER BY created_at DESC
+        LIMIT $2
+        "#,
+        job.chat_id,
+        limit
+    )
+    .fetch_all(db)
+    .await
+    .map_err(internal)?;
+    rows.reverse();
+
+    let messages: Vec<serde_json::Value> = rows
+        .into_iter()
+        .map(|r| {
+            serde_json::json!({
+                "direction": r.direction,
+                "content": r.content,
+                "created_at": r.created_at,
+            })
+        })
+        .collect();
+    Ok(serde_json::json!({ "messages": messages }))
+}
+
+#[derive(Deserialize)]
+struct JobBody {
+    prompt: String,
+    #[serde(default)]
+    profile: Option<String>,
+    /// Jobs of the same chat that must be done first, e.g. the caller itself.
+    #[serde(default)]
+    depends_on: Vec<Uuid>,
+}
+
+/// Follow-up jobs enter as drafts, so context enriches them like any other,
+/// and share the caller's persistent workspace.
+async fn create_job(db: &PgPool, job: &AgentJob, body: JobBody) -> ApiResult {
+    if body.prompt.trim().is_empty() {
+        return Err(bad_request("prompt is empty"));
+    }
+    if !body.depends_on.is_empty() {
+        let known = sqlx::query_scalar!(
+            r#"SELECT count(*) as "count!" FROM jobs WHERE id = ANY($1) AND chat_id = $2"#,
+            &body.depends_on,
+            job.chat_id
+        )
+        .fetch_one(db)
+        .await
+        .map_err(internal)?;
+        if known != body.depends_on.len() as i64 {
+            return Err(bad_request("depends_on lists jobs outside this chat"));
+        }
+    }
+    let job_id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO jobs (id, kind, chat_id, status, prompt, source_ids, trace_id, resource_profile,
+                          depends_on, workspace_id)
+        SELECT $1, 'action', $2, 'draft', $3, '{}', $4, $5, $6, workspace_id
+        FROM jobs WHERE id = $7
+        "#,
+        job_id,
+        job.chat_id,
+        body.prompt,
+        job.trace_id,
+        body.profile,
+        &body.depends_on,
+        job.id
+    )
+    .execute(db)
+    .await
+    .map_err(internal)?;
+    insert_event(
+        db,
+        job.trace_id,
+        "job_created",
+        serde_json::json!({
+            "job_id": job_id,
+            "parent_job_id": job.id,
+            "chat_id": job.chat_id,
+            "depends_on": body.depends_on,
+        }),
+    )
+    .await?;
+    Ok(serde_json::json!({ "job_id": job_id }))
+}
+
+#[derive(Deserialize)]
+struct CronBody {
+    name: String,
+    schedule: String,
+    prompt: String,
+    #[serde(default)]
+    notify: Option<String>,
+}
+
+async fn create_cron(db: &PgPool, job: &AgentJob, body: CronBody) -> ApiResult {
+    let timezone = "UTC";
+    let next_run_at = compute_next_run_at(&body.schedule, timezone, chrono::Utc::now())
+        .map_err(|e| bad_request(format!("invalid schedule `{}`: {e}", body.schedule)))?;
+    let notify_policy = parse_notify_policy(body.notify.as_deref().unwrap_or_default());
+
+    let cron_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO crons (name, schedule, timezone, chat_id, prompt, next_run_at, notify_policy,
+                           created_by)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, 'agent')
+        RETURNING id
+        "#,
+        body.name,
+        body.schedule,
+        timezone,
+        job.chat_id,
+        body.prompt,
+        next_run_at,
+        notify_policy as _
+    )
+    .fetch_one(db)
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(ref db_err) if db_err.is_unique_violation() => (
+            StatusCode::CONFLICT,
+            format!("a cron named `{}` already exists", body.name),
+        ),
+        e => internal(e),
+    })?;
+    insert_event(
+        db,
+        job.trace_id,
+        "cron_created",
+        serde_json::json!({ "cron_id": cron_id, "name": body.name, "job_id": job.id }),
+    )
+    .await?;
+    Ok(serde_json::json!({ "cron_id": cron_id, "next_run_at": next_run_at }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history_limit_defaults_and_clamps() {
+        assert_eq!(history_limit(None), 20);
+        assert_eq!(history_limit(Some("limit=5")), 5);
+        assert_eq!(history_limit(Some("x=1&limit=500")), MAX_HISTORY);
+        assert_eq!(history_limit(Some("limit=0")), 1);
+        assert_eq!(history_limit(Some("limit=abc")), 20);
+    }
+}
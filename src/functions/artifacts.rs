@@ -0,0 +1,159 @@
+use crate::services::open_confined;
+use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::OnceLock;
+use tokio::io::AsyncReadExt;
+use tokio_util::io::ReaderStream;
+use uuid::Uuid;
+
+static DB: OnceLock<PgPool> = OnceLock::new();
+
+/// Makes downloads servable from this process; called by the runtime loop.
+pub fn install(db: &PgPool) {
+    let _ = DB.set(db.clone());
+}
+
+/// Artifacts are the copies runs leave in the media dir. A recorded path
+/// anywhere else, or one that is a symlink, is neither hashed nor served.
+async fn open_artifact(path: &str) -> std::io::Result<tokio::fs::File> {
+    let media_dir = std::env::var("YUI_MEDIA_DIR").unwrap_or_else(|_| "storage/media".to_string());
+    open_confined(&[Path::new(&media_dir)], Path::new(path)).await
+}
+
+/// Size and hex sha256 of a file, read in chunks so large videos do not have
+/// to fit in memory.
+async fn file_digest(path: &str) -> std::io::Result<(i64, String)> {
+    let mut file = open_artifact(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut size = 0i64;
+    loop {
+        let n = file.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        size += n as i64;
+    }
+    Ok((size, format!("{:x}", hasher.finalize())))
+}
+
+/// Records the files a job handed to the outbox, in the outbox attachment
+/// format (`type`, `path`, `name`, `mime`). Files that are already recorded
+/// for the job, no longer exist or are not in the media dir are skipped.
+pub(crate) async fn record_artifacts(
+    db: &PgPool,
+    job_id: Uuid,
+    attachments: &[serde_json::Value],
+) -> sqlx::Result<()> {
+    for att in attachments {
+        let Some(path) = att["path"].as_str() else {
+            continue;
+        };
+        let (size, sha256) = match file_digest(path).await {
+            Ok(digest) => digest,
+            Err(e) => {
+                tracing::warn!(job_id = %job_id, path, error = %e, "artifact file not recorded");
+                continue;
+            }
+        };
+        let name = att["name"].as_str().map(str::to_string).unwrap_or_else(|| {
+            std::path::Path::new(path)
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "file".to_string())
+        });
+
+        sqlx::query!(
+            r#"
+            INSERT INTO artifacts (job_id, chat_id, name, kind, mime, size_bytes, sha256, path,
+                                   trace_id)
+            SELECT id, chat_id, $2, $3, $4, $5, $6, $7, trace_id FROM jobs WHERE id = $1
+            ON CONFLICT (job_id, path) DO NOTHING
+            "#,
+            job_id,
+            name,
+            att["type"].as_str().unwrap_or("document"),
+            att["mime"].as_str().unwrap_or("application/octet-stream"),
+            size,
+            sha256,
+            path
+        )
+        .execute(db)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Entry point for `GET /artifacts/{id}`, the dashboard's download link.
+pub fn serve(req: Request<Body>) -> Pin<Box<dyn Future<Output = Response> + Send>> {
+    Box::pin(async move {
+        match download(req).await {
+            Ok(response) => response,
+            Err((status, message)) => (status, message).into_response(),
+        }
+    })
+}
+
+async fn download(req: Request<Body>) -> Result<Response, (StatusCode, String)> {
+    if req.method() != Method::GET {
+        return Err((StatusCode::METHOD_NOT_ALLOWED, "use GET".to_string()));
+    }
+    let Some(db) = DB.get() else {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "artifacts are not served on this instance".to_string(),
+        ));
+    };
+    let id = req
+        .uri()
+        .path()
+        .trim_start_matches("/artifacts/")
+        .parse::<Uuid>()
+        .map_err(|_| (StatusCode::BAD_REQUEST, "invalid artifact id".to_string()))?;
+
+    let artifact = sqlx::query!("SELECT name, path FROM artifacts WHERE id = $1", id)
+        .fetch_optional(db)
+        .await
+        .map_err(|e| {
+            tracing::warn!(error = %e, "artifact lookup failed");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "internal error".to_string(),
+            )
+        })?
+        .ok_or((StatusCode::NOT_FOUND, "no such artifact".to_string()))?;
+
+    let file = open_artifact(&artifact.path)
+        .await
+        .map_err(|_| (StatusCode::GONE, "artifact file is gone".to_string()))?;
+    let size = file
+        .metadata()
+        .await
+        .map_err(|_| (StatusCode::GONE, "artifact file is gone".to_string()))?
+        .len();
+    let disposition = format!(
+        "attachment; filename=\"{}\"",
+        artifact.name.replace(['"', '\\', '\r', '\n'], "_")
+    );
+    // the recorded mime comes from the agent, so the browser is never told to
+    // render the file, only to save it
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+            (header::CONTENT_LENGTH, size.to_string()),
+        ],
+        Body::from_stream(ReaderStream::new(file)),
+    )
+        .into_response())
+}
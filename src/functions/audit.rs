@@ -1,3 +1,7 @@
+use crate::functions::claim::instance_id;
+use crate::functions::heartbeat::Heartbeat;
+use crate::functions::wakeup::{MESSAGES_CHANNEL, Wakeup};
+use crate::services::metrics;
 use forge::prelude::*;
 use sqlx::PgPool;
 use uuid::Uuid;
@@ -19,14 +23,24 @@ pub async fn audit_tick(db: &PgPool) -> Result<u32> {
     let changed = sqlx::query_as!(
         AuditableMessage,
         r#"
+        WITH claimed AS (
+            UPDATE messages SET claimed_by = $1, lease_until = now() + interval '1 minute'
+            WHERE id IN (
+                SELECT id FROM messages
+                WHERE (audit_processed_version < content_version
+                       OR (is_deleted = true AND audit_processed_at IS NULL))
+                  AND (lease_until IS NULL OR lease_until < now())
+                ORDER BY updated_at
+                LIMIT 20
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, platform_chat_id, content, is_deleted, content_version, updated_at
+        )
         SELECT id, platform_chat_id, content, is_deleted, content_version
-        FROM messages
-        WHERE audit_processed_version < content_version
-           OR (is_deleted = true AND audit_processed_at IS NULL)
+        FROM claimed
         ORDER BY updated_at
-        LIMIT 20
-        FOR UPDATE SKIP LOCKED
-        "#
+        "#,
+        instance_id()
     )
     .fetch_all(db)
     .await?;
@@ -47,7 +61,7 @@ pub async fn audit_tick(db: &PgPool) -> Result<u32> {
             SELECT id, chat_id
             FROM jobs
             WHERE $1 = ANY(source_ids)
-              AND status IN ('draft', 'pending', 'running', 'paused')
+              AND status IN ('draft', 'pending', 'awaiting_approval', 'running', 'paused')
             "#,
             msg.id
         )
@@ -64,7 +78,7 @@ pub async fn audit_tick(db: &PgPool) -> Result<u32> {
             sqlx::query!(
                 r#"
                 UPDATE jobs SET status = 'cancelled', cancel_reason = $2, finished_at = now()
-                WHERE id = $1 AND status IN ('draft', 'pending', 'running', 'paused')
+                WHERE id = $1 AND status IN ('draft', 'pending', 'awaiting_approval', 'running', 'paused')
                 "#,
                 job.id,
                 reason
@@ -128,7 +142,11 @@ pub async fn audit_tick(db: &PgPool) -> Result<u32> {
         }
 
         sqlx::query!(
-            "UPDATE messages SET audit_processed_at = now(), audit_processed_version = $2 WHERE id = $1",
+            r#"
+            UPDATE messages SET audit_processed_at = now(), audit_processed_version = $2,
+                                claimed_by = NULL, lease_until = NULL
+            WHERE id = $1
+            "#,
             msg.id,
             msg.content_version
         )
@@ -145,12 +163,16 @@ pub async fn audit_tick(db: &PgPool) -> Result<u32> {
 #[forge::daemon]
 pub async fn audit(ctx: &DaemonContext) -> Result<()> {
     let poll_ms: u64 = ctx.env_parse("YUI_LOOP_POLL_MS_AUDIT").unwrap_or(500);
+    let mut wakeup = Wakeup::listen(ctx.db(), &[MESSAGES_CHANNEL]).await;
+    let mut heartbeat = Heartbeat::new("audit");
 
     loop {
         tokio::select! {
             _ = ctx.shutdown_signal() => break,
-            _ = tokio::time::sleep(std::time::Duration::from_millis(poll_ms)) => {
-                match audit_tick(ctx.db()).await {
+            _ = wakeup.wait(std::time::Duration::from_millis(poll_ms)) => {
+                let result = metrics::observe_tick("audit", audit_tick(ctx.db()).await);
+                heartbeat.record(ctx.db(), &result).await;
+                match result {
                     Ok(n) if n > 0 => tracing::info!(processed = n, "audit tick"),
                     Err(e) => tracing::error!(error = %e, "audit tick failed"),
                     _ => {}
@@ -180,6 +202,8 @@ mod tests {
                 content_version int NOT NULL DEFAULT 1,
                 audit_processed_version int NOT NULL DEFAULT 1,
                 audit_processed_at timestamptz,
+                claimed_by text,
+                lease_until timestamptz,
                 created_at timestamptz NOT NULL DEFAULT now(),
                 updated_at timestamptz NOT NULL DEFAULT now()
             );
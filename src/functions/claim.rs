@@ -0,0 +1,20 @@
+use std::sync::LazyLock;
+use uuid::Uuid;
+
+// Loops claim rows by stamping `claimed_by` and `lease_until` in a single
+// UPDATE ... WHERE id IN (SELECT ... FOR UPDATE SKIP LOCKED) statement, so the
+// claim outlives the SELECT and two replicas of a loop never work the same row.
+// A replica that dies mid-tick simply lets its leases expire.
+
+static INSTANCE_ID: LazyLock<String> = LazyLock::new(|| {
+    std::env::var("YUI_INSTANCE_ID").unwrap_or_else(|_| {
+        let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "yui".to_string());
+        let suffix = Uuid::new_v4().as_simple().to_string();
+        format!("{host}:{}:{}", std::process::id(), &suffix[..8])
+    })
+});
+
+/// Identifies this process in `claimed_by` columns.
+pub fn instance_id() -> &'static str {
+    &INSTANCE_ID
+}
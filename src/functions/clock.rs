@@ -1,3 +1,8 @@
+use crate::functions::claim::instance_id;
+use crate::functions::heartbeat::Heartbeat;
+use crate::functions::wakeup::{CRONS_CHANNEL, Wakeup};
+use crate::schema::CronNotifyPolicy;
+use crate::services::metrics;
 use forge::prelude::*;
 use sqlx::PgPool;
 use std::str::FromStr;
@@ -10,9 +15,45 @@ struct DueCron {
     schedule: String,
     prompt: String,
     timezone: String,
+    notify_policy: CronNotifyPolicy,
     next_run_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
+/// Marker a `noteworthy` cron job emits when the run found nothing worth telling the user.
+pub const NOTHING_NOTEWORTHY_MARKER: &str = "NOTHING_NOTEWORTHY";
+
+/// Cron jobs queue behind anything the user asked for directly.
+const SCHEDULED_JOB_PRIORITY: i32 = -10;
+
+const NOTEWORTHY_INSTRUCTION: &str = "Only report back if something noteworthy happened. If nothing noteworthy happened on this run, reply with exactly NOTHING_NOTEWORTHY and nothing else.";
+
+fn normalize_output(output: &str) -> String {
+    output.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Decides whether a finished cron run should reach the user under the cron's notify policy.
+/// `previous_output` is the output of the last completed run of the same cron, if any.
+pub fn should_notify(
+    policy: &CronNotifyPolicy,
+    previous_output: Option<&str>,
+    output: &str,
+) -> bool {
+    match policy {
+        CronNotifyPolicy::OnChange => previous_output
+            .is_none_or(|previous| normalize_output(previous) != normalize_output(output)),
+        CronNotifyPolicy::Noteworthy => output.trim() != NOTHING_NOTEWORTHY_MARKER,
+        CronNotifyPolicy::Always => true,
+    }
+}
+
+fn job_prompt_for_cron(prompt: &str, notify_policy: &CronNotifyPolicy) -> String {
+    if matches!(notify_policy, CronNotifyPolicy::Noteworthy) {
+        format!("{prompt}\n\n{NOTEWORTHY_INSTRUCTION}")
+    } else {
+        prompt.to_string()
+    }
+}
+
 // the `cron` crate requires 6-field (second-granularity) expressions,
 // so we prepend "0" to standard 5-field minute-granularity inputs
 fn normalize_schedule(schedule: &str) -> String {
@@ -51,13 +92,24 @@ pub async fn clock_tick(db: &PgPool) -> Result<u32> {
     let due = sqlx::query_as!(
         DueCron,
         r#"
-        SELECT id, name, chat_id, schedule, prompt, timezone, next_run_at
-        FROM crons
-        WHERE enabled = true AND (next_run_at IS NULL OR next_run_at <= now())
+        WITH claimed AS (
+            UPDATE crons SET claimed_by = $1, lease_until = now() + interval '30 seconds'
+            WHERE id IN (
+                SELECT id FROM crons
+                WHERE enabled = true AND (next_run_at IS NULL OR next_run_at <= now())
+                  AND (lease_until IS NULL OR lease_until < now())
+                ORDER BY next_run_at NULLS FIRST
+                LIMIT 20
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, name, chat_id, schedule, prompt, timezone, notify_policy, next_run_at
+        )
+        SELECT id, name, chat_id, schedule, prompt, timezone,
+               notify_policy as "notify_policy: CronNotifyPolicy", next_run_at
+        FROM claimed
         ORDER BY next_run_at NULLS FIRST
-        LIMIT 20
-        FOR UPDATE SKIP LOCKED
-        "#
+        "#,
+        instance_id()
     )
     .fetch_all(db)
     .await?;
@@ -95,7 +147,10 @@ pub async fn clock_tick(db: &PgPool) -> Result<u32> {
                     "clock: auto-stopping cron (limit reached)"
                 );
                 let mut tx = db.begin().await?;
-                sqlx::query!("UPDATE crons SET enabled = false WHERE id = $1", cron.id)
+                sqlx::query!(
+                    "UPDATE crons SET enabled = false, claimed_by = NULL, lease_until = NULL WHERE id = $1",
+                    cron.id
+                )
                     .execute(&mut *tx)
                     .await?;
                 sqlx::query!(
@@ -124,7 +179,7 @@ pub async fn clock_tick(db: &PgPool) -> Result<u32> {
                 let mut tx = db.begin().await?;
                 sqlx::query!(
                     r#"
-                    UPDATE crons SET enabled = false
+                    UPDATE crons SET enabled = false, claimed_by = NULL, lease_until = NULL
                     WHERE id = $1
                     "#,
                     cron.id
@@ -159,7 +214,7 @@ pub async fn clock_tick(db: &PgPool) -> Result<u32> {
             let mut tx = db.begin().await?;
             sqlx::query!(
                 r#"
-                UPDATE crons SET next_run_at = $2
+                UPDATE crons SET next_run_at = $2, claimed_by = NULL, lease_until = NULL
                 WHERE id = $1
                 "#,
                 cron.id,
@@ -197,24 +252,28 @@ pub async fn clock_tick(db: &PgPool) -> Result<u32> {
             "clock: firing cron, creating job"
         );
 
+        let job_prompt = job_prompt_for_cron(&cron.prompt, &cron.notify_policy);
         let mut tx = db.begin().await?;
 
         sqlx::query!(
             r#"
-            INSERT INTO jobs (id, kind, chat_id, status, prompt, trace_id)
-            VALUES ($1, 'schedule', $2, 'draft', $3, $4)
+            INSERT INTO jobs (id, kind, chat_id, status, prompt, cron_id, trace_id, priority)
+            VALUES ($1, 'schedule', $2, 'draft', $3, $4, $5, $6)
             "#,
             job_id,
             cron.chat_id,
-            cron.prompt,
-            trace_id
+            job_prompt,
+            cron.id,
+            trace_id,
+            SCHEDULED_JOB_PRIORITY
         )
         .execute(&mut *tx)
         .await?;
 
         sqlx::query!(
             r#"
-            UPDATE crons SET last_run_at = now(), next_run_at = $2, last_job_id = $3
+            UPDATE crons SET last_run_at = now(), next_run_at = $2, last_job_id = $3,
+                             claimed_by = NULL, lease_until = NULL
             WHERE id = $1
             "#,
             cron.id,
@@ -257,12 +316,16 @@ fn parse_auto_stop_limit(prompt: &str) -> Option<i64> {
 #[forge::daemon]
 pub async fn clock(ctx: &DaemonContext) -> Result<()> {
     let poll_ms: u64 = ctx.env_parse("YUI_LOOP_POLL_MS_CLOCK").unwrap_or(1000);
+    let mut wakeup = Wakeup::listen(ctx.db(), &[CRONS_CHANNEL]).await;
+    let mut heartbeat = Heartbeat::new("clock");
 
     loop {
         tokio::select! {
             _ = ctx.shutdown_signal() => break,
-            _ = tokio::time::sleep(std::time::Duration::from_millis(poll_ms)) => {
-                match clock_tick(ctx.db()).await {
+            _ = wakeup.wait(std::time::Duration::from_millis(poll_ms)) => {
+                let result = metrics::observe_tick("clock", clock_tick(ctx.db()).await);
+                heartbeat.record(ctx.db(), &result).await;
+                match result {
                     Ok(n) if n > 0 => tracing::info!(processed = n, "clock tick"),
                     Err(e) => tracing::error!(error = %e, "clock tick failed"),
                     _ => {}
@@ -292,9 +355,12 @@ mod tests {
                 chat_id text NOT NULL,
                 prompt text NOT NULL,
                 enabled bool NOT NULL DEFAULT true,
+                notify_policy text NOT NULL DEFAULT 'always',
                 last_run_at timestamptz,
                 next_run_at timestamptz,
-                last_job_id uuid
+                last_job_id uuid,
+                claimed_by text,
+                lease_until timestamptz
             );
 
             CREATE TABLE jobs (
@@ -303,6 +369,7 @@ mod tests {
                 chat_id text NOT NULL,
                 status text NOT NULL,
                 prompt text,
+                cron_id uuid,
                 trace_id uuid,
                 created_at timestamptz NOT NULL DEFAULT now()
             );
@@ -340,6 +407,26 @@ mod tests {
         assert_eq!(parse_auto_stop_limit("no marker"), None);
     }
 
+    #[test]
+    fn on_change_policy_suppresses_identical_output() {
+        let policy = CronNotifyPolicy::OnChange;
+        assert!(should_notify(&policy, None, "site is up"));
+        assert!(!should_notify(&policy, Some("site is up"), "site  is up\n"));
+        assert!(should_notify(&policy, Some("site is up"), "site is down"));
+    }
+
+    #[test]
+    fn noteworthy_policy_suppresses_marker_output() {
+        let policy = CronNotifyPolicy::Noteworthy;
+        assert!(!should_notify(&policy, None, " NOTHING_NOTEWORTHY\n"));
+        assert!(should_notify(&policy, None, "price dropped to $40"));
+        assert!(should_notify(
+            &CronNotifyPolicy::Always,
+            Some("same"),
+            "same"
+        ));
+    }
+
     #[tokio::test]
     async fn initializes_missing_next_run_without_firing_job() {
         let (_db, pool) = setup().await;
@@ -373,6 +460,36 @@ mod tests {
         assert!(next_run.is_some());
     }
 
+    #[tokio::test]
+    async fn skips_cron_leased_by_another_instance() {
+        let (_db, pool) = setup().await;
+        let due_at = chrono::Utc::now() - chrono::Duration::seconds(2);
+
+        sqlx::query!(
+            r#"
+            INSERT INTO crons (id, name, schedule, timezone, chat_id, prompt, enabled, next_run_at,
+                               claimed_by, lease_until)
+            VALUES ($1, 'leased_cron', '* * * * * *', 'UTC', 'chat', 'echo test', true, $2,
+                    'other-replica', now() + interval '1 minute')
+            "#,
+            Uuid::new_v4(),
+            due_at
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let processed = clock_tick(&pool).await.unwrap();
+        assert_eq!(processed, 0);
+
+        let job_count: i64 = sqlx::query_scalar!("SELECT COUNT(*) FROM jobs")
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+            .unwrap_or(0);
+        assert_eq!(job_count, 0);
+    }
+
     #[tokio::test]
     async fn fires_due_cron_and_advances_next_run() {
         let (_db, pool) = setup().await;
@@ -1,4 +1,10 @@
-use crate::services::{AiService, EnrichInput, MediaPreprocessor};
+use crate::functions::claim::instance_id;
+use crate::functions::heartbeat::Heartbeat;
+use crate::functions::wakeup::{JOBS_CHANNEL, Wakeup};
+use crate::services::{
+    AiService, EnrichInput, MediaPreprocessor, approval_patterns, approval_question, approval_rule,
+    approval_timeout_mins, metrics,
+};
 use forge::prelude::*;
 use sqlx::PgPool;
 use std::sync::Arc;
@@ -6,10 +12,15 @@ use uuid::Uuid;
 
 struct DraftJob {
     id: Uuid,
+    kind: String,
     chat_id: String,
     prompt: Option<String>,
     trace_id: Option<Uuid>,
     source_ids: Vec<Uuid>,
+    requires_approval: bool,
+    approval_reason: Option<String>,
+    /// Started by a cron the user set up themselves, not one an agent made.
+    from_user_cron: bool,
 }
 
 struct HistoryRow {
@@ -112,13 +123,29 @@ pub async fn context_tick(db: &PgPool, ai: &dyn AiService) -> Result<u32> {
     let drafts = sqlx::query_as!(
         DraftJob,
         r#"
-        SELECT id, chat_id, prompt, trace_id, source_ids as "source_ids!"
-        FROM jobs
-        WHERE status = 'draft'
+        WITH claimed AS (
+            UPDATE jobs SET claimed_by = $1, lease_until = now() + interval '5 minutes'
+            WHERE id IN (
+                SELECT id FROM jobs
+                WHERE status = 'draft'
+                  AND (lease_until IS NULL OR lease_until < now())
+                ORDER BY created_at
+                LIMIT 10
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, kind, chat_id, prompt, trace_id, source_ids, requires_approval,
+                      approval_reason, created_at,
+                      EXISTS (
+                          SELECT 1 FROM crons c
+                          WHERE c.id = jobs.cron_id AND c.created_by = 'user'
+                      ) AS from_user_cron
+        )
+        SELECT id, kind, chat_id, prompt, trace_id, source_ids as "source_ids!",
+               requires_approval, approval_reason, from_user_cron as "from_user_cron!"
+        FROM claimed
         ORDER BY created_at
-        LIMIT 10
-        FOR UPDATE SKIP LOCKED
-        "#
+        "#,
+        instance_id()
     )
     .fetch_all(db)
     .await?;
@@ -128,6 +155,8 @@ pub async fn context_tick(db: &PgPool, ai: &dyn AiService) -> Result<u32> {
     }
 
     let mut processed = 0u32;
+    let patterns = approval_patterns();
+    let timeout_mins = approval_timeout_mins();
 
     tracing::debug!(count = drafts.len(), "context: enriching draft jobs");
 
@@ -218,27 +247,74 @@ pub async fn context_tick(db: &PgPool, ai: &dyn AiService) -> Result<u32> {
 
         let trace_id = draft.trace_id.unwrap_or_else(Uuid::new_v4);
 
+        // the rule set covers jobs from every source; runs of the user's own
+        // crons are left out since they agreed to them when setting the cron
+        // up, but a cron an agent created gets no such pass
+        let approval = if draft.requires_approval {
+            Some(draft.approval_reason.clone())
+        } else if !(draft.kind == "schedule" && draft.from_user_cron) {
+            approval_rule(&prompt, &patterns).map(|p| Some(format!("it mentions \"{p}\"")))
+        } else {
+            None
+        };
+
         tracing::info!(
             job_id = %draft.id,
             history_count,
             attachment_count,
             enriched_len = enriched.enriched_prompt.len(),
-            "context: enrichment complete, promoting to pending"
+            needs_approval = approval.is_some(),
+            "context: enrichment complete, promoting"
         );
 
         let mut tx = db.begin().await?;
 
-        sqlx::query!(
+        let promoted = sqlx::query!(
             r#"
-            UPDATE jobs SET status = 'pending', enriched_prompt = $2
+            UPDATE jobs SET status = CASE WHEN $3 THEN 'awaiting_approval' ELSE 'pending' END,
+                            enriched_prompt = $2, enriched_at = now(),
+                            claimed_by = NULL, lease_until = NULL,
+                            requires_approval = $3, approval_reason = $4,
+                            approval_expires_at = CASE WHEN $3
+                                THEN now() + make_interval(mins => $5) END
             WHERE id = $1 AND status = 'draft'
             "#,
             draft.id,
-            enriched.enriched_prompt
+            enriched.enriched_prompt,
+            approval.is_some(),
+            approval.clone().flatten(),
+            timeout_mins
         )
         .execute(&mut *tx)
         .await?;
 
+        if let Some(reason) = approval
+            && promoted.rows_affected() > 0
+        {
+            sqlx::query!(
+                r#"
+                INSERT INTO outbox (chat_id, content, job_id, trace_id)
+                VALUES ($1, $2, $3, $4)
+                "#,
+                draft.chat_id,
+                approval_question(&prompt, reason.as_deref(), timeout_mins),
+                draft.id,
+                trace_id
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!(
+                r#"
+                INSERT INTO events (trace_id, source, action, payload)
+                VALUES ($1, 'context', 'job_awaiting_approval', $2)
+                "#,
+                trace_id,
+                serde_json::json!({ "job_id": draft.id, "reason": reason })
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
         sqlx::query!(
             r#"
             INSERT INTO events (trace_id, source, action, payload)
@@ -261,12 +337,16 @@ pub async fn context_tick(db: &PgPool, ai: &dyn AiService) -> Result<u32> {
 pub async fn context_loop(ctx: &DaemonContext) -> Result<()> {
     let ai: Arc<dyn AiService> = crate::get_ai_service();
     let poll_ms: u64 = ctx.env_parse("YUI_LOOP_POLL_MS_CONTEXT").unwrap_or(500);
+    let mut wakeup = Wakeup::listen(ctx.db(), &[JOBS_CHANNEL]).await;
+    let mut heartbeat = Heartbeat::new("context");
 
     loop {
         tokio::select! {
             _ = ctx.shutdown_signal() => break,
-            _ = tokio::time::sleep(std::time::Duration::from_millis(poll_ms)) => {
-                match context_tick(ctx.db(), ai.as_ref()).await {
+            _ = wakeup.wait(std::time::Duration::from_millis(poll_ms)) => {
+                let result = metrics::observe_tick("context", context_tick(ctx.db(), ai.as_ref()).await);
+                heartbeat.record(ctx.db(), &result).await;
+                match result {
                     Ok(n) if n > 0 => tracing::info!(processed = n, "context tick"),
                     Err(e) => tracing::error!(error = %e, "context tick failed"),
                     _ => {}
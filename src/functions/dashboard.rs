@@ -60,9 +60,9 @@ pub async fn list_jobs(ctx: &QueryContext, input: ListJobsInput) -> Result<Vec<J
             Job,
             r#"
             SELECT id, kind as "kind: JobKind", chat_id, status as "status: JobStatus",
-                   prompt, enriched_prompt, source_ids as "source_ids!", resume_input, output, error,
-                   cancel_reason, forge_job_id, session_id, container_id, last_heartbeat_at,
-                   question_pending, started_at, finished_at,
+                   prompt, enriched_prompt, source_ids as "source_ids!", depends_on, workspace_id, resume_input, output, error,
+                   cancel_reason, forge_job_id, cron_id, session_id, container_id, last_heartbeat_at,
+                   question_pending, priority, attempt_count, max_attempts, retry_at, started_at, finished_at,
                    trace_id, created_at, updated_at
             FROM jobs
             WHERE status = $1
@@ -80,9 +80,9 @@ pub async fn list_jobs(ctx: &QueryContext, input: ListJobsInput) -> Result<Vec<J
             Job,
             r#"
             SELECT id, kind as "kind: JobKind", chat_id, status as "status: JobStatus",
-                   prompt, enriched_prompt, source_ids as "source_ids!", resume_input, output, error,
-                   cancel_reason, forge_job_id, session_id, container_id, last_heartbeat_at,
-                   question_pending, started_at, finished_at,
+                   prompt, enriched_prompt, source_ids as "source_ids!", depends_on, workspace_id, resume_input, output, error,
+                   cancel_reason, forge_job_id, cron_id, session_id, container_id, last_heartbeat_at,
+                   question_pending, priority, attempt_count, max_attempts, retry_at, started_at, finished_at,
                    trace_id, created_at, updated_at
             FROM jobs
             ORDER BY created_at DESC, id DESC
@@ -96,6 +96,37 @@ pub async fn list_jobs(ctx: &QueryContext, input: ListJobsInput) -> Result<Vec<J
     }
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListQueueInput {}
+
+#[derive(Debug, Serialize)]
+pub struct QueueEntry {
+    pub job_id: Uuid,
+    pub chat_id: String,
+    pub priority: i32,
+    pub chat_slot: i64,
+    pub position: i64,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Pending jobs in the order the runtime will start them.
+#[forge::query(public)]
+pub async fn list_queue(ctx: &QueryContext, _input: ListQueueInput) -> Result<Vec<QueueEntry>> {
+    sqlx::query_as!(
+        QueueEntry,
+        r#"
+        SELECT id as "job_id!", chat_id as "chat_id!", priority as "priority!",
+               chat_slot as "chat_slot!", position as "position!", created_at as "created_at!"
+        FROM job_queue
+        ORDER BY position
+        LIMIT 200
+        "#
+    )
+    .fetch_all(ctx.db())
+    .await
+    .map_err(|e| ForgeError::Database(e.to_string()))
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ListOutboxInput {
     pub pending_only: Option<bool>,
@@ -155,6 +186,8 @@ pub async fn list_crons(ctx: &QueryContext, input: ListCronsInput) -> Result<Vec
         Cron,
         r#"
         SELECT id, name, schedule, timezone, chat_id, prompt, enabled,
+               notify_policy as "notify_policy: CronNotifyPolicy",
+               egress_policy as "egress_policy: EgressPolicy", egress_allow,
                last_run_at, next_run_at, last_job_id, created_at, updated_at
         FROM crons
         ORDER BY created_at DESC, id DESC
@@ -216,6 +249,100 @@ pub async fn list_messages(ctx: &QueryContext, input: ListMessagesInput) -> Resu
     }
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListJobLogsInput {
+    pub job_id: Uuid,
+    /// Only lines written after this timestamp, for tailing.
+    pub after: Option<chrono::DateTime<chrono::Utc>>,
+    pub limit: Option<i64>,
+}
+
+#[forge::query(public)]
+pub async fn list_job_logs(ctx: &QueryContext, input: ListJobLogsInput) -> Result<Vec<LogEntry>> {
+    let limit = input.limit.unwrap_or(500).min(2000);
+
+    // newest lines first in the subquery so the tail is what gets cut by the limit
+    sqlx::query_as!(
+        LogEntry,
+        r#"
+        SELECT id as "id!", job_id as "job_id!", stream as "stream!: LogStream",
+               line as "line!", created_at as "created_at!"
+        FROM (
+            SELECT id, job_id, stream, line, created_at
+            FROM logs
+            WHERE job_id = $1 AND ($2::timestamptz IS NULL OR created_at > $2)
+            ORDER BY created_at DESC, id DESC
+            LIMIT $3
+        ) tail
+        ORDER BY created_at, id
+        "#,
+        input.job_id,
+        input.after,
+        limit
+    )
+    .fetch_all(ctx.db())
+    .await
+    .map_err(|e| ForgeError::Database(e.to_string()))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListJobStepsInput {
+    pub job_id: Uuid,
+}
+
+#[forge::query(public)]
+pub async fn list_job_steps(
+    ctx: &QueryContext,
+    input: ListJobStepsInput,
+) -> Result<Vec<AgentStep>> {
+    sqlx::query_as!(
+        AgentStep,
+        r#"
+        SELECT id, job_id, step_number, tool_name, input_summary, output_summary,
+               duration_ms, created_at
+        FROM agent_steps
+        WHERE job_id = $1
+        ORDER BY step_number, created_at
+        "#,
+        input.job_id
+    )
+    .fetch_all(ctx.db())
+    .await
+    .map_err(|e| ForgeError::Database(e.to_string()))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListArtifactsInput {
+    pub job_id: Option<Uuid>,
+    pub limit: Option<i64>,
+}
+
+/// Files jobs produced, newest first. Each one downloads from `/artifacts/{id}`.
+#[forge::query(public)]
+pub async fn list_artifacts(
+    ctx: &QueryContext,
+    input: ListArtifactsInput,
+) -> Result<Vec<Artifact>> {
+    let limit = input.limit.unwrap_or(50).min(200);
+
+    sqlx::query_as!(
+        Artifact,
+        r#"
+        SELECT id, job_id, chat_id, name, kind, mime, size_bytes, sha256, path, trace_id,
+               created_at
+        FROM artifacts
+        WHERE $1::uuid IS NULL OR job_id = $1
+        ORDER BY created_at DESC, id DESC
+        LIMIT $2
+        "#,
+        input.job_id,
+        limit
+    )
+    .fetch_all(ctx.db())
+    .await
+    .map_err(|e| ForgeError::Database(e.to_string()))
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GetTraceInput {
     pub trace_id: Uuid,
@@ -248,9 +375,9 @@ pub async fn get_trace(ctx: &QueryContext, input: GetTraceInput) -> Result<Trace
         Job,
         r#"
         SELECT id, kind as "kind: JobKind", chat_id, status as "status: JobStatus",
-               prompt, enriched_prompt, source_ids as "source_ids!", resume_input, output, error,
-               cancel_reason, forge_job_id, session_id, container_id, last_heartbeat_at,
-               question_pending, started_at, finished_at,
+               prompt, enriched_prompt, source_ids as "source_ids!", depends_on, workspace_id, resume_input, output, error,
+               cancel_reason, forge_job_id, cron_id, session_id, container_id, last_heartbeat_at,
+               question_pending, priority, attempt_count, max_attempts, retry_at, started_at, finished_at,
                trace_id, created_at, updated_at
         FROM jobs
         WHERE trace_id = $1
@@ -297,6 +424,22 @@ pub struct HealthView {
     pub pending_outbox: i64,
     pub dead_letter_outbox: i64,
     pub stuck_jobs: i64,
+    pub stale_daemons: i64,
+    pub daemons: Vec<DaemonStatus>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DaemonStatus {
+    pub daemon: String,
+    pub instance_id: String,
+    pub host: Option<String>,
+    pub version: String,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub last_tick_at: chrono::DateTime<chrono::Utc>,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub items_processed: i64,
+    pub stale: bool,
 }
 
 #[forge::query(public)]
@@ -329,7 +472,26 @@ pub async fn get_health(ctx: &QueryContext, _input: GetHealthInput) -> Result<He
     .await
     .map_err(|e| ForgeError::Database(e.to_string()))?;
 
+    // rows from instances that went away long ago are left out rather than
+    // reported as stale forever
+    let daemons = sqlx::query_as!(
+        DaemonStatus,
+        r#"
+        SELECT daemon, instance_id, host, version, started_at, last_tick_at,
+               last_error, last_error_at, items_processed,
+               last_tick_at < now() - interval '2 minutes' as "stale!"
+        FROM daemon_heartbeats
+        WHERE last_tick_at > now() - interval '1 day'
+        ORDER BY daemon, instance_id
+        "#
+    )
+    .fetch_all(ctx.db())
+    .await
+    .map_err(|e| ForgeError::Database(e.to_string()))?;
+
     Ok(HealthView {
+        stale_daemons: daemons.iter().filter(|d| d.stale).count() as i64,
+        daemons,
         pending_jobs: jobs.pending,
         running_jobs: jobs.running,
         paused_jobs: jobs.paused,
@@ -339,6 +501,88 @@ pub async fn get_health(ctx: &QueryContext, _input: GetHealthInput) -> Result<He
     })
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GetStageLatencyInput {
+    pub days: Option<i32>,
+    pub kind: Option<String>,
+}
+
+/// Latency of one pipeline stage for one day and job kind. Message-side stages
+/// (buffer wait, triage) use the kind `inbound`; replies sent straight from
+/// triage without a job use `direct`.
+#[derive(Debug, Serialize)]
+pub struct StageLatency {
+    pub day: chrono::NaiveDate,
+    pub kind: String,
+    pub stage: String,
+    pub samples: i64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+}
+
+#[forge::query(public)]
+pub async fn get_stage_latency(
+    ctx: &QueryContext,
+    input: GetStageLatencyInput,
+) -> Result<Vec<StageLatency>> {
+    let days = input.days.unwrap_or(7).clamp(1, 90);
+
+    sqlx::query_as!(
+        StageLatency,
+        r#"
+        WITH spans AS (
+            SELECT created_at AS day_at, 'inbound' AS kind, 'buffer_wait' AS stage,
+                   created_at - received_at AS span
+            FROM messages
+            WHERE direction = 'in' AND received_at IS NOT NULL
+            UNION ALL
+            SELECT created_at, 'inbound', 'triage', routed_at - created_at
+            FROM messages
+            WHERE direction = 'in' AND routed_at IS NOT NULL
+            UNION ALL
+            SELECT created_at, kind, 'enrichment', enriched_at - created_at
+            FROM jobs
+            WHERE enriched_at IS NOT NULL
+            UNION ALL
+            SELECT created_at, kind, 'queue_wait', started_at - COALESCE(enriched_at, created_at)
+            FROM jobs
+            WHERE started_at IS NOT NULL
+            UNION ALL
+            SELECT created_at, kind, 'execution', finished_at - started_at
+            FROM jobs
+            WHERE started_at IS NOT NULL AND finished_at IS NOT NULL
+            UNION ALL
+            SELECT o.created_at, COALESCE(j.kind, 'direct'), 'rewrite', o.rewritten_at - o.created_at
+            FROM outbox o
+            LEFT JOIN jobs j ON j.id = o.job_id
+            WHERE o.rewritten_at IS NOT NULL
+            UNION ALL
+            SELECT o.created_at, COALESCE(j.kind, 'direct'), 'delivery',
+                   o.processed_at - COALESCE(o.rewritten_at, o.created_at)
+            FROM outbox o
+            LEFT JOIN jobs j ON j.id = o.job_id
+            WHERE o.processed_at IS NOT NULL
+        )
+        SELECT date_trunc('day', day_at)::date as "day!",
+               kind as "kind!",
+               stage as "stage!",
+               COUNT(*) as "samples!",
+               percentile_cont(0.5) WITHIN GROUP (ORDER BY EXTRACT(EPOCH FROM span) * 1000) as "p50_ms!",
+               percentile_cont(0.95) WITHIN GROUP (ORDER BY EXTRACT(EPOCH FROM span) * 1000) as "p95_ms!"
+        FROM spans
+        WHERE day_at >= date_trunc('day', now()) - make_interval(days => $1 - 1)
+          AND ($2::text IS NULL OR kind = $2)
+        GROUP BY 1, 2, 3
+        ORDER BY 1 DESC, 2, 3
+        "#,
+        days,
+        input.kind
+    )
+    .fetch_all(ctx.db())
+    .await
+    .map_err(|e| ForgeError::Database(e.to_string()))
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CancelJobInput {
     pub job_id: Uuid,
@@ -361,7 +605,7 @@ pub async fn cancel_job(ctx: &MutationContext, input: CancelJobInput) -> Result<
         .execute(sqlx::query!(
             r#"
             UPDATE jobs SET status = 'cancelled', cancel_reason = $2, finished_at = now()
-            WHERE id = $1 AND status IN ('draft', 'pending', 'running', 'paused')
+            WHERE id = $1 AND status IN ('draft', 'pending', 'awaiting_approval', 'running', 'paused')
             "#,
             input.job_id,
             reason
@@ -384,6 +628,50 @@ pub async fn cancel_job(ctx: &MutationContext, input: CancelJobInput) -> Result<
     })
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RetryJobInput {
+    pub job_id: Uuid,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RetryJobOutput {
+    pub retried: bool,
+}
+
+/// Re-queues a failed job with a fresh set of attempts.
+#[forge::mutation(public)]
+pub async fn retry_job(ctx: &MutationContext, input: RetryJobInput) -> Result<RetryJobOutput> {
+    let db = ctx.db();
+
+    let result = db
+        .execute(sqlx::query!(
+            r#"
+            UPDATE jobs SET status = 'pending', attempt_count = 0, retry_at = NULL,
+                            error = NULL, finished_at = NULL, claimed_by = NULL, lease_until = NULL,
+                            runner_backend = NULL, runner_run_id = NULL, container_id = NULL
+            WHERE id = $1 AND status = 'failed'
+            "#,
+            input.job_id
+        ))
+        .await?;
+
+    if result.rows_affected() > 0 {
+        db.execute(sqlx::query!(
+            r#"
+            INSERT INTO events (trace_id, source, action, payload)
+            SELECT trace_id, 'dashboard', 'job_retried', $2 FROM jobs WHERE id = $1
+            "#,
+            input.job_id,
+            serde_json::json!({ "job_id": input.job_id })
+        ))
+        .await?;
+    }
+
+    Ok(RetryJobOutput {
+        retried: result.rows_affected() > 0,
+    })
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ToggleCronInput {
     pub cron_id: Uuid,
@@ -425,3 +713,328 @@ pub async fn toggle_cron(
         updated: result.rows_affected() > 0,
     })
 }
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetCronNotifyPolicyInput {
+    pub cron_id: Uuid,
+    pub notify_policy: CronNotifyPolicy,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SetCronNotifyPolicyOutput {
+    pub updated: bool,
+}
+
+#[forge::mutation(public)]
+pub async fn set_cron_notify_policy(
+    ctx: &MutationContext,
+    input: SetCronNotifyPolicyInput,
+) -> Result<SetCronNotifyPolicyOutput> {
+    let db = ctx.db();
+
+    let result = db
+        .execute(sqlx::query!(
+            "UPDATE crons SET notify_policy = $2 WHERE id = $1",
+            input.cron_id,
+            input.notify_policy as _
+        ))
+        .await?;
+
+    if result.rows_affected() > 0 {
+        db.execute(sqlx::query!(
+            r#"
+            INSERT INTO events (source, action, payload)
+            VALUES ('dashboard', 'cron_notify_policy_set', $1)
+            "#,
+            serde_json::json!({ "cron_id": input.cron_id, "notify_policy": input.notify_policy })
+        ))
+        .await?;
+    }
+
+    Ok(SetCronNotifyPolicyOutput {
+        updated: result.rows_affected() > 0,
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetCronEgressPolicyInput {
+    pub cron_id: Uuid,
+    /// `None` goes back to the default for scheduled jobs.
+    pub egress_policy: Option<EgressPolicy>,
+    #[serde(default)]
+    pub egress_allow: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SetCronEgressPolicyOutput {
+    pub updated: bool,
+}
+
+#[forge::mutation(public)]
+pub async fn set_cron_egress_policy(
+    ctx: &MutationContext,
+    input: SetCronEgressPolicyInput,
+) -> Result<SetCronEgressPolicyOutput> {
+    let db = ctx.db();
+    let allow: Vec<String> = input
+        .egress_allow
+        .iter()
+        .map(|h| h.trim().to_ascii_lowercase())
+        .filter(|h| !h.is_empty())
+        .collect();
+
+    let result = db
+        .execute(sqlx::query!(
+            "UPDATE crons SET egress_policy = $2, egress_allow = $3 WHERE id = $1",
+            input.cron_id,
+            input.egress_policy as _,
+            &allow
+        ))
+        .await?;
+
+    if result.rows_affected() > 0 {
+        db.execute(sqlx::query!(
+            r#"
+            INSERT INTO events (source, action, payload)
+            VALUES ('dashboard', 'cron_egress_policy_set', $1)
+            "#,
+            serde_json::json!({
+                "cron_id": input.cron_id,
+                "egress_policy": input.egress_policy,
+                "egress_allow": allow,
+            })
+        ))
+        .await?;
+    }
+
+    Ok(SetCronEgressPolicyOutput {
+        updated: result.rows_affected() > 0,
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListSecretsInput {}
+
+#[forge::query(public)]
+pub async fn list_secrets(ctx: &QueryContext, _input: ListSecretsInput) -> Result<Vec<Secret>> {
+    sqlx::query_as!(
+        Secret,
+        r#"
+        SELECT id, name, description, inject_as as "inject_as: SecretInjection",
+               allowed_kinds, allowed_crons, created_at, updated_at
+        FROM secrets
+        ORDER BY name
+        "#
+    )
+    .fetch_all(ctx.db())
+    .await
+    .map_err(|e| ForgeError::Database(e.to_string()))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetSecretInput {
+    pub name: String,
+    pub description: Option<String>,
+    /// Left unchanged on an existing secret when omitted.
+    pub value: Option<String>,
+    pub inject_as: SecretInjection,
+    #[serde(default)]
+    pub allowed_kinds: Vec<String>,
+    #[serde(default)]
+    pub allowed_crons: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SetSecretOutput {
+    pub id: Uuid,
+}
+
+/// Creates or updates a vault entry by name. The value is sealed before it is
+/// stored and never returned.
+#[forge::mutation(public)]
+pub async fn set_secret(ctx: &MutationContext, input: SetSecretInput) -> Result<SetSecretOutput> {
+    let key = crate::services::secrets_key()
+        .ok_or_else(|| ForgeError::Validation("YUI_SECRETS_KEY is not set".to_string()))?;
+    let name = input.name.trim();
+    let valid_name = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid_name {
+        return Err(ForgeError::Validation(format!(
+            "invalid secret name `{name}`: use letters, digits and underscores"
+        )));
+    }
+    let db = ctx.db();
+
+    let id = match input.value {
+        Some(ref value) => sqlx::query_scalar!(
+            r#"
+            INSERT INTO secrets (name, description, value_sealed, inject_as,
+                                 allowed_kinds, allowed_crons)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (name) DO UPDATE SET
+                description = EXCLUDED.description,
+                value_sealed = EXCLUDED.value_sealed,
+                value_encrypted = NULL,
+                inject_as = EXCLUDED.inject_as,
+                allowed_kinds = EXCLUDED.allowed_kinds,
+                allowed_crons = EXCLUDED.allowed_crons
+            RETURNING id
+            "#,
+            name,
+            input.description,
+            key.seal(value),
+            input.inject_as as _,
+            &input.allowed_kinds,
+            &input.allowed_crons
+        )
+        .fetch_one(db)
+        .await
+        .map_err(|e| ForgeError::Database(e.to_string()))?,
+        // without a value only an existing secret can be changed
+        None => sqlx::query_scalar!(
+            r#"
+            UPDATE secrets SET description = $2, inject_as = $3,
+                               allowed_kinds = $4, allowed_crons = $5
+            WHERE name = $1
+            RETURNING id
+            "#,
+            name,
+            input.description,
+            input.inject_as as _,
+            &input.allowed_kinds,
+            &input.allowed_crons
+        )
+        .fetch_optional(db)
+        .await
+        .map_err(|e| ForgeError::Database(e.to_string()))?
+        .ok_or_else(|| {
+            ForgeError::Validation(format!(
+                "secret `{name}` does not exist yet, give it a value"
+            ))
+        })?,
+    };
+
+    db.execute(sqlx::query!(
+        r#"
+        INSERT INTO events (source, action, payload)
+        VALUES ('dashboard', 'secret_set', $1)
+        "#,
+        serde_json::json!({
+            "secret_id": id,
+            "name": name,
+            "value_changed": input.value.is_some(),
+            "allowed_kinds": input.allowed_kinds,
+            "allowed_crons": input.allowed_crons,
+        })
+    ))
+    .await?;
+
+    Ok(SetSecretOutput { id })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeleteSecretInput {
+    pub secret_id: Uuid,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeleteSecretOutput {
+    pub deleted: bool,
+}
+
+#[forge::mutation(public)]
+pub async fn delete_secret(
+    ctx: &MutationContext,
+    input: DeleteSecretInput,
+) -> Result<DeleteSecretOutput> {
+    let db = ctx.db();
+
+    let result = db
+        .execute(sqlx::query!(
+            "DELETE FROM secrets WHERE id = $1",
+            input.secret_id
+        ))
+        .await?;
+
+    if result.rows_affected() > 0 {
+        db.execute(sqlx::query!(
+            r#"
+            INSERT INTO events (source, action, payload)
+            VALUES ('dashboard', 'secret_deleted', $1)
+            "#,
+            serde_json::json!({ "secret_id": input.secret_id })
+        ))
+        .await?;
+    }
+
+    Ok(DeleteSecretOutput {
+        deleted: result.rows_affected() > 0,
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListWorkspacesInput {}
+
+#[forge::query(public)]
+pub async fn list_workspaces(
+    ctx: &QueryContext,
+    _input: ListWorkspacesInput,
+) -> Result<Vec<Workspace>> {
+    sqlx::query_as!(
+        Workspace,
+        r#"
+        SELECT id, chat_id, name, quota_mb, size_bytes, locked_by, reset_requested_at,
+               last_used_at, created_at
+        FROM workspaces
+        ORDER BY last_used_at DESC NULLS LAST, name
+        "#
+    )
+    .fetch_all(ctx.db())
+    .await
+    .map_err(|e| ForgeError::Database(e.to_string()))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResetWorkspaceInput {
+    pub workspace_id: Uuid,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResetWorkspaceOutput {
+    pub requested: bool,
+}
+
+/// Marks the workspace for a reset; the runtime empties it once no job is
+/// using it and tells the chat.
+#[forge::mutation(public)]
+pub async fn reset_workspace(
+    ctx: &MutationContext,
+    input: ResetWorkspaceInput,
+) -> Result<ResetWorkspaceOutput> {
+    let db = ctx.db();
+
+    let result = db
+        .execute(sqlx::query!(
+            "UPDATE workspaces SET reset_requested_at = now() WHERE id = $1",
+            input.workspace_id
+        ))
+        .await?;
+
+    if result.rows_affected() > 0 {
+        db.execute(sqlx::query!(
+            r#"
+            INSERT INTO events (source, action, payload)
+            VALUES ('dashboard', 'workspace_reset_requested', $1)
+            "#,
+            serde_json::json!({ "workspace_id": input.workspace_id })
+        ))
+        .await?;
+    }
+
+    Ok(ResetWorkspaceOutput {
+        requested: result.rows_affected() > 0,
+    })
+}
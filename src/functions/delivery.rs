@@ -1,5 +1,9 @@
+use crate::functions::claim::instance_id;
 use crate::functions::gateway::WA_CLIENT;
+use crate::functions::heartbeat::Heartbeat;
+use crate::functions::wakeup::{OUTBOX_CHANNEL, Wakeup};
 use crate::schema::message::Attachment;
+use crate::services::metrics;
 use forge::prelude::*;
 use sqlx::PgPool;
 use uuid::Uuid;
@@ -226,14 +230,24 @@ pub async fn delivery_tick(db: &PgPool) -> Result<u32> {
     let pending = sqlx::query_as!(
         PendingOutbox,
         r#"
+        WITH claimed AS (
+            UPDATE outbox SET claimed_by = $2, lease_until = now() + interval '2 minutes'
+            WHERE id IN (
+                SELECT id FROM outbox
+                WHERE processed_at IS NULL AND rewritten_at IS NOT NULL AND attempt_count < $1
+                  AND (lease_until IS NULL OR lease_until < now())
+                ORDER BY created_at
+                LIMIT 20
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, chat_id, content, attachments, attempt_count, trace_id, created_at
+        )
         SELECT id, chat_id, content, attachments, attempt_count, trace_id
-        FROM outbox
-        WHERE processed_at IS NULL AND rewritten_at IS NOT NULL AND attempt_count < $1
+        FROM claimed
         ORDER BY created_at
-        LIMIT 20
-        FOR UPDATE SKIP LOCKED
         "#,
-        MAX_DELIVERY_ATTEMPTS
+        MAX_DELIVERY_ATTEMPTS,
+        instance_id()
     )
     .fetch_all(db)
     .await?;
@@ -304,7 +318,8 @@ pub async fn delivery_tick(db: &PgPool) -> Result<u32> {
             Ok(()) => {
                 sqlx::query!(
                     r#"
-                    UPDATE outbox SET processed_at = now(), attempt_count = attempt_count + 1
+                    UPDATE outbox SET processed_at = now(), attempt_count = attempt_count + 1,
+                                      claimed_by = NULL, lease_until = NULL
                     WHERE id = $1
                     "#,
                     item.id
@@ -328,7 +343,8 @@ pub async fn delivery_tick(db: &PgPool) -> Result<u32> {
             Err(err) => {
                 sqlx::query!(
                     r#"
-                    UPDATE outbox SET attempt_count = attempt_count + 1, last_error = $2
+                    UPDATE outbox SET attempt_count = attempt_count + 1, last_error = $2,
+                                      claimed_by = NULL, lease_until = NULL
                     WHERE id = $1
                     "#,
                     item.id,
@@ -356,19 +372,44 @@ pub async fn delivery_tick(db: &PgPool) -> Result<u32> {
     Ok(processed)
 }
 
+async fn refresh_outbox_gauges(db: &PgPool) -> Result<()> {
+    let depth = sqlx::query!(
+        r#"
+        SELECT
+            COUNT(*) FILTER (WHERE attempt_count < $1) as "backlog!",
+            COUNT(*) FILTER (WHERE attempt_count >= $1) as "dead_letters!"
+        FROM outbox
+        WHERE processed_at IS NULL
+        "#,
+        MAX_DELIVERY_ATTEMPTS
+    )
+    .fetch_one(db)
+    .await?;
+
+    metrics::set_outbox_depth(depth.backlog, depth.dead_letters);
+    Ok(())
+}
+
 #[forge::daemon]
 pub async fn delivery(ctx: &DaemonContext) -> Result<()> {
     let poll_ms: u64 = ctx.env_parse("YUI_LOOP_POLL_MS_DELIVERY").unwrap_or(500);
+    let mut wakeup = Wakeup::listen(ctx.db(), &[OUTBOX_CHANNEL]).await;
+    let mut heartbeat = Heartbeat::new("delivery");
 
     loop {
         tokio::select! {
             _ = ctx.shutdown_signal() => break,
-            _ = tokio::time::sleep(std::time::Duration::from_millis(poll_ms)) => {
-                match delivery_tick(ctx.db()).await {
+            _ = wakeup.wait(std::time::Duration::from_millis(poll_ms)) => {
+                let result = metrics::observe_tick("delivery", delivery_tick(ctx.db()).await);
+                heartbeat.record(ctx.db(), &result).await;
+                match result {
                     Ok(n) if n > 0 => tracing::info!(processed = n, "delivery tick"),
                     Err(e) => tracing::error!(error = %e, "delivery tick failed"),
                     _ => {}
                 }
+                if let Err(e) = refresh_outbox_gauges(ctx.db()).await {
+                    tracing::warn!(error = %e, "failed to refresh outbox gauges");
+                }
             }
         }
     }
@@ -409,6 +450,8 @@ mod tests {
                 attempt_count int NOT NULL DEFAULT 0,
                 last_error text,
                 trace_id uuid,
+                claimed_by text,
+                lease_until timestamptz,
                 created_at timestamptz NOT NULL DEFAULT now()
             );
 
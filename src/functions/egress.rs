@@ -0,0 +1,481 @@
+use crate::schema::EgressPolicy;
+use crate::services::{EgressRules, egress_config, proxy_token};
+use forge::prelude::*;
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use uuid::Uuid;
+
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+/// Longest chunk size or trailer line accepted in a chunked request body.
+const MAX_CHUNK_LINE_BYTES: u64 = 4 * 1024;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Works out a job's egress rules and records them on the job, before its
+/// run starts, so the proxy already knows them on the first request.
+pub(crate) async fn assign_egress(db: &PgPool, job_id: Uuid, kind: &str) -> Result<EgressRules> {
+    let cron = sqlx::query!(
+        r#"
+        SELECT c.egress_policy as "egress_policy: EgressPolicy", c.egress_allow
+        FROM jobs j JOIN crons c ON c.id = j.cron_id
+        WHERE j.id = $1
+        "#,
+        job_id
+    )
+    .fetch_optional(db)
+    .await?
+    .map(|c| (c.egress_policy, c.egress_allow));
+
+    let rules = egress_config().rules(kind, cron);
+    sqlx::query!(
+        "UPDATE jobs SET egress_policy = $2, egress_allow = $3 WHERE id = $1",
+        job_id,
+        rules.policy.clone() as _,
+        &rules.allow
+    )
+    .execute(db)
+    .await?;
+    Ok(rules)
+}
+
+/// One request to the proxy, as far as the proxy cares about it.
+#[derive(Debug)]
+struct ProxyRequest {
+    method: String,
+    host: String,
+    port: u16,
+    token: Option<String>,
+    /// The request head rewritten for the origin server; `None` for CONNECT,
+    /// which only opens a tunnel.
+    forward: Option<String>,
+    /// Where the request body ends, so nothing after it reaches the origin.
+    body: BodyLength,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BodyLength {
+    Bytes(u64),
+    Chunked,
+}
+
+fn parse_authority(authority: &str, default_port: u16) -> Option<(String, u16)> {
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) if !port.contains(']') => (host, port.parse().ok()?),
+        _ => (authority, default_port),
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    (!host.is_empty()).then(|| (host.to_ascii_lowercase(), port))
+}
+
+/// Parses a proxy request head: `CONNECT host:port` for TLS, or a plain HTTP
+/// request with an absolute URL, which is rewritten to the form the origin
+/// expects with the proxy's own headers taken out. The `Host` header always
+/// comes from the checked URL, never from the client.
+fn parse_proxy_request(head: &str) -> Option<ProxyRequest> {
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next()?.split(' ');
+    let method = request_line.next()?.to_string();
+    let target = request_line.next()?;
+
+    let mut token = None;
+    let mut headers = vec![];
+    let mut content_length = None;
+    let mut chunked = false;
+    for line in lines.take_while(|l| !l.is_empty()) {
+        let (name, value) = line.split_once(':')?;
+        match name.trim().to_ascii_lowercase().as_str() {
+            "proxy-authorization" => token = proxy_token(value),
+            "proxy-connection" | "connection" | "host" => {}
+            // a body framed two ways, or in a way the proxy cannot follow, is
+            // refused rather than guessed at
+            "content-length" => {
+                let length = value.trim().parse::<u64>().ok()?;
+                if content_length.is_some_and(|l| l != length) {
+                    return None;
+                }
+                content_length = Some(length);
+                headers.push(line);
+            }
+            "transfer-encoding" => {
+                let last = value.rsplit(',').next()?.trim();
+                if chunked || !last.eq_ignore_ascii_case("chunked") {
+                    return None;
+                }
+                chunked = true;
+                headers.push(line);
+            }
+            _ => headers.push(line),
+        }
+    }
+    let body = match (chunked, content_length) {
+        (true, Some(_)) => return None,
+        (true, None) => BodyLength::Chunked,
+        (false, length) => BodyLength::Bytes(length.unwrap_or(0)),
+    };
+
+    if method.eq_ignore_ascii_case("CONNECT") {
+        let (host, port) = parse_authority(target, 443)?;
+        return Some(ProxyRequest {
+            method,
+            host,
+            port,
+            token,
+            forward: None,
+            body,
+        });
+    }
+
+    let url = reqwest::Url::parse(target).ok()?;
+    if url.scheme() != "http" {
+        return None;
+    }
+    let host = url
+        .host_str()?
+        .trim_matches(['[', ']'])
+        .to_ascii_lowercase();
+    let port = url.port_or_known_default()?;
+    let path = match url.query() {
+        Some(query) => format!("{}?{query}", url.path()),
+        None => url.path().to_string(),
+    };
+    // the authority without any user info, which has no place in `Host`
+    let authority = match url.port() {
+        Some(port) => format!("{}:{port}", url.host_str()?),
+        None => url.host_str()?.to_string(),
+    };
+    let mut forward = format!("{method} {path} HTTP/1.1\r\nHost: {authority}\r\n");
+    for header in headers {
+        forward.push_str(header);
+        forward.push_str("\r\n");
+    }
+    // one request per connection keeps the check per request
+    forward.push_str("Connection: close\r\n\r\n");
+    Some(ProxyRequest {
+        method,
+        host,
+        port,
+        token,
+        forward: Some(forward),
+        body,
+    })
+}
+
+/// Copies one request body from the client to the origin and stops where it
+/// ends. Anything the client sent after it, such as a pipelined request for
+/// another host, is never forwarded.
+async fn forward_body<R, W>(client: R, upstream: &mut W, body: BodyLength) -> std::io::Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut client = BufReader::new(client);
+    let length = match body {
+        BodyLength::Bytes(length) => length,
+        BodyLength::Chunked => {
+            loop {
+                let line = read_chunk_line(&mut client).await?;
+                upstream.write_all(line.as_bytes()).await?;
+                let size = line.split(';').next().unwrap_or_default().trim();
+                let size = u64::from_str_radix(size, 16).map_err(|_| bad_body("chunk size"))?;
+                if size == 0 {
+                    break;
+                }
+                copy_exactly(&mut client, upstream, size + 2).await?;
+            }
+            // trailers, up to the empty line that ends the body
+            loop {
+                let line = read_chunk_line(&mut client).await?;
+                upstream.write_all(line.as_bytes()).await?;
+                if line == "\r\n" {
+                    return Ok(());
+                }
+            }
+        }
+    };
+    copy_exactly(&mut client, upstream, length).await
+}
+
+async fn read_chunk_line<R: AsyncBufReadExt + Unpin>(client: &mut R) -> std::io::Result<String> {
+    let mut line = String::new();
+    (&mut *client)
+        .take(MAX_CHUNK_LINE_BYTES)
+        .read_line(&mut line)
+        .await?;
+    if !line.ends_with("\r\n") {
+        return Err(bad_body("chunk line"));
+    }
+    Ok(line)
+}
+
+async fn copy_exactly<R, W>(client: &mut R, upstream: &mut W, length: u64) -> std::io::Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let copied = tokio::io::copy(&mut (&mut *client).take(length), upstream).await?;
+    if copied < length {
+        return Err(std::io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(())
+}
+
+fn bad_body(what: &str) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("malformed {what} in request body"),
+    )
+}
+
+/// Reads up to the end of the request head; whatever arrived after it is
+/// the start of the body.
+async fn read_head(stream: &mut TcpStream) -> std::io::Result<Option<(String, Vec<u8>)>> {
+    let mut buf = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 4096];
+    loop {
+        if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            let rest = buf.split_off(end + 4);
+            return Ok(Some((String::from_utf8_lossy(&buf).into_owned(), rest)));
+        }
+        if buf.len() > MAX_HEAD_BYTES {
+            return Ok(None);
+        }
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(None);
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+}
+
+async fn respond(stream: &mut TcpStream, status: &str, extra: &str) {
+    let response =
+        format!("HTTP/1.1 {status}\r\n{extra}Content-Length: 0\r\nConnection: close\r\n\r\n");
+    let _ = stream.write_all(response.as_bytes()).await;
+}
+
+async fn run_rules(db: &PgPool, token: &str) -> sqlx::Result<Option<(Uuid, EgressRules)>> {
+    let row = sqlx::query!(
+        r#"
+        SELECT id, egress_policy as "egress_policy: EgressPolicy", egress_allow
+        FROM jobs
+        WHERE agent_token_hash = sha256(convert_to($1, 'UTF8'))
+          AND status IN ('pending', 'running')
+        "#,
+        token
+    )
+    .fetch_optional(db)
+    .await?;
+    Ok(row.map(|r| {
+        (
+            r.id,
+            EgressRules {
+                // runs started before egress rules existed kept full access
+                policy: r.egress_policy.unwrap_or(EgressPolicy::Full),
+                allow: r.egress_allow,
+            },
+        )
+    }))
+}
+
+async fn handle_connection(db: &PgPool, mut client: TcpStream) -> anyhow::Result<()> {
+    let Some((head, body_start)) = read_head(&mut client).await? else {
+        return Ok(());
+    };
+    let Some(request) = parse_proxy_request(&head) else {
+        respond(&mut client, "400 Bad Request", "").await;
+        return Ok(());
+    };
+    let run = match request.token {
+        Some(ref token) => run_rules(db, token).await?,
+        None => None,
+    };
+    let Some((job_id, rules)) = run else {
+        respond(
+            &mut client,
+            "407 Proxy Authentication Required",
+            "Proxy-Authenticate: Basic realm=\"yui\"\r\n",
+        )
+        .await;
+        return Ok(());
+    };
+
+    if !rules.allows(&request.host) {
+        tracing::info!(job_id = %job_id, host = %request.host, "egress blocked");
+        sqlx::query!(
+            "INSERT INTO logs (job_id, stream, line) VALUES ($1, 'stderr', $2)",
+            job_id,
+            format!(
+                "egress blocked: {} {}:{}",
+                request.method, request.host, request.port
+            )
+        )
+        .execute(db)
+        .await?;
+        respond(&mut client, "403 Forbidden", "").await;
+        return Ok(());
+    }
+
+    let connect = TcpStream::connect((request.host.as_str(), request.port));
+    let mut upstream = match tokio::time::timeout(CONNECT_TIMEOUT, connect).await {
+        Ok(Ok(upstream)) => upstream,
+        _ => {
+            respond(&mut client, "502 Bad Gateway", "").await;
+            return Ok(());
+        }
+    };
+    let Some(ref forward) = request.forward else {
+        client
+            .write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n")
+            .await?;
+        if !body_start.is_empty() {
+            upstream.write_all(&body_start).await?;
+        }
+        tokio::io::copy_bidirectional(&mut client, &mut upstream).await?;
+        return Ok(());
+    };
+    // only the request that was checked goes through; the origin sees the
+    // end of it and answers, and the connection closes after that answer
+    upstream.write_all(forward.as_bytes()).await?;
+    let body = AsyncReadExt::chain(std::io::Cursor::new(body_start), &mut client);
+    forward_body(body, &mut upstream, request.body).await?;
+    upstream.shutdown().await?;
+    tokio::io::copy(&mut upstream, &mut client).await?;
+    Ok(())
+}
+
+/// Starts the forward proxy restricted runs are pointed at. Every request
+/// carries the run's agent token, which picks the rules it is held to.
+pub(crate) async fn spawn_proxy(db: &PgPool) {
+    let addr = &egress_config().proxy_addr;
+    let listener = match TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            tracing::error!(addr = %addr, error = %e, "egress proxy failed to bind");
+            return;
+        }
+    };
+    tracing::info!(addr = %addr, "egress proxy listening");
+    let db = db.clone();
+    tokio::spawn(async move {
+        loop {
+            let client = match listener.accept().await {
+                Ok((client, _)) => client,
+                Err(e) => {
+                    tracing::warn!(error = %e, "egress proxy accept failed");
+                    continue;
+                }
+            };
+            let db = db.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle_connection(&db, client).await {
+                    tracing::debug!(error = %e, "egress proxy connection ended");
+                }
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+
+    fn auth() -> String {
+        format!(
+            "Proxy-Authorization: Basic {}",
+            base64::engine::general_purpose::STANDARD.encode("yui:t0k")
+        )
+    }
+
+    #[test]
+    fn connect_requests_open_a_tunnel() {
+        let head = format!("CONNECT api.example.com:443 HTTP/1.1\r\n{}\r\n\r\n", auth());
+        let request = parse_proxy_request(&head).unwrap();
+        assert_eq!(request.host, "api.example.com");
+        assert_eq!(request.port, 443);
+        assert_eq!(request.token.as_deref(), Some("t0k"));
+        assert!(request.forward.is_none());
+
+        let request = parse_proxy_request("CONNECT [::1]:8443 HTTP/1.1\r\n\r\n").unwrap();
+        assert_eq!((request.host.as_str(), request.port), ("::1", 8443));
+        assert!(request.token.is_none());
+    }
+
+    #[test]
+    fn plain_requests_are_rewritten_for_the_origin() {
+        let head = format!(
+            "GET http://Example.com:8080/a?b=1 HTTP/1.1\r\nHost: example.com:8080\r\n{}\r\n\
+             Proxy-Connection: keep-alive\r\nAccept: */*\r\n\r\n",
+            auth()
+        );
+        let request = parse_proxy_request(&head).unwrap();
+        assert_eq!((request.host.as_str(), request.port), ("example.com", 8080));
+        assert_eq!(
+            request.forward.as_deref(),
+            Some(
+                "GET /a?b=1 HTTP/1.1\r\nHost: example.com:8080\r\nAccept: */*\r\n\
+                 Connection: close\r\n\r\n"
+            )
+        );
+
+        // a virtual host other than the one the rules were checked against
+        // never reaches the origin
+        let head = "GET http://allowed.example.com/ HTTP/1.1\r\nHost: internal.example.com\r\n\r\n";
+        let request = parse_proxy_request(head).unwrap();
+        assert_eq!(
+            request.forward.as_deref(),
+            Some("GET / HTTP/1.1\r\nHost: allowed.example.com\r\nConnection: close\r\n\r\n")
+        );
+
+        assert!(parse_proxy_request("GET /relative HTTP/1.1\r\n\r\n").is_none());
+        assert!(parse_proxy_request("GET ftp://example.com/ HTTP/1.1\r\n\r\n").is_none());
+    }
+
+    #[test]
+    fn request_bodies_are_framed_one_way_only() {
+        let body = |headers: &str| {
+            let head = format!("POST http://example.com/ HTTP/1.1\r\n{headers}\r\n");
+            parse_proxy_request(&head).map(|r| r.body)
+        };
+        assert_eq!(body(""), Some(BodyLength::Bytes(0)));
+        assert_eq!(body("Content-Length: 5\r\n"), Some(BodyLength::Bytes(5)));
+        assert_eq!(
+            body("Transfer-Encoding: gzip, chunked\r\n"),
+            Some(BodyLength::Chunked)
+        );
+        assert_eq!(
+            body("Content-Length: 5\r\nTransfer-Encoding: chunked\r\n"),
+            None
+        );
+        assert_eq!(body("Content-Length: 5\r\nContent-Length: 6\r\n"), None);
+        assert_eq!(body("Content-Length: -1\r\n"), None);
+        assert_eq!(body("Transfer-Encoding: gzip\r\n"), None);
+    }
+
+    #[tokio::test]
+    async fn pipelined_requests_never_reach_the_origin() {
+        let smuggled = "GET http://internal.example.com/ HTTP/1.1\r\n\r\n";
+
+        let client = format!("hello{smuggled}");
+        let mut upstream = vec![];
+        forward_body(client.as_bytes(), &mut upstream, BodyLength::Bytes(5))
+            .await
+            .unwrap();
+        assert_eq!(upstream, b"hello");
+
+        let chunks = "5;ext=1\r\nhello\r\n1\r\n!\r\n0\r\nX-Trailer: 1\r\n\r\n";
+        let client = format!("{chunks}{smuggled}");
+        let mut upstream = vec![];
+        forward_body(client.as_bytes(), &mut upstream, BodyLength::Chunked)
+            .await
+            .unwrap();
+        assert_eq!(upstream, chunks.as_bytes());
+
+        let mut upstream = vec![];
+        let short = forward_body(&b"hel"[..], &mut upstream, BodyLength::Bytes(5)).await;
+        assert!(short.is_err());
+        let bad = forward_body(&b"zz\r\n"[..], &mut upstream, BodyLength::Chunked).await;
+        assert!(bad.is_err());
+    }
+}
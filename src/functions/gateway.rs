@@ -1,4 +1,4 @@
-use crate::services::AiService;
+use crate::services::{AiService, metrics};
 use forge::prelude::*;
 use sqlx::PgPool;
 use std::collections::HashMap;
@@ -25,6 +25,7 @@ struct BufferedMessage {
     platform_sender_id: String,
     content: Option<String>,
     attachments: serde_json::Value,
+    received_at: chrono::DateTime<chrono::Utc>,
 }
 
 struct TypingBuffer {
@@ -48,7 +49,10 @@ impl TypingBuffer {
             .iter_mut()
             .find(|m| m.platform_id == message.platform_id)
         {
+            // an edit replaces the content but the buffer wait counts from first receipt
+            let received_at = existing.received_at;
             *existing = message;
+            existing.received_at = received_at;
         } else {
             self.messages.push(message);
         }
@@ -105,8 +109,8 @@ async fn flush_buffer(
 
         sqlx::query!(
             r#"
-            INSERT INTO messages (platform_id, platform_chat_id, platform_sender_id, direction, content, attachments, embedding, trace_id)
-            VALUES ($1, $2, $3, 'in', $4, $5, $6::vector, $7)
+            INSERT INTO messages (platform_id, platform_chat_id, platform_sender_id, direction, content, attachments, embedding, trace_id, received_at)
+            VALUES ($1, $2, $3, 'in', $4, $5, $6::vector, $7, $8)
             ON CONFLICT (platform_id) DO UPDATE SET
                 content = EXCLUDED.content,
                 attachments = EXCLUDED.attachments,
@@ -132,7 +136,8 @@ async fn flush_buffer(
             msg.content,
             msg.attachments,
             embedding.as_deref() as Option<&[f32]>,
-            trace_id
+            trace_id,
+            msg.received_at
         )
         .execute(&mut *tx)
         .await?;
@@ -150,6 +155,7 @@ async fn flush_buffer(
     .await?;
 
     tx.commit().await?;
+    metrics::record_items("gateway", messages.len());
     tracing::info!(chat_id, count = messages.len(), "flushed inbound buffer");
     Ok(())
 }
@@ -305,6 +311,7 @@ pub async fn gateway(ctx: &DaemonContext) -> Result<()> {
                             platform_sender_id: sender_id,
                             content: text,
                             attachments: serde_json::json!(attachments),
+                            received_at: chrono::Utc::now(),
                         };
 
                         let now = tokio::time::Instant::now();
@@ -419,7 +426,7 @@ pub async fn gateway(ctx: &DaemonContext) -> Result<()> {
                         WHERE o.processed_at IS NULL
                           AND NOT EXISTS (
                             SELECT 1 FROM jobs j
-                            WHERE j.id = o.job_id AND j.status = 'paused'
+                            WHERE j.id = o.job_id AND j.status IN ('paused', 'awaiting_approval')
                           )
                     ) active
                     "#
@@ -454,6 +461,7 @@ mod tests {
             platform_sender_id: "sender".to_string(),
             content: Some(content.to_string()),
             attachments: serde_json::json!([]),
+            received_at: chrono::Utc::now(),
         }
     }
 
@@ -499,6 +507,21 @@ mod tests {
         assert_eq!(buffer.messages[0].content.as_deref(), Some("hello edited"));
     }
 
+    #[test]
+    fn upsert_keeps_first_receipt_time() {
+        let t0 = tokio::time::Instant::now();
+        let mut buffer = TypingBuffer::new(t0);
+        let first = make_message("same", "hello");
+        let received_at = first.received_at;
+        buffer.upsert_message(first, t0);
+
+        let mut edited = make_message("same", "hello edited");
+        edited.received_at = received_at + chrono::Duration::seconds(30);
+        buffer.upsert_message(edited, t0 + Duration::from_secs(30));
+
+        assert_eq!(buffer.messages[0].received_at, received_at);
+    }
+
     #[test]
     fn message_clears_typing_flag_so_buffer_can_flush() {
         let t0 = tokio::time::Instant::now();
@@ -0,0 +1,156 @@
+use crate::functions::claim::instance_id;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+/// Loops whose silence means user messages stop flowing. Readiness fails until
+/// each of them has ticked, and again whenever one of them stalls.
+pub const CRITICAL_DAEMONS: &[&str] = &["triage", "context", "reply", "delivery"];
+
+/// How often a loop rewrites its heartbeat row. Ticks in between only update
+/// the in-process state that readiness reads.
+const WRITE_INTERVAL: Duration = Duration::from_secs(15);
+
+static LAST_TICKS: LazyLock<Mutex<HashMap<&'static str, Instant>>> =
+    LazyLock::new(Default::default);
+
+/// Tracks one daemon's liveness in `daemon_heartbeats` (one row per daemon
+/// per instance) and in the process-local tick map behind `/readyz`.
+pub struct Heartbeat {
+    daemon: &'static str,
+    started_at: chrono::DateTime<chrono::Utc>,
+    items_processed: i64,
+    pending_error: Option<String>,
+    last_write: Option<Instant>,
+}
+
+impl Heartbeat {
+    pub fn new(daemon: &'static str) -> Self {
+        Self {
+            daemon,
+            started_at: chrono::Utc::now(),
+            items_processed: 0,
+            pending_error: None,
+            last_write: None,
+        }
+    }
+
+    pub async fn record<E: std::fmt::Display>(&mut self, db: &PgPool, result: &Result<u32, E>) {
+        let now = Instant::now();
+        if let Ok(mut ticks) = LAST_TICKS.lock() {
+            ticks.insert(self.daemon, now);
+        }
+
+        match result {
+            Ok(n) => self.items_processed += i64::from(*n),
+            Err(e) => self.pending_error = Some(e.to_string()),
+        }
+
+        let due = self
+            .last_write
+            .is_none_or(|at| now.duration_since(at) >= WRITE_INTERVAL);
+        if !due && self.pending_error.is_none() {
+            return;
+        }
+
+        match self.write(db).await {
+            Ok(()) => {
+                self.last_write = Some(now);
+                self.pending_error = None;
+            }
+            Err(e) => tracing::warn!(daemon = self.daemon, error = %e, "heartbeat write failed"),
+        }
+    }
+
+    async fn write(&self, db: &PgPool) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO daemon_heartbeats (
+                daemon, instance_id, host, version, started_at, last_tick_at,
+                last_error, last_error_at, items_processed
+            )
+            VALUES ($1, $2, $3, $4, $5, now(), $6, CASE WHEN $6::text IS NULL THEN NULL ELSE now() END, $7)
+            ON CONFLICT (daemon, instance_id) DO UPDATE SET
+                host = EXCLUDED.host,
+                version = EXCLUDED.version,
+                started_at = EXCLUDED.started_at,
+                last_tick_at = now(),
+                last_error = COALESCE(EXCLUDED.last_error, daemon_heartbeats.last_error),
+                last_error_at = COALESCE(EXCLUDED.last_error_at, daemon_heartbeats.last_error_at),
+                items_processed = EXCLUDED.items_processed
+            "#,
+            self.daemon,
+            instance_id(),
+            std::env::var("HOSTNAME").ok(),
+            env!("CARGO_PKG_VERSION"),
+            self.started_at,
+            self.pending_error,
+            self.items_processed
+        )
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+}
+
+/// Critical loops in this process that have not ticked within `max_age`,
+/// including ones that have not ticked at all yet.
+pub fn stale_critical_daemons(max_age: Duration) -> Vec<&'static str> {
+    let Ok(ticks) = LAST_TICKS.lock() else {
+        return CRITICAL_DAEMONS.to_vec();
+    };
+    stale_in(&ticks, Instant::now(), max_age)
+}
+
+fn stale_in(
+    ticks: &HashMap<&'static str, Instant>,
+    now: Instant,
+    max_age: Duration,
+) -> Vec<&'static str> {
+    CRITICAL_DAEMONS
+        .iter()
+        .copied()
+        .filter(|daemon| {
+            ticks
+                .get(daemon)
+                .is_none_or(|at| now.duration_since(*at) > max_age)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn critical_loop_without_a_tick_is_stale() {
+        let now = Instant::now();
+        let ticks: HashMap<&'static str, Instant> =
+            [("triage", now), ("context", now), ("reply", now)].into();
+
+        assert_eq!(
+            stale_in(&ticks, now, Duration::from_secs(60)),
+            vec!["delivery"]
+        );
+    }
+
+    #[test]
+    fn critical_loop_past_max_age_is_stale() {
+        let t0 = Instant::now();
+        let later = t0 + Duration::from_secs(90);
+        let ticks: HashMap<&'static str, Instant> = [
+            ("triage", later),
+            ("context", later),
+            ("reply", t0),
+            ("delivery", later),
+        ]
+        .into();
+
+        assert_eq!(
+            stale_in(&ticks, later, Duration::from_secs(60)),
+            vec!["reply"]
+        );
+        assert!(stale_in(&ticks, later, Duration::from_secs(120)).is_empty());
+    }
+}
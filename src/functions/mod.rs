@@ -1,12 +1,19 @@
+pub mod agent_api;
+pub mod artifacts;
 pub mod audit;
+pub mod claim;
 pub mod clock;
 pub mod context;
 pub mod dashboard;
 pub mod delivery;
+pub mod egress;
 pub mod gateway;
+pub mod heartbeat;
 pub mod reply;
 pub mod runtime;
 pub mod triage;
+pub mod wakeup;
+pub mod workspaces;
 
 pub use audit::*;
 pub use clock::*;
@@ -1,4 +1,7 @@
-use crate::services::AiService;
+use crate::functions::claim::instance_id;
+use crate::functions::heartbeat::Heartbeat;
+use crate::functions::wakeup::{OUTBOX_CHANNEL, Wakeup};
+use crate::services::{AiService, metrics};
 use forge::prelude::*;
 use sqlx::PgPool;
 use std::sync::Arc;
@@ -121,13 +124,23 @@ pub async fn reply_tick(db: &PgPool, ai: &dyn AiService) -> Result<u32> {
     let pending = sqlx::query_as!(
         PendingRewrite,
         r#"
+        WITH claimed AS (
+            UPDATE outbox SET claimed_by = $1, lease_until = now() + interval '2 minutes'
+            WHERE id IN (
+                SELECT id FROM outbox
+                WHERE rewritten_at IS NULL AND processed_at IS NULL
+                  AND (lease_until IS NULL OR lease_until < now())
+                ORDER BY created_at
+                LIMIT 10
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, chat_id, content, created_at
+        )
         SELECT id, chat_id, content
-        FROM outbox
-        WHERE rewritten_at IS NULL AND processed_at IS NULL
+        FROM claimed
         ORDER BY created_at
-        LIMIT 10
-        FOR UPDATE SKIP LOCKED
-        "#
+        "#,
+        instance_id()
     )
     .fetch_all(db)
     .await?;
@@ -148,7 +161,7 @@ pub async fn reply_tick(db: &PgPool, ai: &dyn AiService) -> Result<u32> {
         let Some(ref content) = entry.content else {
             tracing::debug!(outbox_id = %entry.id, "reply: media-only entry, skipping rewrite");
             sqlx::query!(
-                "UPDATE outbox SET rewritten_at = now() WHERE id = $1",
+                "UPDATE outbox SET rewritten_at = now(), claimed_by = NULL, lease_until = NULL WHERE id = $1",
                 entry.id
             )
             .execute(db)
@@ -162,7 +175,7 @@ pub async fn reply_tick(db: &PgPool, ai: &dyn AiService) -> Result<u32> {
             let reason = if skip_rewrite { "llm_disabled" } else { "format_preserved" };
             tracing::debug!(outbox_id = %entry.id, reason, "reply: skipping rewrite");
             sqlx::query!(
-                "UPDATE outbox SET content = $2, rewritten_at = now() WHERE id = $1",
+                "UPDATE outbox SET content = $2, rewritten_at = now(), claimed_by = NULL, lease_until = NULL WHERE id = $1",
                 entry.id,
                 final_text
             )
@@ -214,7 +227,7 @@ pub async fn reply_tick(db: &PgPool, ai: &dyn AiService) -> Result<u32> {
             let final_text = segments.first().copied().unwrap_or(content);
             let final_text = sanitize_reply_text(final_text);
             sqlx::query!(
-                "UPDATE outbox SET content = $2, rewritten_at = now() WHERE id = $1",
+                "UPDATE outbox SET content = $2, rewritten_at = now(), claimed_by = NULL, lease_until = NULL WHERE id = $1",
                 entry.id,
                 final_text
             )
@@ -228,7 +241,7 @@ pub async fn reply_tick(db: &PgPool, ai: &dyn AiService) -> Result<u32> {
                     .await?;
 
             sqlx::query!(
-                "UPDATE outbox SET content = $2, rewritten_at = now() WHERE id = $1",
+                "UPDATE outbox SET content = $2, rewritten_at = now(), claimed_by = NULL, lease_until = NULL WHERE id = $1",
                 entry.id,
                 segments[0]
             )
@@ -261,12 +274,16 @@ pub async fn reply_tick(db: &PgPool, ai: &dyn AiService) -> Result<u32> {
 pub async fn reply(ctx: &DaemonContext) -> Result<()> {
     let ai: Arc<dyn AiService> = crate::get_ai_service();
     let poll_ms: u64 = ctx.env_parse("YUI_LOOP_POLL_MS_REPLY").unwrap_or(300);
+    let mut wakeup = Wakeup::listen(ctx.db(), &[OUTBOX_CHANNEL]).await;
+    let mut heartbeat = Heartbeat::new("reply");
 
     loop {
         tokio::select! {
             _ = ctx.shutdown_signal() => break,
-            _ = tokio::time::sleep(std::time::Duration::from_millis(poll_ms)) => {
-                match reply_tick(ctx.db(), ai.as_ref()).await {
+            _ = wakeup.wait(std::time::Duration::from_millis(poll_ms)) => {
+                let result = metrics::observe_tick("reply", reply_tick(ctx.db(), ai.as_ref()).await);
+                heartbeat.record(ctx.db(), &result).await;
+                match result {
                     Ok(n) if n > 0 => tracing::info!(processed = n, "reply tick"),
                     Err(e) => tracing::error!(error = %e, "reply tick failed"),
                     _ => {}
@@ -1,19 +1,40 @@
+use crate::functions::agent_api;
+use crate::functions::artifacts;
+use crate::functions::claim::instance_id;
+use crate::functions::clock::should_notify;
+use crate::functions::egress;
+use crate::functions::heartbeat::Heartbeat;
+use crate::functions::wakeup::{JOBS_CHANNEL, Wakeup};
+use crate::functions::workspaces::{self, WorkspaceLock};
+use crate::schema::{Attachment, CronNotifyPolicy, LogStream, SecretInjection};
 use crate::services::{
-    AgentExecutor, AgentRunnerService, ExecutionInput, ExecutionOutcome, OpenRouterAgentRunner,
-    RunnerEvent, RunnerHandle, RunnerStartInput,
+    AgentExecutor, AgentRunnerService, ExecutionInput, ExecutionOutcome, ExecutorEvent,
+    FrameOffset, OpenRouterAgentRunner, ProcessAgentRunner, ProcessRunnerConfig, Redactor,
+    RunnerEvent, RunnerHandle, RunnerStartInput, SecretValue, StepReport, container_name,
+    egress_config, job_profile, metrics, project_path, secrets_key, summarize_step,
 };
 use forge::prelude::*;
 use sqlx::PgPool;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock, Mutex};
 use uuid::Uuid;
 
+/// Redactors for the secrets handed to each active run, so nothing a run
+/// reports is stored or sent with a secret value in it.
+static REDACTORS: LazyLock<Mutex<HashMap<Uuid, Redactor>>> = LazyLock::new(Default::default);
+
 struct PendingJob {
     id: Uuid,
+    kind: String,
     chat_id: String,
     enriched_prompt: Option<String>,
     prompt: Option<String>,
     resume_input: Option<String>,
+    session_id: Option<String>,
+    source_ids: Vec<Uuid>,
+    resource_profile: Option<String>,
+    workspace_id: Option<Uuid>,
     trace_id: Option<Uuid>,
 }
 
@@ -36,6 +57,36 @@ async fn fetch_job_context(db: &PgPool, job_id: Uuid) -> Result<Option<JobContex
     }))
 }
 
+struct CronRunContext {
+    cron_id: Uuid,
+    notify_policy: CronNotifyPolicy,
+    previous_output: Option<String>,
+}
+
+/// Loads the notify policy and the previous run's output for jobs fired by a cron.
+async fn fetch_cron_run_context(db: &PgPool, job_id: Uuid) -> Result<Option<CronRunContext>> {
+    let row = sqlx::query!(
+        r#"
+        SELECT c.id, c.notify_policy as "notify_policy: CronNotifyPolicy",
+               (SELECT p.output FROM jobs p
+                WHERE p.cron_id = c.id AND p.id != j.id AND p.status = 'done'
+                ORDER BY p.finished_at DESC NULLS LAST
+                LIMIT 1) as previous_output
+        FROM jobs j
+        JOIN crons c ON c.id = j.cron_id
+        WHERE j.id = $1
+        "#,
+        job_id
+    )
+    .fetch_optional(db)
+    .await?;
+    Ok(row.map(|r| CronRunContext {
+        cron_id: r.id,
+        notify_policy: r.notify_policy,
+        previous_output: r.previous_output,
+    }))
+}
+
 async fn insert_runtime_event(
     db: &PgPool,
     trace_id: Uuid,
@@ -56,7 +107,7 @@ async fn insert_runtime_event(
     Ok(())
 }
 
-async fn insert_outbox_text(
+pub(crate) async fn insert_outbox_text(
     db: &PgPool,
     chat_id: &str,
     text: &str,
@@ -78,7 +129,7 @@ async fn insert_outbox_text(
     Ok(())
 }
 
-async fn insert_outbox_with_attachments(
+pub(crate) async fn insert_outbox_with_attachments(
     db: &PgPool,
     chat_id: &str,
     text: &str,
@@ -102,28 +153,174 @@ async fn insert_outbox_with_attachments(
     Ok(())
 }
 
+/// Writes a poll's worth of log lines in one statement.
+async fn insert_logs(db: &PgPool, job_id: Uuid, lines: Vec<(&'static str, String)>) -> Result<()> {
+    if lines.is_empty() {
+        return Ok(());
+    }
+    let (streams, lines): (Vec<&str>, Vec<String>) = lines.into_iter().unzip();
+    // rows in one statement share now(); offsetting by position keeps the
+    // dashboard's created_at ordering identical to the order lines were printed
+    sqlx::query!(
+        r#"
+        INSERT INTO logs (job_id, stream, line, created_at)
+        SELECT $1, stream, line, now() + ord * interval '1 microsecond'
+        FROM UNNEST($2::text[], $3::text[]) WITH ORDINALITY AS t(stream, line, ord)
+        "#,
+        job_id,
+        &streams as &[&str],
+        &lines
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// At most one progress message per job per interval, and only a few per job,
+/// so a chatty agent cannot flood the chat.
+const PROGRESS_MIN_INTERVAL_SECS: f64 = 60.0;
+const PROGRESS_MAX_MESSAGES: i32 = 5;
+
+/// Keeps the latest progress on the job for the dashboard and triage, and
+/// forwards it to the user when the throttle allows. Cron jobs stay quiet.
+pub(crate) async fn record_progress(db: &PgPool, job_id: Uuid, message: &str) -> Result<()> {
+    let notify = sqlx::query!(
+        r#"
+        UPDATE jobs SET progress = $2, progress_at = now(),
+                        progress_notified_at = CASE WHEN notify THEN now() ELSE progress_notified_at END,
+                        progress_messages = progress_messages + CASE WHEN notify THEN 1 ELSE 0 END
+        FROM (
+            SELECT id AS job_id,
+                   kind <> 'schedule'
+                   AND progress_messages < $4
+                   AND (progress_notified_at IS NULL
+                        OR progress_notified_at < now() - make_interval(secs => $3)) AS notify
+            FROM jobs WHERE id = $1 AND status = 'running'
+            FOR UPDATE
+        ) throttle
+        WHERE jobs.id = throttle.job_id
+        RETURNING throttle.notify as "notify!", jobs.chat_id, jobs.trace_id
+        "#,
+        job_id,
+        message,
+        PROGRESS_MIN_INTERVAL_SECS,
+        PROGRESS_MAX_MESSAGES
+    )
+    .fetch_optional(db)
+    .await?;
+
+    let Some(row) = notify.filter(|row| row.notify) else {
+        return Ok(());
+    };
+    let trace_id = trace_id_or_new(row.trace_id);
+    insert_outbox_text(db, &row.chat_id, message, job_id, trace_id).await?;
+    insert_runtime_event(
+        db,
+        trace_id,
+        "job_progress",
+        serde_json::json!({ "job_id": job_id, "message": message }),
+    )
+    .await
+}
+
+/// Appends a step after the job's last one, so a resumed run keeps numbering
+/// where the paused one stopped.
+async fn insert_step(db: &PgPool, job_id: Uuid, step: StepReport) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO agent_steps (job_id, step_number, tool_name, input_summary, output_summary, duration_ms)
+        SELECT $1, COALESCE(MAX(step_number), 0) + 1, $2, $3, $4, $5
+        FROM agent_steps WHERE job_id = $1
+        "#,
+        job_id,
+        step.tool_name,
+        step.input_summary.as_deref().map(summarize_step),
+        step.output_summary.as_deref().map(summarize_step),
+        step.duration_ms
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Delay before the first retry; doubles with every further attempt.
+const RETRY_BASE_DELAY_SECS: f64 = 30.0;
+const RETRY_MAX_DELAY_SECS: f64 = 1800.0;
+
+struct ScheduledRetry {
+    attempt_count: i32,
+    retry_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Puts a failed run back in the queue with exponential backoff, unless the
+/// job has used up its attempts. The runner fields are cleared so nothing
+/// tries to reattach to the dead run.
+async fn schedule_retry(db: &PgPool, job_id: Uuid, error: &str) -> Result<Option<ScheduledRetry>> {
+    let retry = sqlx::query_as!(
+        ScheduledRetry,
+        r#"
+        UPDATE jobs SET status = 'pending', error = $2,
+                        retry_at = now() + make_interval(
+                            secs => least($3 * power(2, greatest(attempt_count - 1, 0)), $4)
+                        ),
+                        claimed_by = NULL, lease_until = NULL,
+                        runner_backend = NULL, runner_run_id = NULL, container_id = NULL
+        WHERE id = $1 AND status = 'running' AND attempt_count < max_attempts
+        RETURNING attempt_count, retry_at as "retry_at!"
+        "#,
+        job_id,
+        error,
+        RETRY_BASE_DELAY_SECS,
+        RETRY_MAX_DELAY_SECS
+    )
+    .fetch_optional(db)
+    .await?;
+    Ok(retry)
+}
+
 async fn handle_runner_event(db: &PgPool, job_id: Uuid, event: RunnerEvent) -> Result<bool> {
     match event {
         RunnerEvent::Stdout(line) => {
+            insert_logs(db, job_id, vec![("stdout", line)]).await?;
+            Ok(false)
+        }
+        RunnerEvent::Stderr(line) => {
+            insert_logs(db, job_id, vec![("stderr", line)]).await?;
+            Ok(false)
+        }
+        RunnerEvent::Session { session_id } => {
             sqlx::query!(
-                "INSERT INTO logs (job_id, stream, line) VALUES ($1, 'stdout', $2)",
+                "UPDATE jobs SET session_id = $2 WHERE id = $1",
                 job_id,
-                line
+                session_id
             )
             .execute(db)
             .await?;
             Ok(false)
         }
-        RunnerEvent::Stderr(line) => {
+        RunnerEvent::Seen(seen) => {
             sqlx::query!(
-                "INSERT INTO logs (job_id, stream, line) VALUES ($1, 'stderr', $2)",
+                r#"
+                UPDATE jobs SET stdout_frames_seen = GREATEST(stdout_frames_seen, $2),
+                                stderr_lines_seen = GREATEST(stderr_lines_seen, $3)
+                WHERE id = $1
+                "#,
                 job_id,
-                line
+                seen.stdout as i64,
+                seen.stderr as i64
             )
             .execute(db)
             .await?;
             Ok(false)
         }
+        RunnerEvent::Step(step) => {
+            insert_step(db, job_id, step).await?;
+            Ok(false)
+        }
+        RunnerEvent::Progress { message } => {
+            record_progress(db, job_id, &message).await?;
+            Ok(false)
+        }
         RunnerEvent::AskUser { question } => {
             tracing::info!(job_id = %job_id, "runtime: job asking user for input");
             if let Some(ctx) = fetch_job_context(db, job_id).await? {
@@ -178,17 +375,43 @@ async fn handle_runner_event(db: &PgPool, job_id: Uuid, event: RunnerEvent) -> R
             )
             .execute(db)
             .await?;
+            artifacts::record_artifacts(db, job_id, &attachments).await?;
 
             if let Some(ctx) = fetch_job_context(db, job_id).await? {
-                insert_outbox_with_attachments(
-                    db,
-                    &ctx.chat_id,
-                    &output,
-                    attachments,
-                    job_id,
-                    ctx.trace_id,
-                )
-                .await?;
+                let cron_run = fetch_cron_run_context(db, job_id).await?;
+                let notify = cron_run.as_ref().is_none_or(|run| {
+                    should_notify(&run.notify_policy, run.previous_output.as_deref(), &output)
+                });
+
+                if notify {
+                    insert_outbox_with_attachments(
+                        db,
+                        &ctx.chat_id,
+                        &output,
+                        attachments,
+                        job_id,
+                        ctx.trace_id,
+                    )
+                    .await?;
+                } else if let Some(run) = cron_run {
+                    tracing::info!(
+                        job_id = %job_id,
+                        cron_id = %run.cron_id,
+                        notify_policy = ?run.notify_policy,
+                        "runtime: suppressing cron output"
+                    );
+                    insert_runtime_event(
+                        db,
+                        ctx.trace_id,
+                        "cron_output_suppressed",
+                        serde_json::json!({
+                            "job_id": job_id,
+                            "cron_id": run.cron_id,
+                            "notify_policy": run.notify_policy,
+                        }),
+                    )
+                    .await?;
+                }
 
                 insert_runtime_event(
                     db,
@@ -200,35 +423,60 @@ async fn handle_runner_event(db: &PgPool, job_id: Uuid, event: RunnerEvent) -> R
             }
             Ok(true)
         }
-        RunnerEvent::Failed { error } => {
+        RunnerEvent::Failed { error, retryable } => {
+            if retryable && let Some(retry) = schedule_retry(db, job_id, &error).await? {
+                tracing::warn!(
+                    job_id = %job_id,
+                    error = %error,
+                    attempt = retry.attempt_count,
+                    retry_at = %retry.retry_at,
+                    "runtime: job failed, retry scheduled"
+                );
+                if let Some(ctx) = fetch_job_context(db, job_id).await? {
+                    insert_runtime_event(
+                        db,
+                        ctx.trace_id,
+                        "job_retry_scheduled",
+                        serde_json::json!({
+                            "job_id": job_id,
+                            "error": error,
+                            "attempt": retry.attempt_count,
+                            "retry_at": retry.retry_at,
+                        }),
+                    )
+                    .await?;
+                }
+                return Ok(true);
+            }
+
             tracing::error!(job_id = %job_id, error = %error, "runtime: job failed");
 
-            sqlx::query!(
+            let attempts = sqlx::query_scalar!(
                 r#"
                 UPDATE jobs SET status = 'failed', error = $2, finished_at = now()
                 WHERE id = $1
+                RETURNING attempt_count
                 "#,
                 job_id,
                 error
             )
-            .execute(db)
-            .await?;
+            .fetch_optional(db)
+            .await?
+            .unwrap_or(1);
 
             if let Some(ctx) = fetch_job_context(db, job_id).await? {
-                insert_outbox_text(
-                    db,
-                    &ctx.chat_id,
-                    &format!("task failed: {error}"),
-                    job_id,
-                    ctx.trace_id,
-                )
-                .await?;
+                let text = if attempts > 1 {
+                    format!("task failed after {attempts} attempts: {error}")
+                } else {
+                    format!("task failed: {error}")
+                };
+                insert_outbox_text(db, &ctx.chat_id, &text, job_id, ctx.trace_id).await?;
 
                 insert_runtime_event(
                     db,
                     ctx.trace_id,
                     "job_failed",
-                    serde_json::json!({ "job_id": job_id, "error": error }),
+                    serde_json::json!({ "job_id": job_id, "error": error, "attempts": attempts }),
                 )
                 .await?;
             }
@@ -237,72 +485,534 @@ async fn handle_runner_event(db: &PgPool, job_id: Uuid, event: RunnerEvent) -> R
     }
 }
 
+/// Attachments of the messages a job was created from, in the order they arrived.
+async fn job_attachments(db: &PgPool, source_ids: &[Uuid]) -> Result<Vec<Attachment>> {
+    if source_ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let rows = sqlx::query_scalar!(
+        r#"
+        SELECT attachments FROM messages
+        WHERE id = ANY($1) AND attachments <> '[]'::jsonb
+        ORDER BY created_at
+        "#,
+        source_ids
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|value| serde_json::from_value::<Vec<Attachment>>(value).ok())
+        .flatten()
+        .collect())
+}
+
+/// Longest output of one dependency copied into the prompt of a job that
+/// waited for it.
+const DEPENDENCY_OUTPUT_CHARS: usize = 4000;
+
+struct DependencyResult {
+    prompt: Option<String>,
+    output: Option<String>,
+}
+
+/// Appends what the job's dependencies produced, in the order they were
+/// created, so a later plan step can build on the earlier ones.
+fn prompt_with_dependencies(prompt: String, deps: &[DependencyResult]) -> String {
+    if deps.is_empty() {
+        return prompt;
+    }
+    let mut prompt = prompt;
+    prompt.push_str("\n\nResults of the tasks this one waited for:");
+    for (i, dep) in deps.iter().enumerate() {
+        let task = dep.prompt.as_deref().unwrap_or("(no prompt)");
+        let output = dep.output.as_deref().unwrap_or("(no output)");
+        let mut output: String = output.chars().take(DEPENDENCY_OUTPUT_CHARS).collect();
+        if dep
+            .output
+            .as_ref()
+            .is_some_and(|o| o.chars().count() > DEPENDENCY_OUTPUT_CHARS)
+        {
+            output.push_str(" [truncated]");
+        }
+        prompt.push_str(&format!("\n\n{}. Task: {task}\nResult: {output}", i + 1));
+    }
+    prompt
+}
+
+/// Outputs and delivered files of the jobs this one depends on.
+async fn dependency_results(
+    db: &PgPool,
+    job_id: Uuid,
+) -> Result<(Vec<DependencyResult>, Vec<Attachment>)> {
+    let deps = sqlx::query_as!(
+        DependencyResult,
+        r#"
+        SELECT d.prompt, d.output
+        FROM jobs j
+        JOIN jobs d ON d.id = ANY(j.depends_on)
+        WHERE j.id = $1
+        ORDER BY d.created_at, d.id
+        "#,
+        job_id
+    )
+    .fetch_all(db)
+    .await?;
+    if deps.is_empty() {
+        return Ok((deps, vec![]));
+    }
+
+    let files = sqlx::query_scalar!(
+        r#"
+        SELECT o.attachments as "attachments!"
+        FROM jobs j
+        JOIN outbox o ON o.job_id = ANY(j.depends_on)
+        WHERE j.id = $1 AND o.attachments <> '[]'::jsonb
+        ORDER BY o.created_at
+        "#,
+        job_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    let attachments = files
+        .into_iter()
+        .filter_map(|value| serde_json::from_value::<Vec<Attachment>>(value).ok())
+        .flatten()
+        .collect();
+    Ok((deps, attachments))
+}
+
+/// Cancels jobs that can never start because a job they depend on, directly
+/// or through another dependent, failed or was cancelled. Tells each chat
+/// once per failed dependency.
+async fn cancel_blocked_dependents(db: &PgPool) -> Result<()> {
+    let cancelled = sqlx::query!(
+        r#"
+        WITH RECURSIVE blocked AS (
+            SELECT j.id, d.id AS cause_id, d.status AS cause_status
+            FROM jobs j
+            JOIN jobs d ON d.id = ANY(j.depends_on)
+            WHERE j.status IN ('draft', 'pending', 'paused', 'awaiting_approval')
+              AND d.status IN ('failed', 'cancelled')
+            UNION
+            SELECT j.id, b.cause_id, b.cause_status
+            FROM jobs j
+            JOIN blocked b ON b.id = ANY(j.depends_on)
+            WHERE j.status IN ('draft', 'pending', 'paused', 'awaiting_approval')
+        ), first_cause AS (
+            SELECT DISTINCT ON (id) id, cause_id, cause_status
+            FROM blocked
+            ORDER BY id, cause_id
+        )
+        UPDATE jobs j SET status = 'cancelled', finished_at = now(), claimed_by = NULL,
+                          lease_until = NULL,
+                          cancel_reason = 'dependency ' || f.cause_id || ' ' || f.cause_status
+        FROM first_cause f
+        WHERE j.id = f.id AND j.status IN ('draft', 'pending', 'paused', 'awaiting_approval')
+        RETURNING j.id, j.chat_id, j.trace_id, f.cause_id as "cause_id!",
+                  f.cause_status as "cause_status!"
+        "#
+    )
+    .fetch_all(db)
+    .await?;
+
+    let mut notified = std::collections::HashSet::new();
+    for job in cancelled {
+        let trace_id = trace_id_or_new(job.trace_id);
+        tracing::info!(
+            job_id = %job.id,
+            cause_id = %job.cause_id,
+            "runtime: cancelling job whose dependency did not finish"
+        );
+        insert_runtime_event(
+            db,
+            trace_id,
+            "job_dependency_cancelled",
+            serde_json::json!({
+                "job_id": job.id,
+                "cause_id": job.cause_id,
+                "cause_status": job.cause_status,
+            }),
+        )
+        .await?;
+        if notified.insert((job.chat_id.clone(), job.cause_id)) {
+            let text = if job.cause_status == "failed" {
+                "skipping the follow-up steps, an earlier step failed"
+            } else {
+                "skipping the follow-up steps, an earlier step was cancelled"
+            };
+            insert_outbox_text(db, &job.chat_id, text, job.id, trace_id).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Cancels jobs whose approval question went unanswered for too long, and
+/// says so in the chat that was asked.
+async fn expire_approvals(db: &PgPool) -> Result<()> {
+    let expired = sqlx::query!(
+        r#"
+        UPDATE jobs SET status = 'cancelled', finished_at = now(),
+                        cancel_reason = 'approval timed out', approval_expires_at = NULL
+        WHERE status = 'awaiting_approval' AND approval_expires_at < now()
+        RETURNING id, chat_id, trace_id, prompt
+        "#
+    )
+    .fetch_all(db)
+    .await?;
+
+    for job in expired {
+        let trace_id = trace_id_or_new(job.trace_id);
+        tracing::info!(job_id = %job.id, "runtime: approval timed out");
+        insert_runtime_event(
+            db,
+            trace_id,
+            "job_approval_expired",
+            serde_json::json!({ "job_id": job.id }),
+        )
+        .await?;
+        let preview: String = job.prompt.unwrap_or_default().chars().take(80).collect();
+        let text = format!("no answer, so I dropped: {preview}");
+        insert_outbox_text(db, &job.chat_id, &text, job.id, trace_id).await?;
+    }
+    Ok(())
+}
+
+/// Decrypts the vault entries a job may use: those listing its kind, or the
+/// name of the cron that created it. Only crons the user set up count, since
+/// an agent can create a cron under any name it likes. Without
+/// `YUI_SECRETS_KEY` runs get none.
+pub(crate) async fn job_secrets(db: &PgPool, job_id: Uuid) -> Result<Vec<SecretValue>> {
+    let Some(key) = secrets_key() else {
+        return Ok(vec![]);
+    };
+    let rows = sqlx::query!(
+        r#"
+        SELECT s.name, s.value_sealed, s.inject_as as "inject_as: SecretInjection"
+        FROM secrets s, jobs j
+        LEFT JOIN crons c ON c.id = j.cron_id
+        WHERE j.id = $1
+          AND (j.kind = ANY(s.allowed_kinds)
+               OR (c.created_by = 'user' AND c.name = ANY(s.allowed_crons)))
+        ORDER BY s.name
+        "#,
+        job_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            let value = row
+                .value_sealed
+                .and_then(|sealed| key.open(&sealed))
+                .ok_or_else(|| {
+                    ForgeError::Internal(format!(
+                        "secret {} does not decrypt with YUI_SECRETS_KEY",
+                        row.name
+                    ))
+                })?;
+            Ok(SecretValue {
+                name: row.name,
+                value,
+                inject_as: row.inject_as,
+            })
+        })
+        .collect()
+}
+
+/// Moves vault entries written by `pgp_sym_encrypt` over to values sealed in
+/// this process. Decrypting them needs the key in one last query each, after
+/// which it is never sent to the database again.
+async fn reseal_legacy_secrets(db: &PgPool) -> Result<u64> {
+    let Some(key) = secrets_key() else {
+        return Ok(0);
+    };
+    let passphrase = std::env::var("YUI_SECRETS_KEY").unwrap_or_default();
+    let legacy = sqlx::query!(
+        r#"
+        SELECT id, pgp_sym_decrypt(value_encrypted, $1) as "value!"
+        FROM secrets
+        WHERE value_sealed IS NULL AND value_encrypted IS NOT NULL
+        "#,
+        passphrase
+    )
+    .fetch_all(db)
+    .await?;
+
+    for row in &legacy {
+        sqlx::query!(
+            "UPDATE secrets SET value_sealed = $2, value_encrypted = NULL WHERE id = $1",
+            row.id,
+            key.seal(&row.value)
+        )
+        .execute(db)
+        .await?;
+    }
+    Ok(legacy.len() as u64)
+}
+
+/// The redactor for a run, rebuilt from the vault when the run was started by
+/// another runtime process.
+async fn run_redactor(db: &PgPool, job_id: Uuid) -> Result<Redactor> {
+    if let Some(redactor) = REDACTORS.lock().unwrap().get(&job_id) {
+        return Ok(redactor.clone());
+    }
+    let redactor = Redactor::new(&job_secrets(db, job_id).await?);
+    REDACTORS.lock().unwrap().insert(job_id, redactor.clone());
+    Ok(redactor)
+}
+
+/// How many agent runs may be in flight at once, across every runtime replica.
+pub struct RunLimits {
+    pub max_concurrent: i64,
+    pub max_per_chat: i64,
+}
+
+impl RunLimits {
+    fn from_env() -> Self {
+        let var = |name: &str, default: i64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|n: &i64| *n > 0)
+                .unwrap_or(default)
+        };
+        Self {
+            max_concurrent: var("YUI_MAX_CONCURRENT_RUNS", 4),
+            max_per_chat: var("YUI_MAX_RUNS_PER_CHAT", 2),
+        }
+    }
+}
+
+fn ordinal(n: i64) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{n}{suffix}")
+}
+
+/// Advisory lock held while claiming jobs to start ("yui_runs"), so replicas
+/// count the free slots one after another instead of all seeing the same
+/// number of running jobs and overshooting the global cap together.
+const START_LOCK: i64 = 0x7975_695f_7275_6e73;
+
+/// Claims pending jobs in `job_queue` order, taking only as many as the free
+/// global slots allow and skipping chats that already use their share.
 async fn start_pending_jobs(
     db: &PgPool,
     runner: &dyn AgentRunnerService,
     active_runs: &mut HashMap<Uuid, RunnerHandle>,
+    limits: &RunLimits,
 ) -> Result<()> {
+    let mut tx = db.begin().await?;
+    sqlx::query!("SELECT pg_advisory_xact_lock($1)", START_LOCK)
+        .execute(&mut *tx)
+        .await?;
     let pending = sqlx::query_as!(
         PendingJob,
         r#"
-        SELECT id, chat_id, enriched_prompt, prompt, resume_input, trace_id
-        FROM jobs
-        WHERE status = 'pending'
-          AND id != ALL($1::uuid[])
+        WITH claimed AS (
+            UPDATE jobs SET claimed_by = $2, lease_until = now() + interval '1 minute'
+            WHERE id IN (
+                SELECT id FROM jobs
+                WHERE status = 'pending'
+                  AND (lease_until IS NULL OR lease_until < now())
+                  AND id IN (
+                      SELECT id FROM job_queue
+                      WHERE chat_slot <= $3 AND id != ALL($1::uuid[])
+                      ORDER BY position
+                      LIMIT GREATEST(LEAST(10, $4 - (
+                          SELECT count(*) FROM jobs
+                          WHERE status = 'running' OR (status = 'pending' AND lease_until > now())
+                      )), 0)
+                  )
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, kind, chat_id, enriched_prompt, prompt, resume_input, session_id,
+                      source_ids, resource_profile, workspace_id, trace_id, created_at
+        )
+        SELECT id, kind, chat_id, enriched_prompt, prompt, resume_input, session_id,
+               source_ids as "source_ids!", resource_profile, workspace_id, trace_id
+        FROM claimed
         ORDER BY created_at
-        LIMIT 10
-        FOR UPDATE SKIP LOCKED
         "#,
-        &active_runs.keys().copied().collect::<Vec<_>>()
+        &active_runs.keys().copied().collect::<Vec<_>>(),
+        instance_id(),
+        limits.max_per_chat,
+        limits.max_concurrent
     )
-    .fetch_all(db)
+    .fetch_all(&mut *tx)
     .await?;
+    tx.commit().await?;
 
     if !pending.is_empty() {
         tracing::debug!(count = pending.len(), "runtime: starting pending jobs");
     }
 
     for job in &pending {
-        let prompt = job
+        let mut prompt = job
             .enriched_prompt
             .clone()
             .or_else(|| job.prompt.clone())
             .unwrap_or_default();
-
-        let is_resume = job.resume_input.is_some();
-        let full_prompt = if let Some(ref input) = job.resume_input {
-            format!("{prompt}\n\nUser response: {input}")
-        } else {
-            prompt
+        let mut project_dir = None;
+        if let Some(workspace_id) = job.workspace_id {
+            match workspaces::lock_workspace(db, job.id, workspace_id).await? {
+                WorkspaceLock::Acquired { name, path } => {
+                    prompt = workspaces::prompt_with_project(prompt, &name);
+                    project_dir = Some(path);
+                }
+                // another job of this project got there first in the same batch
+                WorkspaceLock::Busy => {
+                    sqlx::query!(
+                        r#"
+                        UPDATE jobs SET claimed_by = NULL, lease_until = NULL
+                        WHERE id = $1 AND status = 'pending' AND claimed_by = $2
+                        "#,
+                        job.id,
+                        instance_id()
+                    )
+                    .execute(db)
+                    .await?;
+                    continue;
+                }
+                WorkspaceLock::OverQuota {
+                    name,
+                    size_mb,
+                    quota_mb,
+                } => {
+                    let error = format!(
+                        "the {name} workspace is full ({size_mb} of {quota_mb} MB), \
+                         reset it or free some space first"
+                    );
+                    handle_runner_event(
+                        db,
+                        job.id,
+                        RunnerEvent::Failed {
+                            error,
+                            retryable: false,
+                        },
+                    )
+                    .await?;
+                    continue;
+                }
+            }
+        }
+        let mut attachments = job_attachments(db, &job.source_ids).await?;
+        let (deps, dep_files) = dependency_results(db, job.id).await?;
+        let prompt = prompt_with_dependencies(prompt, &deps);
+        attachments.extend(dep_files);
+        let (profile, limits) = job_profile(job.resource_profile.as_deref(), &job.kind);
+        let egress = egress::assign_egress(db, job.id, &job.kind).await?;
+        let prompt = match egress.prompt_note() {
+            Some(note) => format!("{prompt}\n\n{note}"),
+            None => prompt,
+        };
+        let agent_token = agent_api::issue_token(db, job.id).await?;
+        // a secret that no longer decrypts (say the key changed) only stops
+        // the jobs that use it, not every start in this tick
+        let secrets = match job_secrets(db, job.id).await {
+            Ok(secrets) => secrets,
+            Err(e) => {
+                tracing::error!(job_id = %job.id, error = %e, "runtime: could not load job secrets");
+                handle_runner_event(
+                    db,
+                    job.id,
+                    RunnerEvent::Failed {
+                        error: format!("could not load the secrets this job uses: {e}"),
+                        retryable: false,
+                    },
+                )
+                .await?;
+                continue;
+            }
         };
+        REDACTORS
+            .lock()
+            .unwrap()
+            .insert(job.id, Redactor::new(&secrets));
 
         tracing::info!(
             job_id = %job.id,
             chat_id = %job.chat_id,
-            prompt_len = full_prompt.len(),
-            is_resume,
+            prompt_len = prompt.len(),
+            attachments = attachments.len(),
+            dependencies = deps.len(),
+            secrets = secrets.len(),
+            profile,
+            egress = ?egress.policy,
+            is_resume = job.resume_input.is_some(),
+            session_id = job.session_id.as_deref(),
             "runtime: launching job"
         );
 
         match runner
             .start(RunnerStartInput {
                 job_id: job.id,
-                prompt: full_prompt,
+                prompt,
+                session_id: job.session_id.clone(),
+                resume_input: job.resume_input.clone(),
+                attachments,
+                limits,
+                agent_token: Some(agent_token),
+                secrets,
+                project_dir,
+                egress,
             })
             .await
         {
             Ok(handle) => {
                 let trace_id = trace_id_or_new(job.trace_id);
-                sqlx::query!(
+                // resuming a paused job continues the same attempt; only a first
+                // start or a scheduled retry uses up one
+                let promoted = sqlx::query!(
                     r#"
-                    UPDATE jobs SET status = 'running', started_at = now(), last_heartbeat_at = now()
-                    WHERE id = $1 AND status = 'pending'
+                    UPDATE jobs SET status = 'running', started_at = now(), last_heartbeat_at = now(),
+                                    claimed_by = $2, lease_until = NULL,
+                                    runner_backend = $3, runner_run_id = $4, container_id = $5,
+                                    attempt_count = attempt_count + CASE
+                                        WHEN resume_input IS NOT NULL AND retry_at IS NULL
+                                             AND attempt_count > 0 THEN 0
+                                        ELSE 1
+                                    END,
+                                    retry_at = NULL,
+                                    question_pending = NULL, agent_answer = NULL,
+                                    stdout_frames_seen = 0, stderr_lines_seen = 0,
+                                    resource_profile = $6, max_wall_secs = $7, memory_mb = $8,
+                                    cpus = $9, disk_mb = $10, max_turns = $11
+                    WHERE id = $1 AND status = 'pending' AND claimed_by = $2
                     "#,
-                    job.id
+                    job.id,
+                    instance_id(),
+                    runner.backend(),
+                    handle.run_id,
+                    handle.container_id,
+                    profile,
+                    limits.max_wall_secs,
+                    limits.memory_mb,
+                    limits.cpus,
+                    limits.disk_mb,
+                    limits.max_turns
                 )
                 .execute(db)
                 .await?;
 
+                // lost the claim (cancelled or lease expired) while the runner was starting
+                if promoted.rows_affected() == 0 {
+                    tracing::warn!(job_id = %job.id, "runtime: job claim lost during start, cancelling run");
+                    REDACTORS.lock().unwrap().remove(&job.id);
+                    let _ = runner.cancel(&handle).await;
+                    continue;
+                }
+
                 sqlx::query!(
                     r#"
                     INSERT INTO events (trace_id, source, action, payload)
@@ -318,12 +1028,64 @@ async fn start_pending_jobs(
             }
             Err(e) => {
                 tracing::error!(job_id = %job.id, error = %e, "failed to start job");
+                REDACTORS.lock().unwrap().remove(&job.id);
+                sqlx::query!(
+                    r#"
+                    UPDATE jobs SET claimed_by = NULL, lease_until = NULL
+                    WHERE id = $1 AND status = 'pending' AND claimed_by = $2
+                    "#,
+                    job.id,
+                    instance_id()
+                )
+                .execute(db)
+                .await?;
             }
         }
     }
     Ok(())
 }
 
+/// Tells the user once when their job has to wait for a free slot, and where
+/// it stands in line. Cron jobs wait silently.
+async fn notify_queued_jobs(db: &PgPool, limits: &RunLimits) -> Result<()> {
+    let waiting = sqlx::query!(
+        r#"
+        WITH in_flight AS (
+            SELECT count(*) AS runs FROM jobs
+            WHERE status = 'running' OR (status = 'pending' AND lease_until > now())
+        )
+        UPDATE jobs j SET queue_notified_at = now()
+        FROM job_queue q, in_flight
+        WHERE j.id = q.id
+          AND j.queue_notified_at IS NULL
+          AND j.kind <> 'schedule'
+          AND (q.chat_slot > $1 OR in_flight.runs >= $2)
+        RETURNING j.id, j.chat_id, j.trace_id, q.position as "position!"
+        "#,
+        limits.max_per_chat,
+        limits.max_concurrent
+    )
+    .fetch_all(db)
+    .await?;
+
+    for job in waiting {
+        let trace_id = trace_id_or_new(job.trace_id);
+        let text = format!(
+            "Queued, you're {} in line. I'll start on it as soon as a slot frees up.",
+            ordinal(job.position)
+        );
+        insert_outbox_text(db, &job.chat_id, &text, job.id, trace_id).await?;
+        insert_runtime_event(
+            db,
+            trace_id,
+            "job_queued",
+            serde_json::json!({ "job_id": job.id, "position": job.position }),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
 async fn poll_active_runs(
     db: &PgPool,
     runner: &dyn AgentRunnerService,
@@ -351,15 +1113,75 @@ async fn poll_active_runs(
         .execute(db)
         .await?;
 
+        let redactor = run_redactor(db, job_id).await?;
+        let mut logs = vec![];
+        let mut rest = vec![];
         for event in events {
+            match redactor.redact_event(event) {
+                RunnerEvent::Stdout(line) => logs.push(("stdout", line)),
+                RunnerEvent::Stderr(line) => logs.push(("stderr", line)),
+                other => rest.push(other),
+            }
+        }
+        insert_logs(db, job_id, logs).await?;
+
+        for event in rest {
             if handle_runner_event(db, job_id, event).await? {
                 active_runs.remove(&job_id);
+                REDACTORS.lock().unwrap().remove(&job_id);
+                workspaces::refresh_workspace_size(db, job_id).await?;
             }
         }
     }
     Ok(())
 }
 
+/// Stops runs that outlived their profile's wall-clock budget. Each start
+/// (including a resume) gets the full budget again.
+async fn enforce_wall_time(
+    db: &PgPool,
+    runner: &dyn AgentRunnerService,
+    active_runs: &mut HashMap<Uuid, RunnerHandle>,
+) -> Result<()> {
+    let overdue = sqlx::query!(
+        r#"
+        SELECT id, max_wall_secs as "max_wall_secs!" FROM jobs
+        WHERE id = ANY($1::uuid[]) AND status = 'running' AND max_wall_secs IS NOT NULL
+          AND started_at + make_interval(secs => max_wall_secs) < now()
+        "#,
+        &active_runs.keys().copied().collect::<Vec<_>>()
+    )
+    .fetch_all(db)
+    .await?;
+
+    for job in overdue {
+        let Some(handle) = active_runs.remove(&job.id) else {
+            continue;
+        };
+        REDACTORS.lock().unwrap().remove(&job.id);
+        tracing::warn!(
+            job_id = %job.id,
+            max_wall_secs = job.max_wall_secs,
+            "runtime: run exceeded wall time, stopping"
+        );
+        if let Err(e) = runner.cancel(&handle).await {
+            tracing::warn!(job_id = %job.id, error = %e, "runtime: failed to stop overdue run");
+        }
+        let error = format!("timed out after {}s", job.max_wall_secs);
+        handle_runner_event(
+            db,
+            job.id,
+            RunnerEvent::Failed {
+                error,
+                retryable: false,
+            },
+        )
+        .await?;
+        workspaces::refresh_workspace_size(db, job.id).await?;
+    }
+    Ok(())
+}
+
 async fn cleanup_cancelled_runs(
     db: &PgPool,
     runner: &dyn AgentRunnerService,
@@ -376,38 +1198,160 @@ async fn cleanup_cancelled_runs(
     .await?;
 
     for job_id in cancelled {
+        REDACTORS.lock().unwrap().remove(&job_id);
         if let Some(handle) = active_runs.remove(&job_id) {
             let _ = runner.cancel(&handle).await;
+            workspaces::refresh_workspace_size(db, job_id).await?;
         }
     }
     Ok(())
 }
 
+struct OrphanedRun {
+    id: Uuid,
+    runner_run_id: Option<Uuid>,
+    container_id: Option<String>,
+    stdout_frames_seen: i64,
+    stderr_lines_seen: i64,
+    workspace_id: Option<Uuid>,
+    trace_id: Option<Uuid>,
+}
+
+/// Takes over running jobs whose owning runtime stopped polling them, a dead
+/// replica or this process before a restart. Runs that still exist are
+/// reattached; the rest are re-queued.
+async fn adopt_orphaned_runs(
+    db: &PgPool,
+    runner: &dyn AgentRunnerService,
+    active_runs: &mut HashMap<Uuid, RunnerHandle>,
+) -> Result<()> {
+    // a live owner bumps last_heartbeat_at on every poll, so half a minute of
+    // silence means nobody is watching the run
+    let orphans = sqlx::query_as!(
+        OrphanedRun,
+        r#"
+        WITH orphans AS (
+            SELECT id FROM jobs
+            WHERE status = 'running'
+              AND runner_backend = $2
+              AND id != ALL($3::uuid[])
+              AND (last_heartbeat_at IS NULL
+                   OR last_heartbeat_at < now() - interval '30 seconds')
+            LIMIT 10
+            FOR UPDATE SKIP LOCKED
+        ),
+        adopted AS (
+            UPDATE jobs j SET claimed_by = $1, last_heartbeat_at = now()
+            FROM orphans o
+            WHERE j.id = o.id
+            RETURNING j.id, j.runner_run_id, j.container_id, j.stdout_frames_seen,
+                      j.stderr_lines_seen, j.workspace_id, j.trace_id
+        )
+        SELECT id as "id!", runner_run_id, container_id,
+               stdout_frames_seen as "stdout_frames_seen!",
+               stderr_lines_seen as "stderr_lines_seen!", workspace_id, trace_id
+        FROM adopted
+        "#,
+        instance_id(),
+        runner.backend(),
+        &active_runs.keys().copied().collect::<Vec<_>>()
+    )
+    .fetch_all(db)
+    .await?;
+
+    for orphan in orphans {
+        let handle = RunnerHandle {
+            run_id: orphan.runner_run_id.unwrap_or_else(Uuid::new_v4),
+            job_id: orphan.id,
+            container_id: orphan.container_id,
+        };
+        let trace_id = trace_id_or_new(orphan.trace_id);
+        let seen = FrameOffset {
+            stdout: orphan.stdout_frames_seen as u64,
+            stderr: orphan.stderr_lines_seen as u64,
+        };
+
+        let project_dir = orphan.workspace_id.map(project_path);
+
+        match runner.reattach(&handle, seen, project_dir).await {
+            Ok(true) => {
+                tracing::info!(job_id = %orphan.id, "runtime: reattached to running job");
+                insert_runtime_event(
+                    db,
+                    trace_id,
+                    "job_reattached",
+                    serde_json::json!({ "job_id": orphan.id, "run_id": handle.run_id }),
+                )
+                .await?;
+                active_runs.insert(orphan.id, handle);
+            }
+            Ok(false) => {
+                tracing::warn!(job_id = %orphan.id, "runtime: run is gone, re-queueing job");
+                sqlx::query!(
+                    r#"
+                    UPDATE jobs SET status = 'pending', last_heartbeat_at = NULL,
+                                    claimed_by = NULL, lease_until = NULL,
+                                    runner_backend = NULL, runner_run_id = NULL
+                    WHERE id = $1 AND status = 'running' AND claimed_by = $2
+                    "#,
+                    orphan.id,
+                    instance_id()
+                )
+                .execute(db)
+                .await?;
+                insert_runtime_event(
+                    db,
+                    trace_id,
+                    "job_requeued",
+                    serde_json::json!({ "job_id": orphan.id, "reason": "run_gone" }),
+                )
+                .await?;
+            }
+            // leave the job alone; the refreshed heartbeat delays the next attempt
+            Err(e) => {
+                tracing::warn!(job_id = %orphan.id, error = %e, "runtime: reattach failed");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Backends whose runs outlive the runtime that started them. Their orphans
+/// are left to `adopt_orphaned_runs` on a runtime with the same backend, which
+/// checks whether the run still exists before re-queueing the job.
+const REATTACHABLE_BACKENDS: &[&str] = &["docker"];
+
+/// Re-queues running jobs that nobody has polled for five minutes and whose
+/// run died with its runtime.
 async fn recover_orphaned_jobs(db: &PgPool) -> Result<()> {
+    // flipping status back to pending in the same statement that finds the
+    // orphan is the claim: a concurrent replica's UPDATE will no longer match
     let orphaned = sqlx::query_scalar!(
         r#"
-        SELECT id FROM jobs
-        WHERE status = 'running'
-          AND last_heartbeat_at < now() - interval '5 minutes'
-        LIMIT 10
-        FOR UPDATE SKIP LOCKED
-        "#
+        UPDATE jobs SET status = 'pending', last_heartbeat_at = NULL,
+                        claimed_by = NULL, lease_until = NULL,
+                        runner_backend = NULL, runner_run_id = NULL
+        WHERE id IN (
+            SELECT id FROM jobs
+            WHERE status = 'running'
+              AND last_heartbeat_at < now() - interval '5 minutes'
+              AND (runner_backend IS NULL OR runner_backend != ALL($1::text[]))
+            LIMIT 10
+            FOR UPDATE SKIP LOCKED
+        )
+        AND status = 'running'
+        RETURNING id
+        "#,
+        &REATTACHABLE_BACKENDS
+            .iter()
+            .map(|backend| backend.to_string())
+            .collect::<Vec<_>>()
     )
     .fetch_all(db)
     .await?;
 
     for job_id in orphaned {
-        tracing::warn!(job_id = %job_id, "recovering orphaned running job");
-        sqlx::query!(
-            r#"
-            UPDATE jobs SET status = 'pending', last_heartbeat_at = NULL
-            WHERE id = $1 AND status = 'running'
-            "#,
-            job_id
-        )
-        .execute(db)
-        .await?;
-
+        tracing::warn!(job_id = %job_id, "recovered orphaned running job");
         sqlx::query!(
             r#"
             INSERT INTO events (source, action, payload)
@@ -425,10 +1369,17 @@ pub async fn runtime_tick(
     db: &PgPool,
     runner: &dyn AgentRunnerService,
     active_runs: &mut HashMap<Uuid, RunnerHandle>,
+    limits: &RunLimits,
 ) -> Result<()> {
-    start_pending_jobs(db, runner, active_runs).await?;
+    workspaces::reset_workspaces(db).await?;
+    start_pending_jobs(db, runner, active_runs, limits).await?;
+    notify_queued_jobs(db, limits).await?;
     poll_active_runs(db, runner, active_runs).await?;
+    enforce_wall_time(db, runner, active_runs).await?;
+    cancel_blocked_dependents(db).await?;
+    expire_approvals(db).await?;
     cleanup_cancelled_runs(db, runner, active_runs).await?;
+    adopt_orphaned_runs(db, runner, active_runs).await?;
     recover_orphaned_jobs(db).await?;
     Ok(())
 }
@@ -444,8 +1395,28 @@ pub async fn runtime(ctx: &DaemonContext) -> Result<()> {
     let runner: Arc<dyn AgentRunnerService> = match backend.as_str() {
         "docker" if std::env::var("YUI_DOCKER_IMAGE").is_ok() => {
             tracing::info!("runtime using Docker agent executor");
+            if egress_config().network.is_none() {
+                tracing::error!(
+                    "YUI_EGRESS_NETWORK is not set, runs with a restricted egress policy will be refused"
+                );
+            }
             Arc::new(DockerAgentRunner::new())
         }
+        "process" => match ProcessRunnerConfig::from_env() {
+            Some(config) => {
+                tracing::info!(
+                    command = %config.command,
+                    "runtime using local process agent runner"
+                );
+                Arc::new(ProcessAgentRunner::new(config))
+            }
+            None => {
+                tracing::warn!(
+                    "YUI_RUNTIME_BACKEND=process needs YUI_PROCESS_COMMAND, runtime daemon idle"
+                );
+                return Ok(());
+            }
+        },
         _ if runtime_enabled && std::env::var("OPENROUTER_API_KEY").is_ok() => {
             tracing::info!("runtime using OpenRouter agent runner");
             Arc::new(OpenRouterAgentRunner::from_env())
@@ -460,14 +1431,33 @@ pub async fn runtime(ctx: &DaemonContext) -> Result<()> {
         }
     };
 
+    match reseal_legacy_secrets(ctx.db()).await {
+        Ok(0) => {}
+        Ok(count) => tracing::info!(count, "runtime: resealed secrets stored by pgcrypto"),
+        Err(e) => {
+            tracing::error!(error = %e, "runtime: could not reseal secrets stored by pgcrypto")
+        }
+    }
+    agent_api::spawn_listener(ctx.db()).await;
+    artifacts::install(ctx.db());
+    egress::spawn_proxy(ctx.db()).await;
+
     let poll_ms: u64 = ctx.env_parse("YUI_LOOP_POLL_MS_RUNTIME").unwrap_or(500);
+    let mut wakeup = Wakeup::listen(ctx.db(), &[JOBS_CHANNEL]).await;
+    let mut heartbeat = Heartbeat::new("runtime");
     let mut active_runs: HashMap<Uuid, RunnerHandle> = HashMap::new();
+    let limits = RunLimits::from_env();
 
     loop {
         tokio::select! {
             _ = ctx.shutdown_signal() => break,
-            _ = tokio::time::sleep(std::time::Duration::from_millis(poll_ms)) => {
-                if let Err(e) = runtime_tick(ctx.db(), runner.as_ref(), &mut active_runs).await {
+            _ = wakeup.wait(std::time::Duration::from_millis(poll_ms)) => {
+                let result =
+                    runtime_tick(ctx.db(), runner.as_ref(), &mut active_runs, &limits).await;
+                metrics::set_active_runs(active_runs.len());
+                let result = metrics::observe_tick("runtime", result.map(|()| 0));
+                heartbeat.record(ctx.db(), &result).await;
+                if let Err(e) = result {
                     tracing::error!(error = %e, "runtime tick failed");
                 }
             }
@@ -476,27 +1466,36 @@ pub async fn runtime(ctx: &DaemonContext) -> Result<()> {
     Ok(())
 }
 
-struct DockerAgentRunner;
+struct DockerAgentRunner {
+    executor: Arc<AgentExecutor>,
+}
 
 impl DockerAgentRunner {
     fn new() -> Self {
-        Self
+        Self {
+            executor: Arc::new(AgentExecutor::from_env()),
+        }
     }
 }
 
 #[async_trait::async_trait]
 impl AgentRunnerService for DockerAgentRunner {
+    fn backend(&self) -> &'static str {
+        "docker"
+    }
+
     async fn start(&self, input: RunnerStartInput) -> anyhow::Result<RunnerHandle> {
         // the actual execution happens asynchronously, we return a handle immediately
         let handle = RunnerHandle {
             run_id: Uuid::new_v4(),
             job_id: input.job_id,
+            container_id: Some(container_name(input.job_id)),
         };
         // store the prompt for when poll is called, execution is lazy
         DOCKER_RUNS
             .lock()
             .unwrap()
-            .insert(handle.run_id, DockerRun::Pending(input.prompt));
+            .insert(handle.run_id, DockerRun::Pending(input));
         Ok(handle)
     }
 
@@ -507,87 +1506,121 @@ impl AgentRunnerService for DockerAgentRunner {
         };
 
         match state {
-            Some(DockerRun::Pending(prompt)) => {
+            Some(DockerRun::Pending(input)) => {
                 // start execution
                 {
                     let mut runs = DOCKER_RUNS.lock().unwrap();
                     runs.insert(handle.run_id, DockerRun::Running);
                 }
 
-                let (log_tx, mut log_rx) =
-                    tokio::sync::mpsc::unbounded_channel::<(String, String)>();
+                let (event_tx, event_rx) = tokio::sync::mpsc::unbounded_channel();
+                DOCKER_EVENTS
+                    .lock()
+                    .unwrap()
+                    .insert(handle.run_id, event_rx);
                 let executor_input = ExecutionInput {
                     job_id: handle.job_id,
                     trace_id: Uuid::new_v4(),
-                    prompt: prompt.clone(),
-                    attachments: vec![],
-                    session_id: None,
-                    resume_input: None,
+                    prompt: input.prompt,
+                    attachments: input.attachments,
+                    limits: input.limits,
+                    session_id: input.session_id,
+                    resume_input: input.resume_input,
+                    agent_token: input.agent_token,
+                    secrets: input.secrets,
+                    project_dir: input.project_dir,
+                    egress: input.egress,
                 };
 
                 let run_id = handle.run_id;
-                let executor = AgentExecutor::from_env();
+                let executor = self.executor.clone();
                 tokio::spawn(async move {
-                    let outcome = executor.execute(executor_input, log_tx).await;
+                    let outcome = executor.execute(executor_input, event_tx).await;
                     let mut runs = DOCKER_RUNS.lock().unwrap();
                     runs.insert(run_id, DockerRun::Done(outcome));
                 });
 
-                // collect any immediate log output
-                let mut events = vec![RunnerEvent::Stdout("starting container...".to_string())];
-                while let Ok((stream, line)) = log_rx.try_recv() {
-                    match stream.as_str() {
-                        "stderr" => events.push(RunnerEvent::Stderr(line)),
-                        _ => events.push(RunnerEvent::Stdout(line)),
-                    }
-                }
-                Ok(events)
-            }
-            Some(DockerRun::Running) => {
-                // still running
-                Ok(vec![])
+                Ok(vec![RunnerEvent::Stdout(
+                    "starting container...".to_string(),
+                )])
             }
+            Some(DockerRun::Running) => Ok(drain_docker_events(handle.run_id)),
             Some(DockerRun::Done(outcome)) => {
                 let mut runs = DOCKER_RUNS.lock().unwrap();
                 runs.remove(&handle.run_id);
                 drop(runs);
 
-                match outcome {
+                // the executor has exited, so everything it logged is already queued
+                let mut events = drain_docker_events(handle.run_id);
+                DOCKER_EVENTS.lock().unwrap().remove(&handle.run_id);
+
+                events.push(match outcome {
                     ExecutionOutcome::Completed {
                         output,
                         attachments,
                         ..
-                    } => Ok(vec![RunnerEvent::Completed {
+                    } => RunnerEvent::Completed {
                         output,
                         attachments,
-                    }]),
-                    ExecutionOutcome::Paused { question, .. } => {
-                        Ok(vec![RunnerEvent::AskUser { question }])
-                    }
-                    ExecutionOutcome::Failed { error, .. } => {
-                        Ok(vec![RunnerEvent::Failed { error }])
+                    },
+                    ExecutionOutcome::Paused { question, .. } => RunnerEvent::AskUser { question },
+                    ExecutionOutcome::Failed { error, retryable } => {
+                        RunnerEvent::Failed { error, retryable }
                     }
-                }
+                });
+                Ok(events)
             }
             None => Ok(vec![]),
         }
     }
 
     async fn cancel(&self, handle: &RunnerHandle) -> anyhow::Result<()> {
-        let container_name = format!("yui-job-{}", handle.job_id.as_simple());
-        let _ = tokio::process::Command::new("docker")
-            .args(["kill", &container_name])
-            .output()
-            .await;
+        self.executor.cancel(handle.job_id).await;
         let mut runs = DOCKER_RUNS.lock().unwrap();
         runs.remove(&handle.run_id);
+        DOCKER_EVENTS.lock().unwrap().remove(&handle.run_id);
         Ok(())
     }
+
+    async fn reattach(
+        &self,
+        handle: &RunnerHandle,
+        seen: FrameOffset,
+        project_dir: Option<PathBuf>,
+    ) -> anyhow::Result<bool> {
+        if !self
+            .executor
+            .container_exists(&container_name(handle.job_id))
+            .await?
+        {
+            return Ok(false);
+        }
+
+        DOCKER_RUNS
+            .lock()
+            .unwrap()
+            .insert(handle.run_id, DockerRun::Running);
+
+        let (event_tx, event_rx) = tokio::sync::mpsc::unbounded_channel();
+        DOCKER_EVENTS
+            .lock()
+            .unwrap()
+            .insert(handle.run_id, event_rx);
+        let run_id = handle.run_id;
+        let job_id = handle.job_id;
+        let executor = self.executor.clone();
+        tokio::spawn(async move {
+            let outcome = executor.follow(job_id, seen, project_dir, event_tx).await;
+            let mut runs = DOCKER_RUNS.lock().unwrap();
+            runs.insert(run_id, DockerRun::Done(outcome));
+        });
+        Ok(true)
+    }
 }
 
 #[derive(Clone)]
 enum DockerRun {
-    Pending(String),
+    Pending(RunnerStartInput),
     Running,
     Done(ExecutionOutcome),
 }
@@ -595,3 +1628,89 @@ enum DockerRun {
 static DOCKER_RUNS: std::sync::LazyLock<std::sync::Mutex<HashMap<Uuid, DockerRun>>> =
     std::sync::LazyLock::new(|| std::sync::Mutex::new(HashMap::new()));
 
+/// Event receivers live outside `DockerRun` so they survive every poll for the
+/// whole run instead of being cloned out of the map and dropped.
+static DOCKER_EVENTS: std::sync::LazyLock<
+    std::sync::Mutex<HashMap<Uuid, tokio::sync::mpsc::UnboundedReceiver<ExecutorEvent>>>,
+> = std::sync::LazyLock::new(|| std::sync::Mutex::new(HashMap::new()));
+
+fn drain_docker_events(run_id: Uuid) -> Vec<RunnerEvent> {
+    let mut logs = DOCKER_EVENTS.lock().unwrap();
+    let Some(rx) = logs.get_mut(&run_id) else {
+        return vec![];
+    };
+    let mut events = vec![];
+    let mut seen = None;
+    while let Ok(event) = rx.try_recv() {
+        events.push(match event {
+            ExecutorEvent::Log {
+                stream: LogStream::Stdout,
+                line,
+            } => RunnerEvent::Stdout(line),
+            ExecutorEvent::Log {
+                stream: LogStream::Stderr,
+                line,
+            } => RunnerEvent::Stderr(line),
+            ExecutorEvent::Session { session_id } => RunnerEvent::Session { session_id },
+            ExecutorEvent::Step(step) => RunnerEvent::Step(step),
+            ExecutorEvent::Progress { message } => RunnerEvent::Progress { message },
+            ExecutorEvent::Seen { stream, count } => {
+                // only the latest count matters; streams not seen this drain
+                // stay at zero, which the stored offset ignores
+                let offset: &mut FrameOffset = seen.get_or_insert_default();
+                match stream {
+                    LogStream::Stdout => offset.stdout = count,
+                    LogStream::Stderr => offset.stderr = count,
+                }
+                continue;
+            }
+        });
+    }
+    // last, so the offset is stored after the events it covers
+    events.extend(seen.map(RunnerEvent::Seen));
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queue_positions_read_as_ordinals() {
+        let cases = [
+            (1, "1st"),
+            (2, "2nd"),
+            (3, "3rd"),
+            (4, "4th"),
+            (11, "11th"),
+            (12, "12th"),
+            (13, "13th"),
+            (21, "21st"),
+            (102, "102nd"),
+        ];
+        for (n, expected) in cases {
+            assert_eq!(ordinal(n), expected);
+        }
+    }
+
+    #[test]
+    fn dependency_results_are_appended_in_order() {
+        let deps = [
+            DependencyResult {
+                prompt: Some("clone the repo".to_string()),
+                output: Some("cloned into ./api".to_string()),
+            },
+            DependencyResult {
+                prompt: Some("install dependencies".to_string()),
+                output: Some("x".repeat(DEPENDENCY_OUTPUT_CHARS + 1)),
+            },
+        ];
+
+        let prompt = prompt_with_dependencies("run the tests".to_string(), &deps);
+
+        assert!(prompt.starts_with("run the tests\n\nResults of the tasks this one waited for:"));
+        assert!(prompt.contains("1. Task: clone the repo\nResult: cloned into ./api"));
+        assert!(prompt.ends_with(" [truncated]"));
+        assert_eq!(prompt_with_dependencies("solo".to_string(), &[]), "solo");
+    }
+}
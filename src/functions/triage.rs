@@ -1,6 +1,12 @@
+use crate::functions::claim::instance_id;
 use crate::functions::clock::compute_next_run_at;
+use crate::functions::heartbeat::Heartbeat;
+use crate::functions::wakeup::{MESSAGES_CHANNEL, Wakeup};
+use crate::functions::workspaces::{ensure_workspace, normalize_workspace_name};
+use crate::schema::CronNotifyPolicy;
 use crate::services::{
-    ActiveCronSummary, ActiveJobSummary, AiService, TriageBatchInput, TriageDecision, TriageMessage,
+    ActiveCronSummary, ActiveJobSummary, AiService, ArtifactSummary, TriageBatchInput,
+    TriageDecision, TriageMessage, explicit_answer, metrics,
 };
 use forge::prelude::*;
 use sqlx::PgPool;
@@ -85,6 +91,16 @@ fn should_force_audio_transcription_job(
         .all(|d| matches!(d, TriageDecision::Reply { .. } | TriageDecision::Noop))
 }
 
+// unknown policies from the LLM degrade to always notifying rather than
+// silently dropping cron output
+pub(crate) fn parse_notify_policy(raw: &str) -> CronNotifyPolicy {
+    match raw.trim().to_ascii_lowercase().as_str() {
+        "on_change" => CronNotifyPolicy::OnChange,
+        "noteworthy" => CronNotifyPolicy::Noteworthy,
+        _ => CronNotifyPolicy::Always,
+    }
+}
+
 async fn is_chat_subscribed(db: &PgPool, chat_id: &str) -> Result<bool> {
     let enabled =
         sqlx::query_scalar::<_, bool>("SELECT enabled FROM chat_subscriptions WHERE chat_id = $1")
@@ -140,6 +156,69 @@ async fn resolve_target_chat_id(
     Ok(resolved.unwrap_or_else(|| fallback_chat_id.to_string()))
 }
 
+struct NewJob<'a> {
+    id: Uuid,
+    kind: &'a str,
+    chat_id: &'a str,
+    prompt: &'a str,
+    profile: Option<&'a str>,
+    depends_on: &'a [Uuid],
+    workspace: Option<&'a str>,
+    /// Why the user has to confirm it first.
+    approval: Option<&'a str>,
+}
+
+async fn insert_draft_job(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    job: NewJob<'_>,
+    source_ids: &[Uuid],
+    trace_id: Uuid,
+) -> Result<()> {
+    let workspace_id = match job.workspace {
+        Some(name) => ensure_workspace(&mut **tx, job.chat_id, name).await?,
+        None => None,
+    };
+    sqlx::query!(
+        r#"
+        INSERT INTO jobs (id, kind, chat_id, status, prompt, source_ids, trace_id, resource_profile,
+                          depends_on, workspace_id, requires_approval, approval_reason)
+        VALUES ($1, $2, $3, 'draft', $4, $5, $6, $7, $8, $9, $10, $11)
+        "#,
+        job.id,
+        job.kind,
+        job.chat_id,
+        job.prompt,
+        source_ids,
+        trace_id,
+        job.profile,
+        job.depends_on,
+        workspace_id,
+        job.approval.is_some(),
+        job.approval
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO events (trace_id, source, action, payload)
+        VALUES ($1, 'triage', 'job_created', $2)
+        "#,
+        trace_id,
+        serde_json::json!({
+            "job_id": job.id,
+            "kind": job.kind,
+            "chat_id": job.chat_id,
+            "depends_on": job.depends_on,
+            "workspace_id": workspace_id,
+            "requires_approval": job.approval.is_some(),
+        })
+    )
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
 async fn apply_decisions(
     tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     chat_id: &str,
@@ -147,6 +226,7 @@ async fn apply_decisions(
     source_ids: &[Uuid],
     trace_id: Uuid,
     is_subscribed: &mut bool,
+    confirmed: bool,
 ) -> Result<()> {
     let target_chat_id = resolve_target_chat_id(tx, chat_id, source_ids).await?;
 
@@ -155,7 +235,13 @@ async fn apply_decisions(
             TriageDecision::Reply { text } => {
                 queue_reply(tx, &target_chat_id, &text, trace_id).await?;
             }
-            TriageDecision::CreateJob { prompt, kind } => {
+            TriageDecision::CreateJob {
+                prompt,
+                kind,
+                profile,
+                workspace,
+                approval,
+            } => {
                 if !*is_subscribed {
                     queue_reply(
                         tx,
@@ -167,39 +253,68 @@ async fn apply_decisions(
                     continue;
                 }
 
-                let job_id = Uuid::new_v4();
-                sqlx::query!(
-                    r#"
-                    INSERT INTO jobs (id, kind, chat_id, status, prompt, source_ids, trace_id)
-                    VALUES ($1, $2, $3, 'draft', $4, $5, $6)
-                    "#,
-                    job_id,
-                    kind,
-                    target_chat_id,
-                    prompt,
+                insert_draft_job(
+                    tx,
+                    NewJob {
+                        id: Uuid::new_v4(),
+                        kind: &kind,
+                        chat_id: &target_chat_id,
+                        prompt: &prompt,
+                        profile: profile.as_deref(),
+                        depends_on: &[],
+                        workspace: workspace.as_deref(),
+                        approval: approval.as_deref(),
+                    },
                     source_ids,
-                    trace_id
-                )
-                .execute(&mut **tx)
-                .await?;
-
-                sqlx::query!(
-                    r#"
-                    INSERT INTO events (trace_id, source, action, payload)
-                    VALUES ($1, 'triage', 'job_created', $2)
-                    "#,
                     trace_id,
-                    serde_json::json!({ "job_id": job_id, "kind": kind, "chat_id": target_chat_id })
                 )
-                .execute(&mut **tx)
                 .await?;
             }
+            TriageDecision::CreatePlan { steps } => {
+                if !*is_subscribed {
+                    queue_reply(
+                        tx,
+                        &target_chat_id,
+                        "you're currently unsubscribed, so tasks are paused. let me know if you want to re-enable them",
+                        trace_id,
+                    )
+                    .await?;
+                    continue;
+                }
+
+                let job_ids: Vec<Uuid> = steps.iter().map(|_| Uuid::new_v4()).collect();
+                for (step, &job_id) in steps.iter().zip(&job_ids) {
+                    let depends_on: Vec<Uuid> = step
+                        .after
+                        .iter()
+                        .filter_map(|&i| job_ids.get(i).copied())
+                        .collect();
+                    insert_draft_job(
+                        tx,
+                        NewJob {
+                            id: job_id,
+                            kind: &step.kind,
+                            chat_id: &target_chat_id,
+                            prompt: &step.prompt,
+                            profile: step.profile.as_deref(),
+                            depends_on: &depends_on,
+                            workspace: step.workspace.as_deref(),
+                            approval: step.approval.as_deref(),
+                        },
+                        source_ids,
+                        trace_id,
+                    )
+                    .await?;
+                }
+            }
             TriageDecision::CreateCron {
                 name,
                 schedule,
                 prompt,
+                notify_policy,
             } => {
                 let timezone = "UTC";
+                let notify_policy = parse_notify_policy(&notify_policy);
                 let next_run_at = match compute_next_run_at(&schedule, timezone, chrono::Utc::now())
                 {
                     Ok(next) => next,
@@ -217,15 +332,16 @@ async fn apply_decisions(
 
                 sqlx::query!(
                     r#"
-                    INSERT INTO crons (name, schedule, timezone, chat_id, prompt, next_run_at)
-                    VALUES ($1, $2, $3, $4, $5, $6)
+                    INSERT INTO crons (name, schedule, timezone, chat_id, prompt, next_run_at, notify_policy)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7)
                     "#,
                     name,
                     schedule,
                     timezone,
                     target_chat_id,
                     prompt,
-                    next_run_at
+                    next_run_at,
+                    notify_policy as _
                 )
                 .execute(&mut **tx)
                 .await?;
@@ -261,7 +377,7 @@ async fn apply_decisions(
                 sqlx::query!(
                     r#"
                     UPDATE jobs SET status = 'cancelled', cancel_reason = $2, finished_at = now()
-                    WHERE id = $1 AND status IN ('draft', 'pending', 'running', 'paused')
+                    WHERE id = $1 AND status IN ('draft', 'pending', 'awaiting_approval', 'running', 'paused')
                     "#,
                     job_id,
                     reason
@@ -288,6 +404,82 @@ async fn apply_decisions(
                 )
                 .execute(&mut **tx)
                 .await?;
+
+                // a running agent that asked through the agent api is still
+                // waiting on its request, so the answer goes straight to it
+                sqlx::query!(
+                    r#"
+                    UPDATE jobs SET agent_answer = $2, question_pending = NULL
+                    WHERE id = $1 AND status = 'running' AND question_pending IS NOT NULL
+                    "#,
+                    job_id,
+                    input
+                )
+                .execute(&mut **tx)
+                .await?;
+            }
+            // only a plain yes in this batch starts the job, whatever triage
+            // made of the messages
+            TriageDecision::ApproveJob {
+                job_id,
+                approved: true,
+            } if !confirmed => {
+                tracing::warn!(job_id = %job_id, "triage: approval without a plain yes, asking again");
+                queue_reply(
+                    tx,
+                    &target_chat_id,
+                    "I still need a plain yes or no before I start on that",
+                    trace_id,
+                )
+                .await?;
+            }
+            TriageDecision::ApproveJob { job_id, approved } => {
+                let status = if approved { "pending" } else { "cancelled" };
+                let answered = sqlx::query_scalar!(
+                    r#"
+                    UPDATE jobs SET status = $3,
+                                    cancel_reason = CASE WHEN $4 THEN NULL ELSE 'not approved' END,
+                                    finished_at = CASE WHEN $4 THEN NULL ELSE now() END,
+                                    approval_expires_at = NULL
+                    WHERE id = $1 AND chat_id = $2 AND status = 'awaiting_approval'
+                    RETURNING id
+                    "#,
+                    job_id,
+                    target_chat_id,
+                    status,
+                    approved
+                )
+                .fetch_optional(&mut **tx)
+                .await?;
+
+                if answered.is_none() {
+                    queue_reply(
+                        tx,
+                        &target_chat_id,
+                        "that task isn't waiting on you anymore",
+                        trace_id,
+                    )
+                    .await?;
+                    continue;
+                }
+                sqlx::query!(
+                    r#"
+                    INSERT INTO events (trace_id, source, action, payload)
+                    VALUES ($1, 'triage', $2, $3)
+                    "#,
+                    trace_id,
+                    if approved {
+                        "job_approved"
+                    } else {
+                        "job_rejected"
+                    },
+                    serde_json::json!({ "job_id": job_id, "chat_id": target_chat_id })
+                )
+                .execute(&mut **tx)
+                .await?;
+                if !approved {
+                    queue_reply(tx, &target_chat_id, "ok, I won't do that", trace_id).await?;
+                }
             }
             TriageDecision::SetSubscription { enabled } => {
                 sqlx::query(
@@ -313,6 +505,89 @@ async fn apply_decisions(
                 };
                 queue_reply(tx, &target_chat_id, status, trace_id).await?;
             }
+            TriageDecision::ResendArtifact { artifact_id } => {
+                // only files that were sent to this chat in the first place
+                let resent = sqlx::query_scalar!(
+                    r#"
+                    INSERT INTO outbox (chat_id, content, attachments, trace_id, job_id)
+                    SELECT chat_id, '',
+                           jsonb_build_array(jsonb_build_object(
+                               'type', kind, 'path', path, 'name', name, 'mime', mime
+                           )),
+                           $3, job_id
+                    FROM artifacts
+                    WHERE id = $1 AND chat_id = $2
+                    RETURNING id
+                    "#,
+                    artifact_id,
+                    target_chat_id,
+                    trace_id
+                )
+                .fetch_optional(&mut **tx)
+                .await?;
+
+                match resent {
+                    Some(_) => {
+                        sqlx::query!(
+                            r#"
+                            INSERT INTO events (trace_id, source, action, payload)
+                            VALUES ($1, 'triage', 'artifact_resent', $2)
+                            "#,
+                            trace_id,
+                            serde_json::json!({ "artifact_id": artifact_id, "chat_id": target_chat_id })
+                        )
+                        .execute(&mut **tx)
+                        .await?;
+                    }
+                    None => {
+                        queue_reply(
+                            tx,
+                            &target_chat_id,
+                            "I can't find that file anymore",
+                            trace_id,
+                        )
+                        .await?;
+                    }
+                }
+            }
+            TriageDecision::ResetWorkspace { name } => {
+                // the runtime empties it once no job is using it
+                let requested = sqlx::query_scalar!(
+                    r#"
+                    UPDATE workspaces SET reset_requested_at = now()
+                    WHERE chat_id = $1 AND name = $2
+                    RETURNING id
+                    "#,
+                    target_chat_id,
+                    normalize_workspace_name(&name).unwrap_or_else(|| name.clone())
+                )
+                .fetch_optional(&mut **tx)
+                .await?;
+
+                match requested {
+                    Some(workspace_id) => {
+                        sqlx::query!(
+                            r#"
+                            INSERT INTO events (trace_id, source, action, payload)
+                            VALUES ($1, 'triage', 'workspace_reset_requested', $2)
+                            "#,
+                            trace_id,
+                            serde_json::json!({ "workspace_id": workspace_id, "chat_id": target_chat_id })
+                        )
+                        .execute(&mut **tx)
+                        .await?;
+                    }
+                    None => {
+                        queue_reply(
+                            tx,
+                            &target_chat_id,
+                            &format!("there's no workspace called {name}"),
+                            trace_id,
+                        )
+                        .await?;
+                    }
+                }
+            }
             TriageDecision::Noop => {}
         }
     }
@@ -323,13 +598,25 @@ pub async fn triage_tick(db: &PgPool, ai: &dyn AiService) -> Result<u32> {
     let rows = sqlx::query_as!(
         UnroutedMessage,
         r#"
+        WITH claimed AS (
+            UPDATE messages SET claimed_by = $1, lease_until = now() + interval '2 minutes'
+            WHERE id IN (
+                SELECT id FROM messages
+                WHERE direction = 'in' AND routed_at IS NULL
+                  AND (lease_until IS NULL OR lease_until < now())
+                ORDER BY created_at
+                LIMIT 50
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, platform_chat_id, content, trace_id,
+                      attachments, updated_at, created_at
+        )
         SELECT id, platform_chat_id, content, trace_id,
                attachments, updated_at, created_at
-        FROM messages
-        WHERE direction = 'in' AND routed_at IS NULL
+        FROM claimed
         ORDER BY created_at
-        LIMIT 50
-        "#
+        "#,
+        instance_id()
     )
     .fetch_all(db)
     .await?;
@@ -354,9 +641,19 @@ pub async fn triage_tick(db: &PgPool, ai: &dyn AiService) -> Result<u32> {
         let active_jobs = sqlx::query_as!(
             ActiveJobSummary,
             r#"
-            SELECT id, status, prompt
+            SELECT id,
+                   CASE WHEN status = 'running' AND question_pending IS NOT NULL
+                        THEN 'paused' ELSE status END as "status!",
+                   prompt, progress,
+                   ARRAY(
+                       SELECT line FROM logs l
+                       WHERE l.job_id = jobs.id
+                       ORDER BY l.created_at DESC
+                       LIMIT 3
+                   ) as "recent_logs!"
             FROM jobs
-            WHERE chat_id = $1 AND status IN ('draft', 'pending', 'running', 'paused')
+            WHERE chat_id = $1
+              AND status IN ('draft', 'pending', 'awaiting_approval', 'running', 'paused')
             ORDER BY created_at DESC
             "#,
             chat_id
@@ -377,6 +674,28 @@ pub async fn triage_tick(db: &PgPool, ai: &dyn AiService) -> Result<u32> {
         .fetch_all(db)
         .await?;
 
+        let recent_artifacts = sqlx::query_as!(
+            ArtifactSummary,
+            r#"
+            SELECT a.id, a.name, a.mime, j.prompt as job_prompt, a.created_at
+            FROM artifacts a
+            JOIN jobs j ON j.id = a.job_id
+            WHERE a.chat_id = $1
+            ORDER BY a.created_at DESC
+            LIMIT 10
+            "#,
+            chat_id
+        )
+        .fetch_all(db)
+        .await?;
+
+        let workspaces = sqlx::query_scalar!(
+            "SELECT name FROM workspaces WHERE chat_id = $1 ORDER BY last_used_at DESC NULLS LAST",
+            chat_id
+        )
+        .fetch_all(db)
+        .await?;
+
         let triage_msgs: Vec<TriageMessage> = msgs
             .iter()
             .map(|m| TriageMessage {
@@ -412,6 +731,8 @@ pub async fn triage_tick(db: &PgPool, ai: &dyn AiService) -> Result<u32> {
             active_jobs,
             active_crons,
             history,
+            recent_artifacts,
+            workspaces,
         };
 
         tracing::info!(
@@ -431,11 +752,15 @@ pub async fn triage_tick(db: &PgPool, ai: &dyn AiService) -> Result<u32> {
             let action = match d {
                 TriageDecision::Reply { .. } => "reply",
                 TriageDecision::CreateJob { kind, .. } => kind.as_str(),
+                TriageDecision::CreatePlan { .. } => "create_plan",
                 TriageDecision::CreateCron { .. } => "create_cron",
                 TriageDecision::CancelJob { .. } => "cancel_job",
                 TriageDecision::CancelCron { .. } => "cancel_cron",
                 TriageDecision::ResumeJob { .. } => "resume_job",
+                TriageDecision::ApproveJob { .. } => "approve_job",
                 TriageDecision::SetSubscription { .. } => "set_subscription",
+                TriageDecision::ResendArtifact { .. } => "resend_file",
+                TriageDecision::ResetWorkspace { .. } => "reset_workspace",
                 TriageDecision::Noop => "noop",
             };
             tracing::info!(chat_id = %chat_id, decision_index = i, action, "triage: decision");
@@ -445,6 +770,9 @@ pub async fn triage_tick(db: &PgPool, ai: &dyn AiService) -> Result<u32> {
             vec![TriageDecision::CreateJob {
                 prompt: AUDIO_ONLY_JOB_PROMPT.to_string(),
                 kind: "action".to_string(),
+                profile: None,
+                workspace: None,
+                approval: None,
             }]
         } else {
             result.decisions
@@ -454,8 +782,30 @@ pub async fn triage_tick(db: &PgPool, ai: &dyn AiService) -> Result<u32> {
             .iter()
             .find_map(|m| m.trace_id)
             .unwrap_or_else(Uuid::new_v4);
+        let confirmed = msgs
+            .iter()
+            .any(|m| m.content.as_deref().and_then(explicit_answer) == Some(true));
         let mut tx = db.begin().await?;
 
+        // only route what this replica still holds; if the lease ran out and
+        // another replica took the messages over, its decisions win
+        let routed = sqlx::query_scalar!(
+            r#"
+            UPDATE messages SET routed_at = now(), claimed_by = NULL, lease_until = NULL
+            WHERE id = ANY($1) AND routed_at IS NULL AND claimed_by = $2
+            RETURNING id
+            "#,
+            &source_ids,
+            instance_id()
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        if routed.len() < source_ids.len() {
+            tracing::warn!(chat_id = %chat_id, "triage: lost the claim on a batch, skipping it");
+            tx.rollback().await?;
+            continue;
+        }
+
         apply_decisions(
             &mut tx,
             chat_id,
@@ -463,19 +813,10 @@ pub async fn triage_tick(db: &PgPool, ai: &dyn AiService) -> Result<u32> {
             &source_ids,
             trace_id,
             &mut is_subscribed,
+            confirmed,
         )
         .await?;
 
-        sqlx::query!(
-            r#"
-            UPDATE messages SET routed_at = now()
-            WHERE id = ANY($1)
-            "#,
-            &source_ids
-        )
-        .execute(&mut *tx)
-        .await?;
-
         sqlx::query!(
             r#"
             INSERT INTO events (trace_id, source, action, payload)
@@ -498,12 +839,16 @@ pub async fn triage_tick(db: &PgPool, ai: &dyn AiService) -> Result<u32> {
 pub async fn triage(ctx: &DaemonContext) -> Result<()> {
     let ai: Arc<dyn AiService> = crate::get_ai_service();
     let poll_ms: u64 = ctx.env_parse("YUI_LOOP_POLL_MS_TRIAGE").unwrap_or(500);
+    let mut wakeup = Wakeup::listen(ctx.db(), &[MESSAGES_CHANNEL]).await;
+    let mut heartbeat = Heartbeat::new("triage");
 
     loop {
         tokio::select! {
             _ = ctx.shutdown_signal() => break,
-            _ = tokio::time::sleep(std::time::Duration::from_millis(poll_ms)) => {
-                match triage_tick(ctx.db(), ai.as_ref()).await {
+            _ = wakeup.wait(std::time::Duration::from_millis(poll_ms)) => {
+                let result = metrics::observe_tick("triage", triage_tick(ctx.db(), ai.as_ref()).await);
+                heartbeat.record(ctx.db(), &result).await;
+                match result {
                     Ok(n) if n > 0 => tracing::info!(processed = n, "triage tick"),
                     Err(e) => tracing::error!(error = %e, "triage tick failed"),
                     _ => {}
@@ -554,6 +899,42 @@ mod tests {
         }
     }
 
+    /// Approves the given job whatever the user said.
+    struct ApprovingAiService(Uuid);
+
+    #[async_trait::async_trait]
+    impl AiService for ApprovingAiService {
+        async fn triage_batch(
+            &self,
+            _input: TriageBatchInput,
+        ) -> anyhow::Result<TriageBatchDecision> {
+            Ok(TriageBatchDecision {
+                decisions: vec![TriageDecision::ApproveJob {
+                    job_id: self.0,
+                    approved: true,
+                }],
+            })
+        }
+
+        async fn enrich_job(&self, input: EnrichInput) -> anyhow::Result<EnrichOutput> {
+            Ok(EnrichOutput {
+                enriched_prompt: input.prompt,
+            })
+        }
+
+        async fn embed_text(&self, _text: &str) -> anyhow::Result<Vec<f32>> {
+            Ok(vec![])
+        }
+
+        async fn rewrite_reply(
+            &self,
+            content: &str,
+            _history: &[String],
+        ) -> anyhow::Result<String> {
+            Ok(content.to_string())
+        }
+    }
+
     async fn setup() -> (IsolatedTestDb, PgPool) {
         let base = TestDatabase::embedded().await.unwrap();
         let db = base.isolated("triage").await.unwrap();
@@ -570,6 +951,8 @@ mod tests {
                 attachments jsonb NOT NULL DEFAULT '[]'::jsonb,
                 trace_id uuid,
                 routed_at timestamptz,
+                claimed_by text,
+                lease_until timestamptz,
                 created_at timestamptz NOT NULL DEFAULT now(),
                 updated_at timestamptz NOT NULL DEFAULT now()
             );
@@ -585,9 +968,47 @@ mod tests {
                 cancel_reason text,
                 finished_at timestamptz,
                 resume_input text,
+                question_pending text,
+                agent_answer text,
+                resource_profile text,
+                depends_on uuid[] NOT NULL DEFAULT '{}',
+                workspace_id uuid,
+                progress text,
+                requires_approval bool NOT NULL DEFAULT false,
+                approval_reason text,
+                approval_expires_at timestamptz,
+                created_at timestamptz NOT NULL DEFAULT now()
+            );
+
+            CREATE TABLE logs (
+                id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+                job_id uuid NOT NULL,
+                stream text NOT NULL,
+                line text NOT NULL,
                 created_at timestamptz NOT NULL DEFAULT now()
             );
 
+            CREATE TABLE artifacts (
+                id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+                job_id uuid NOT NULL,
+                chat_id text NOT NULL,
+                name text NOT NULL,
+                kind text NOT NULL,
+                mime text NOT NULL,
+                path text NOT NULL,
+                created_at timestamptz NOT NULL DEFAULT now()
+            );
+
+            CREATE TABLE workspaces (
+                id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+                chat_id text NOT NULL,
+                name text NOT NULL,
+                quota_mb integer NOT NULL,
+                reset_requested_at timestamptz,
+                last_used_at timestamptz,
+                UNIQUE (chat_id, name)
+            );
+
             CREATE TABLE outbox (
                 id uuid PRIMARY KEY DEFAULT (md5(random()::text || clock_timestamp()::text)::uuid),
                 chat_id text NOT NULL,
@@ -677,4 +1098,86 @@ mod tests {
         assert_eq!(outbox_count, 0);
     }
 
+    #[tokio::test]
+    async fn messages_leased_by_another_replica_are_left_alone() {
+        let (_db, pool) = setup().await;
+        sqlx::query(
+            r#"
+            INSERT INTO messages (id, platform_chat_id, direction, content, claimed_by, lease_until)
+            VALUES ($1, '25491067@s.whatsapp.net', 'in', 'hi', 'other', now() + interval '1 minute')
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        assert_eq!(triage_tick(&pool, &GreetingAiService).await.unwrap(), 0);
+        let outbox_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM outbox")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(outbox_count, 0);
+    }
+
+    #[tokio::test]
+    async fn only_a_plain_yes_approves_a_job() {
+        let (_db, pool) = setup().await;
+        let chat_id = "25491067@s.whatsapp.net";
+        let job_id = Uuid::new_v4();
+        sqlx::query(
+            r#"
+            INSERT INTO jobs (id, kind, chat_id, status, prompt, requires_approval)
+            VALUES ($1, 'action', $2, 'awaiting_approval', 'delete all test files', true)
+            "#,
+        )
+        .bind(job_id)
+        .bind(chat_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+        let status = || async {
+            sqlx::query_scalar::<_, String>("SELECT status FROM jobs WHERE id = $1")
+                .bind(job_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+        };
+
+        for (content, expected) in [
+            ("hmm, which files?", "awaiting_approval"),
+            ("Yes!", "pending"),
+        ] {
+            sqlx::query(
+                "INSERT INTO messages (id, platform_chat_id, direction, content) VALUES ($1, $2, 'in', $3)",
+            )
+            .bind(Uuid::new_v4())
+            .bind(chat_id)
+            .bind(content)
+            .execute(&pool)
+            .await
+            .unwrap();
+            triage_tick(&pool, &ApprovingAiService(job_id))
+                .await
+                .unwrap();
+            assert_eq!(status().await, expected);
+        }
+    }
+
+    #[test]
+    fn unknown_notify_policies_fall_back_to_always() {
+        assert!(matches!(
+            parse_notify_policy(" On_Change "),
+            CronNotifyPolicy::OnChange
+        ));
+        assert!(matches!(
+            parse_notify_policy("noteworthy"),
+            CronNotifyPolicy::Noteworthy
+        ));
+        assert!(matches!(
+            parse_notify_policy("whenever"),
+            CronNotifyPolicy::Always
+        ));
+        assert!(matches!(parse_notify_policy(""), CronNotifyPolicy::Always));
+    }
 }
@@ -0,0 +1,80 @@
+use sqlx::PgPool;
+use sqlx::postgres::PgListener;
+use std::time::{Duration, Instant};
+
+pub const MESSAGES_CHANNEL: &str = "yui_messages";
+pub const JOBS_CHANNEL: &str = "yui_jobs";
+pub const OUTBOX_CHANNEL: &str = "yui_outbox";
+pub const CRONS_CHANNEL: &str = "yui_crons";
+
+/// How long a loop polls before trying to listen again after the listener
+/// failed, so a database that is down is not hit with a reconnect per tick.
+const RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// Wakes a loop on a Postgres NOTIFY from its input tables, falling back to
+/// the regular poll interval. Notifications carry no work, they only cut the
+/// sleep short, so the database stays the only coordinator between loops.
+pub struct Wakeup {
+    db: PgPool,
+    channels: Vec<String>,
+    listener: Option<PgListener>,
+    reconnect_at: Instant,
+}
+
+async fn connect(db: &PgPool, channels: &[String]) -> Option<PgListener> {
+    match PgListener::connect_with(db).await {
+        Ok(mut listener) => match listener
+            .listen_all(channels.iter().map(String::as_str))
+            .await
+        {
+            Ok(()) => Some(listener),
+            Err(e) => {
+                tracing::warn!(error = %e, ?channels, "LISTEN failed, polling for now");
+                None
+            }
+        },
+        Err(e) => {
+            tracing::warn!(error = %e, ?channels, "listener connect failed, polling for now");
+            None
+        }
+    }
+}
+
+impl Wakeup {
+    pub async fn listen(db: &PgPool, channels: &[&str]) -> Self {
+        let channels: Vec<String> = channels.iter().map(|c| c.to_string()).collect();
+        let listener = connect(db, &channels).await;
+        Self {
+            db: db.clone(),
+            channels,
+            listener,
+            reconnect_at: Instant::now() + RECONNECT_DELAY,
+        }
+    }
+
+    /// Returns after a notification arrives or `fallback` elapses, whichever is first.
+    pub async fn wait(&mut self, fallback: Duration) {
+        if self.listener.is_none() && Instant::now() >= self.reconnect_at {
+            self.listener = connect(&self.db, &self.channels).await;
+            self.reconnect_at = Instant::now() + RECONNECT_DELAY;
+        }
+        let Some(listener) = self.listener.as_mut() else {
+            tokio::time::sleep(fallback).await;
+            return;
+        };
+
+        let failed = tokio::select! {
+            received = listener.recv() => received.err(),
+            _ = tokio::time::sleep(fallback) => None,
+        };
+        // a broken connection fails every recv straight away, so drop it and
+        // sleep out the poll interval; the tick that follows picks up anything
+        // missed while it was down
+        if let Some(e) = failed {
+            tracing::warn!(error = %e, "listener recv failed, polling until it reconnects");
+            self.listener = None;
+            self.reconnect_at = Instant::now() + RECONNECT_DELAY;
+            tokio::time::sleep(fallback).await;
+        }
+    }
+}
@@ -0,0 +1,272 @@
+use crate::services::{clear_dir, dir_size, project_path, workspace_quota_mb};
+use forge::prelude::*;
+use sqlx::PgPool;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+/// Lowercase, dash-separated name, so "Forge v2" and "forge-v2" are the
+/// same workspace. `None` when nothing usable is left.
+pub(crate) fn normalize_workspace_name(name: &str) -> Option<String> {
+    let mut normalized = String::new();
+    for c in name.trim().to_lowercase().chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            normalized.push(c);
+        } else if !normalized.is_empty() && !normalized.ends_with('-') {
+            normalized.push('-');
+        }
+    }
+    let normalized: String = normalized.trim_end_matches('-').chars().take(64).collect();
+    (!normalized.is_empty()).then_some(normalized)
+}
+
+/// The chat's workspace with this name, created on first use.
+pub(crate) async fn ensure_workspace(
+    conn: &mut sqlx::PgConnection,
+    chat_id: &str,
+    name: &str,
+) -> Result<Option<Uuid>> {
+    let Some(name) = normalize_workspace_name(name) else {
+        return Ok(None);
+    };
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO workspaces (chat_id, name, quota_mb)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (chat_id, name) DO UPDATE SET name = EXCLUDED.name
+        RETURNING id
+        "#,
+        chat_id,
+        name,
+        workspace_quota_mb()
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(Some(id))
+}
+
+pub(crate) enum WorkspaceLock {
+    Acquired {
+        name: String,
+        path: PathBuf,
+    },
+    /// Another job holds it or a reset is pending; the job waits.
+    Busy,
+    OverQuota {
+        name: String,
+        size_mb: i64,
+        quota_mb: i32,
+    },
+}
+
+/// Takes the workspace for a claimed job. The lock only holds while that job
+/// is running or has a live claim, so it never has to be released.
+pub(crate) async fn lock_workspace(
+    db: &PgPool,
+    job_id: Uuid,
+    workspace_id: Uuid,
+) -> Result<WorkspaceLock> {
+    let locked = sqlx::query!(
+        r#"
+        UPDATE workspaces w SET locked_by = $2, last_used_at = now()
+        WHERE w.id = $1
+          AND w.reset_requested_at IS NULL
+          AND NOT EXISTS (
+              SELECT 1 FROM jobs l
+              WHERE l.id = w.locked_by AND l.id <> $2
+                AND (l.status = 'running' OR (l.status = 'pending' AND l.lease_until > now()))
+          )
+        RETURNING name, quota_mb
+        "#,
+        workspace_id,
+        job_id
+    )
+    .fetch_optional(db)
+    .await?;
+    let Some(locked) = locked else {
+        return Ok(WorkspaceLock::Busy);
+    };
+
+    let path = project_path(workspace_id);
+    let size = dir_size(&path)
+        .await
+        .map_err(|e| ForgeError::Internal(format!("failed to measure workspace: {e}")))?;
+    sqlx::query!(
+        "UPDATE workspaces SET size_bytes = $2 WHERE id = $1",
+        workspace_id,
+        size as i64
+    )
+    .execute(db)
+    .await?;
+
+    let size_mb = (size / (1024 * 1024)) as i64;
+    if size_mb >= i64::from(locked.quota_mb) {
+        return Ok(WorkspaceLock::OverQuota {
+            name: locked.name,
+            size_mb,
+            quota_mb: locked.quota_mb,
+        });
+    }
+    Ok(WorkspaceLock::Acquired {
+        name: locked.name,
+        path,
+    })
+}
+
+/// Tells the agent which part of its workspace outlives the run.
+pub(crate) fn prompt_with_project(prompt: String, name: &str) -> String {
+    format!(
+        "{prompt}\n\nThe persistent workspace for `{name}` is the `project` directory in your \
+         workspace. Work that should still be there for the next task in this project goes \
+         there; everything else is discarded after this task."
+    )
+}
+
+/// Records how big a job's workspace has grown once its run ends. A run can
+/// write past the quota, since it is only checked when the workspace is
+/// locked, so the run that crosses it tells the chat; the next lock then
+/// refuses until the workspace is reset or cleaned up.
+pub(crate) async fn refresh_workspace_size(db: &PgPool, job_id: Uuid) -> Result<()> {
+    let Some(workspace) = sqlx::query!(
+        r#"
+        SELECT w.id, w.chat_id, w.name, w.quota_mb, w.size_bytes
+        FROM jobs j JOIN workspaces w ON w.id = j.workspace_id
+        WHERE j.id = $1
+        "#,
+        job_id
+    )
+    .fetch_optional(db)
+    .await?
+    else {
+        return Ok(());
+    };
+    let size = match dir_size(&project_path(workspace.id)).await {
+        Ok(size) => size as i64,
+        Err(e) => {
+            tracing::warn!(workspace_id = %workspace.id, error = %e, "failed to measure workspace");
+            return Ok(());
+        }
+    };
+    sqlx::query!(
+        "UPDATE workspaces SET size_bytes = $2 WHERE id = $1",
+        workspace.id,
+        size
+    )
+    .execute(db)
+    .await?;
+
+    let quota_bytes = i64::from(workspace.quota_mb) * 1024 * 1024;
+    if size < quota_bytes || workspace.size_bytes >= quota_bytes {
+        return Ok(());
+    }
+    let size_mb = size / (1024 * 1024);
+    tracing::warn!(
+        workspace_id = %workspace.id,
+        size_mb,
+        quota_mb = workspace.quota_mb,
+        "workspace went over its quota"
+    );
+    sqlx::query!(
+        "INSERT INTO outbox (chat_id, content) VALUES ($1, $2)",
+        workspace.chat_id,
+        format!(
+            "the {} workspace is now over its quota ({size_mb} of {} MB), \
+             reset it or free some space before the next task in it",
+            workspace.name, workspace.quota_mb
+        )
+    )
+    .execute(db)
+    .await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO events (source, action, payload)
+        VALUES ('runtime', 'workspace_over_quota', $1)
+        "#,
+        serde_json::json!({
+            "workspace_id": workspace.id,
+            "job_id": job_id,
+            "size_bytes": size,
+            "quota_mb": workspace.quota_mb,
+        })
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Empties workspaces whose reset was requested from the chat or dashboard,
+/// once no job is using them, and tells the chat.
+pub(crate) async fn reset_workspaces(db: &PgPool) -> Result<()> {
+    let requested = sqlx::query!(
+        r#"
+        SELECT w.id, w.chat_id, w.name, w.reset_requested_at as "reset_requested_at!"
+        FROM workspaces w
+        WHERE w.reset_requested_at IS NOT NULL
+          AND NOT EXISTS (
+              SELECT 1 FROM jobs l
+              WHERE l.id = w.locked_by
+                AND (l.status = 'running' OR (l.status = 'pending' AND l.lease_until > now()))
+          )
+        LIMIT 10
+        "#
+    )
+    .fetch_all(db)
+    .await?;
+
+    for workspace in requested {
+        if let Err(e) = clear_dir(&project_path(workspace.id)).await {
+            tracing::error!(workspace_id = %workspace.id, error = %e, "failed to reset workspace");
+            continue;
+        }
+        // a second replica that cleared it too finds the request gone
+        let reset = sqlx::query!(
+            r#"
+            UPDATE workspaces SET reset_requested_at = NULL, size_bytes = 0
+            WHERE id = $1 AND reset_requested_at = $2
+            "#,
+            workspace.id,
+            workspace.reset_requested_at
+        )
+        .execute(db)
+        .await?;
+        if reset.rows_affected() == 0 {
+            continue;
+        }
+
+        tracing::info!(workspace_id = %workspace.id, name = %workspace.name, "workspace reset");
+        sqlx::query!(
+            "INSERT INTO outbox (chat_id, content) VALUES ($1, $2)",
+            workspace.chat_id,
+            format!("cleared the {} workspace", workspace.name)
+        )
+        .execute(db)
+        .await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO events (source, action, payload)
+            VALUES ('runtime', 'workspace_reset', $1)
+            "#,
+            serde_json::json!({ "workspace_id": workspace.id, "chat_id": workspace.chat_id })
+        )
+        .execute(db)
+        .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn workspace_names_normalize_to_one_spelling() {
+        assert_eq!(
+            normalize_workspace_name("Forge v2").as_deref(),
+            Some("forge-v2")
+        );
+        assert_eq!(
+            normalize_workspace_name(" forge-v2/ ").as_deref(),
+            Some("forge-v2")
+        );
+        assert_eq!(normalize_workspace_name("../.."), None);
+    }
+}
@@ -43,6 +43,55 @@ mod embedded {
     }
 }
 
+mod http {
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode, header},
+        response::{IntoResponse, Response},
+    };
+    use std::future::Future;
+    use std::pin::Pin;
+
+    fn readiness() -> Response {
+        let max_age = std::env::var("YUI_READY_MAX_TICK_AGE_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(60);
+        let stale = crate::functions::heartbeat::stale_critical_daemons(
+            std::time::Duration::from_secs(max_age),
+        );
+        if stale.is_empty() {
+            (StatusCode::OK, "ready").into_response()
+        } else {
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                format!("stale: {}", stale.join(", ")),
+            )
+                .into_response()
+        }
+    }
+
+    /// Routes served outside the Forge RPC gateway; anything else falls through
+    /// to the embedded frontend.
+    pub fn serve(req: Request<Body>) -> Pin<Box<dyn Future<Output = Response> + Send>> {
+        match req.uri().path() {
+            "/metrics" => Box::pin(async {
+                (
+                    [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+                    crate::services::metrics::render(),
+                )
+                    .into_response()
+            }),
+            "/readyz" => Box::pin(async { readiness() }),
+            path if path.starts_with("/artifacts/") => crate::functions::artifacts::serve(req),
+            #[cfg(feature = "embedded-frontend")]
+            _ => super::embedded::serve_frontend(req),
+            #[cfg(not(feature = "embedded-frontend"))]
+            _ => Box::pin(async { (StatusCode::NOT_FOUND, "not found").into_response() }),
+        }
+    }
+}
+
 static AI_SERVICE: tokio::sync::OnceCell<std::sync::Arc<dyn services::AiService>> =
     tokio::sync::OnceCell::const_new();
 
@@ -91,13 +140,26 @@ async fn main() -> Result<()> {
     let fns = builder.function_registry_mut();
     fns.register_query::<functions::ListEventsQuery>();
     fns.register_query::<functions::ListJobsQuery>();
+    fns.register_query::<functions::ListQueueQuery>();
     fns.register_query::<functions::ListOutboxQuery>();
     fns.register_query::<functions::ListCronsQuery>();
     fns.register_query::<functions::ListMessagesQuery>();
+    fns.register_query::<functions::ListJobLogsQuery>();
+    fns.register_query::<functions::ListJobStepsQuery>();
+    fns.register_query::<functions::ListArtifactsQuery>();
     fns.register_query::<functions::GetTraceQuery>();
     fns.register_query::<functions::GetHealthQuery>();
+    fns.register_query::<functions::GetStageLatencyQuery>();
+    fns.register_query::<functions::ListSecretsQuery>();
+    fns.register_query::<functions::ListWorkspacesQuery>();
     fns.register_mutation::<functions::CancelJobMutation>();
+    fns.register_mutation::<functions::RetryJobMutation>();
     fns.register_mutation::<functions::ToggleCronMutation>();
+    fns.register_mutation::<functions::SetCronNotifyPolicyMutation>();
+    fns.register_mutation::<functions::SetCronEgressPolicyMutation>();
+    fns.register_mutation::<functions::SetSecretMutation>();
+    fns.register_mutation::<functions::DeleteSecretMutation>();
+    fns.register_mutation::<functions::ResetWorkspaceMutation>();
 
     let daemons = builder.daemon_registry_mut();
     daemons.register::<functions::GatewayDaemon>();
@@ -109,8 +171,7 @@ async fn main() -> Result<()> {
     daemons.register::<functions::DeliveryDaemon>();
     daemons.register::<functions::AuditDaemon>();
 
-    #[cfg(feature = "embedded-frontend")]
-    builder.frontend_handler(embedded::serve_frontend);
+    builder.frontend_handler(http::serve);
 
     builder.config(config).build()?.run().await
 }
@@ -0,0 +1,16 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+#[forge::model]
+pub struct AgentStep {
+    pub id: Uuid,
+    pub job_id: Uuid,
+    pub step_number: i32,
+    pub tool_name: Option<String>,
+    pub input_summary: Option<String>,
+    pub output_summary: Option<String>,
+    pub duration_ms: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,20 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A file a job produced, recorded when it was handed to the outbox.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+#[forge::model]
+pub struct Artifact {
+    pub id: Uuid,
+    pub job_id: Uuid,
+    pub chat_id: String,
+    pub name: String,
+    pub kind: String,
+    pub mime: String,
+    pub size_bytes: i64,
+    pub sha256: String,
+    pub path: String,
+    pub trace_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
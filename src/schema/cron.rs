@@ -2,6 +2,22 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+#[forge::forge_enum]
+pub enum CronNotifyPolicy {
+    Always,
+    OnChange,
+    Noteworthy,
+}
+
+/// Network access an agent run gets: nothing beyond the model API, the model
+/// API plus allowlisted domains, or everything.
+#[forge::forge_enum]
+pub enum EgressPolicy {
+    None,
+    Allowlist,
+    Full,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 #[forge::model]
 pub struct Cron {
@@ -12,6 +28,11 @@ pub struct Cron {
     pub chat_id: String,
     pub prompt: String,
     pub enabled: bool,
+    pub notify_policy: CronNotifyPolicy,
+    /// Overrides the job kind's egress policy for this cron's runs.
+    pub egress_policy: Option<EgressPolicy>,
+    /// Domains its runs may reach under the `allowlist` policy.
+    pub egress_allow: Vec<String>,
     pub last_run_at: Option<DateTime<Utc>>,
     pub next_run_at: Option<DateTime<Utc>>,
     pub last_job_id: Option<Uuid>,
@@ -6,6 +6,8 @@ use uuid::Uuid;
 pub enum JobStatus {
     Draft,
     Pending,
+    /// Enriched, but waits for the user to confirm it in chat.
+    AwaitingApproval,
     Running,
     Paused,
     Done,
@@ -30,15 +32,24 @@ pub struct Job {
     pub prompt: Option<String>,
     pub enriched_prompt: Option<String>,
     pub source_ids: Vec<Uuid>,
+    /// Jobs that must be done before this one starts.
+    pub depends_on: Vec<Uuid>,
+    /// Persistent workspace linked into the job's directory as `project`.
+    pub workspace_id: Option<Uuid>,
     pub resume_input: Option<String>,
     pub output: Option<String>,
     pub error: Option<String>,
     pub cancel_reason: Option<String>,
     pub forge_job_id: Option<Uuid>,
+    pub cron_id: Option<Uuid>,
     pub session_id: Option<String>,
     pub container_id: Option<String>,
     pub last_heartbeat_at: Option<DateTime<Utc>>,
     pub question_pending: Option<String>,
+    pub priority: i32,
+    pub attempt_count: i32,
+    pub max_attempts: i32,
+    pub retry_at: Option<DateTime<Utc>>,
     pub started_at: Option<DateTime<Utc>>,
     pub finished_at: Option<DateTime<Utc>>,
     pub trace_id: Option<Uuid>,
@@ -1,13 +1,21 @@
+pub mod agent_step;
+pub mod artifact;
 pub mod cron;
 pub mod event;
 pub mod job;
 pub mod log_entry;
 pub mod message;
 pub mod outbox;
+pub mod secret;
+pub mod workspace;
 
+pub use agent_step::*;
+pub use artifact::*;
 pub use cron::*;
 pub use event::*;
 pub use job::*;
-
+pub use log_entry::*;
 pub use message::*;
 pub use outbox::*;
+pub use secret::*;
+pub use workspace::*;
@@ -0,0 +1,24 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[forge::forge_enum]
+pub enum SecretInjection {
+    Env,
+    File,
+}
+
+/// A vault entry as the dashboard sees it; the value never leaves the database
+/// except into the run that is allowed to use it.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+#[forge::model]
+pub struct Secret {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub inject_as: SecretInjection,
+    pub allowed_kinds: Vec<String>,
+    pub allowed_crons: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
@@ -0,0 +1,20 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A persistent project directory a chat's jobs can share.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+#[forge::model]
+pub struct Workspace {
+    pub id: Uuid,
+    pub chat_id: String,
+    pub name: String,
+    pub quota_mb: i32,
+    /// Measured after each run that used it.
+    pub size_bytes: i64,
+    /// Job using it right now; stale once that job stops running.
+    pub locked_by: Option<Uuid>,
+    pub reset_requested_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
@@ -1,6 +1,17 @@
+use crate::schema::SecretInjection;
+use crate::schema::{Attachment, LogStream};
+use crate::services::agent_runner::{FrameOffset, StepReport};
+use crate::services::workspace::{PROJECT_MOUNT, copy_agent_file, prepare_project};
+use crate::services::{
+    EgressRules, ResourceLimits, SecretValue, create_private_dir, egress_config, env_file,
+    write_private,
+};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
 use uuid::Uuid;
@@ -10,9 +21,19 @@ pub struct ExecutionInput {
     pub job_id: Uuid,
     pub trace_id: Uuid,
     pub prompt: String,
-    pub attachments: Vec<serde_json::Value>,
+    /// Host-side attachments from the job's source messages.
+    pub attachments: Vec<Attachment>,
+    pub limits: ResourceLimits,
     pub session_id: Option<String>,
     pub resume_input: Option<String>,
+    pub agent_token: Option<String>,
+    #[serde(default)]
+    pub secrets: Vec<SecretValue>,
+    /// Persistent workspace, mounted at `/workspace/project`.
+    #[serde(default)]
+    pub project_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub egress: EgressRules,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,9 +42,36 @@ pub struct ExecutionConfig {
     pub workspace_dir: String,
     pub media_dir: String,
     pub sessions_dir: String,
+    /// Per-job env files and secret files, kept only while the container runs.
+    pub secrets_dir: String,
     pub start_timeout_secs: u64,
     pub idle_timeout_secs: u64,
     pub max_attachment_mb: u64,
+    pub max_log_kb: usize,
+    /// Copy input attachments into `/workspace/inputs` instead of pointing the
+    /// agent at the read-only media mount.
+    pub copy_attachments: bool,
+    /// Pass the profile's disk limit as `--storage-opt size=`, which only works
+    /// on storage drivers with quota support.
+    pub disk_quota: bool,
+    /// Binary used for every container command, e.g. `docker` or `podman`.
+    pub container_cli: String,
+    /// Podman mode: bind mounts get a shared SELinux label and the disk quota is
+    /// skipped, since rootless storage cannot enforce it.
+    pub podman: bool,
+    /// `--network` for agent containers; `none` cuts them off from the internet.
+    pub network: Option<String>,
+    pub user: Option<String>,
+    pub security_opts: Vec<String>,
+    /// Extra `-v` mounts in `host:container[:options]` form.
+    pub extra_mounts: Vec<String>,
+    /// Host environment variables copied into the container when set.
+    pub env_passthrough: Vec<String>,
+    /// Appended to `run` as-is, just before the image.
+    pub extra_run_args: Vec<String>,
+    /// Where agents reach the callback API from inside the container, the
+    /// address its own listener is bound to on the bridge.
+    pub agent_api_url: String,
 }
 
 impl Default for ExecutionConfig {
@@ -33,13 +81,38 @@ impl Default for ExecutionConfig {
             workspace_dir: "storage/workspaces".to_string(),
             media_dir: "storage/media".to_string(),
             sessions_dir: "storage/sessions".to_string(),
+            secrets_dir: "storage/secrets".to_string(),
             start_timeout_secs: 60,
             idle_timeout_secs: 300,
             max_attachment_mb: 100,
+            max_log_kb: 1024,
+            copy_attachments: true,
+            disk_quota: false,
+            container_cli: "docker".to_string(),
+            podman: false,
+            network: None,
+            user: None,
+            security_opts: vec![],
+            extra_mounts: vec![],
+            env_passthrough: vec![],
+            extra_run_args: vec![],
+            agent_api_url: "http://172.17.0.1:8090".to_string(),
         }
     }
 }
 
+fn env_list(name: &str) -> Vec<String> {
+    std::env::var(name)
+        .map(|v| {
+            v.split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(ToString::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
 // JSONL protocol frames from the container
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
@@ -48,6 +121,21 @@ pub enum ContainerFrame {
     Session { session_id: String },
     #[serde(rename = "log")]
     Log { stream: String, line: String },
+    /// One tool call or model turn; every field is optional so older images still parse.
+    #[serde(rename = "step")]
+    Step {
+        #[serde(default)]
+        tool: Option<String>,
+        #[serde(default)]
+        input: Option<String>,
+        #[serde(default)]
+        output: Option<String>,
+        #[serde(default)]
+        duration_ms: Option<i32>,
+    },
+    /// A short human-readable update meant for the user, e.g. "cloned the repo, running tests now".
+    #[serde(rename = "progress")]
+    Progress { message: String },
     #[serde(rename = "ask_user")]
     AskUser { question: String },
     #[serde(rename = "final")]
@@ -75,15 +163,84 @@ pub enum ExecutionOutcome {
     },
     Failed {
         error: String,
+        /// Worth running again: the agent said so, or the run died of a
+        /// transient cause such as an idle timeout.
+        retryable: bool,
     },
 }
 
+/// What the executor reports while a container is still running; the final
+/// result comes back separately as an `ExecutionOutcome`. `Seen` follows the
+/// events of each handled line, counting the lines of its stream so far.
+#[derive(Debug, Clone)]
+pub enum ExecutorEvent {
+    Log { stream: LogStream, line: String },
+    Session { session_id: String },
+    Step(StepReport),
+    Progress { message: String },
+    Seen { stream: LogStream, count: u64 },
+}
+
+pub type EventSender = tokio::sync::mpsc::UnboundedSender<ExecutorEvent>;
+
+/// Forwards container output to the runtime, shared by the stdout and stderr
+/// readers so the per-run byte cap covers both streams.
+#[derive(Clone)]
+struct LogSink {
+    tx: EventSender,
+    bytes: Arc<AtomicUsize>,
+    cap: usize,
+}
+
+impl LogSink {
+    fn new(tx: EventSender, cap: usize) -> Self {
+        Self {
+            tx,
+            bytes: Arc::new(AtomicUsize::new(0)),
+            cap,
+        }
+    }
+
+    fn send(&self, stream: LogStream, line: String) {
+        let before = self.bytes.fetch_add(line.len(), Ordering::Relaxed);
+        if before + line.len() <= self.cap {
+            let _ = self.tx.send(ExecutorEvent::Log { stream, line });
+        } else if before <= self.cap {
+            // first line over the cap: say so once, then drop the rest
+            let _ = self.tx.send(ExecutorEvent::Log {
+                stream: LogStream::Stderr,
+                line: format!("[log output truncated after {} KB]", self.cap / 1024),
+            });
+        }
+    }
+
+    /// Counts a replayed line the runtime already stored towards the cap
+    /// without sending it again.
+    fn replayed(&self, line: &str) {
+        self.bytes.fetch_add(line.len(), Ordering::Relaxed);
+    }
+
+    fn seen(&self, stream: LogStream, count: u64) {
+        let _ = self.tx.send(ExecutorEvent::Seen { stream, count });
+    }
+}
+
+fn parse_log_stream(stream: &str) -> LogStream {
+    if stream.eq_ignore_ascii_case("stderr") {
+        LogStream::Stderr
+    } else {
+        LogStream::Stdout
+    }
+}
+
 pub struct AgentExecutor {
     config: ExecutionConfig,
 }
 
 impl AgentExecutor {
     pub fn from_env() -> Self {
+        let container_cli =
+            std::env::var("YUI_CONTAINER_CLI").unwrap_or_else(|_| "docker".to_string());
         let config = ExecutionConfig {
             docker_image: std::env::var("YUI_DOCKER_IMAGE")
                 .unwrap_or_else(|_| "claude-code:latest".to_string()),
@@ -93,6 +250,8 @@ impl AgentExecutor {
                 .unwrap_or_else(|_| "storage/media".to_string()),
             sessions_dir: std::env::var("YUI_SESSIONS_DIR")
                 .unwrap_or_else(|_| "storage/sessions".to_string()),
+            secrets_dir: std::env::var("YUI_SECRETS_DIR")
+                .unwrap_or_else(|_| "storage/secrets".to_string()),
             start_timeout_secs: std::env::var("YUI_DOCKER_TIMEOUT_START_SECS")
                 .ok()
                 .and_then(|s| s.parse().ok())
@@ -105,59 +264,177 @@ impl AgentExecutor {
                 .ok()
                 .and_then(|s| s.parse().ok())
                 .unwrap_or(100),
+            max_log_kb: std::env::var("YUI_MAX_JOB_LOG_KB")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(1024),
+            copy_attachments: std::env::var("YUI_COPY_ATTACHMENTS")
+                .map(|v| v != "0" && v != "false")
+                .unwrap_or(true),
+            disk_quota: std::env::var("YUI_DOCKER_DISK_QUOTA")
+                .map(|v| v == "1" || v == "true")
+                .unwrap_or(false),
+            container_cli: container_cli.clone(),
+            podman: std::env::var("YUI_CONTAINER_PODMAN")
+                .map(|v| v == "1" || v == "true")
+                .unwrap_or_else(|_| container_cli.ends_with("podman")),
+            network: std::env::var("YUI_CONTAINER_NETWORK")
+                .ok()
+                .filter(|v| !v.is_empty()),
+            user: std::env::var("YUI_CONTAINER_USER")
+                .ok()
+                .filter(|v| !v.is_empty()),
+            security_opts: env_list("YUI_CONTAINER_SECURITY_OPTS"),
+            extra_mounts: env_list("YUI_CONTAINER_MOUNTS"),
+            env_passthrough: env_list("YUI_CONTAINER_ENV"),
+            extra_run_args: std::env::var("YUI_CONTAINER_RUN_ARGS")
+                .map(|v| v.split_whitespace().map(ToString::to_string).collect())
+                .unwrap_or_default(),
+            agent_api_url: std::env::var("YUI_AGENT_API_URL")
+                .unwrap_or_else(|_| "http://172.17.0.1:8090".to_string()),
         };
         Self { config }
     }
 
+    pub fn new(config: ExecutionConfig) -> Self {
+        Self { config }
+    }
+
+    fn command(&self) -> Command {
+        Command::new(&self.config.container_cli)
+    }
+
+    /// Mount spec for `-v`, relabelled for SELinux under Podman.
+    fn volume(&self, host: &Path, container: &str, read_only: bool) -> String {
+        let mut options = vec![];
+        if read_only {
+            options.push("ro");
+        }
+        if self.config.podman {
+            options.push("z");
+        }
+        if options.is_empty() {
+            format!("{}:{container}", host.display())
+        } else {
+            format!("{}:{container}:{}", host.display(), options.join(","))
+        }
+    }
+
+    /// The configurable part of `run`: network, user, security options, extra
+    /// mounts, passed-through environment and free-form arguments. A
+    /// restricted run's egress network replaces the configured one.
+    fn configured_run_args(&self, egress_network: Option<&str>) -> Vec<String> {
+        let mut args = vec![];
+        if let Some(network) = egress_network.or(self.config.network.as_deref()) {
+            args.push(format!("--network={network}"));
+        }
+        if let Some(ref user) = self.config.user {
+            args.push(format!("--user={user}"));
+        }
+        for opt in &self.config.security_opts {
+            args.push(format!("--security-opt={opt}"));
+        }
+        for mount in &self.config.extra_mounts {
+            args.push("-v".to_string());
+            args.push(mount.clone());
+        }
+        for name in &self.config.env_passthrough {
+            if let Ok(value) = std::env::var(name) {
+                args.push("-e".to_string());
+                args.push(format!("{name}={value}"));
+            }
+        }
+        args.extend(self.config.extra_run_args.iter().cloned());
+        args
+    }
+
     fn canonical_or(path: &str) -> PathBuf {
         Path::new(path)
             .canonicalize()
             .unwrap_or_else(|_| PathBuf::from(path))
     }
 
-    pub async fn execute(
-        &self,
-        input: ExecutionInput,
-        log_tx: tokio::sync::mpsc::UnboundedSender<(String, String)>,
-    ) -> ExecutionOutcome {
+    pub async fn execute(&self, input: ExecutionInput, events: EventSender) -> ExecutionOutcome {
+        // without a network whose only way out is the proxy, a restricted run
+        // could simply ignore the proxy settings
+        if input.egress.is_restricted() && egress_config().network.is_none() {
+            return ExecutionOutcome::Failed {
+                error: "this run's network access is restricted, but YUI_EGRESS_NETWORK \
+                        is not set to enforce it"
+                    .to_string(),
+                retryable: false,
+            };
+        }
+
         let workspace = format!("{}/{}", self.config.workspace_dir, input.job_id);
         if let Err(e) = tokio::fs::create_dir_all(&workspace).await {
             return ExecutionOutcome::Failed {
                 error: format!("failed to create workspace: {e}"),
+                retryable: false,
             };
         }
 
+        let project = match input.project_dir {
+            Some(ref dir) => match prepare_project(dir).await {
+                Ok(project) => Some(project),
+                Err(e) => {
+                    return ExecutionOutcome::Failed {
+                        error: format!("failed to prepare project workspace: {e}"),
+                        retryable: false,
+                    };
+                }
+            },
+            None => None,
+        };
+
+        let attachments = self.stage_attachments(&workspace, &input.attachments).await;
+
         let prompt_path = format!("{workspace}/prompt.txt");
-        let prompt_content = if let Some(ref resume) = input.resume_input {
-            format!("{}\n\nUser response: {resume}", input.prompt)
-        } else {
-            input.prompt.clone()
+        // a resumed session already holds the original prompt and everything the
+        // agent did with it, so it only needs the answer
+        let resume_session = input.session_id.is_some() && input.resume_input.is_some();
+        let mut prompt_content = match input.resume_input {
+            Some(ref resume) if resume_session => resume.clone(),
+            Some(ref resume) => format!("{}\n\nUser response: {resume}", input.prompt),
+            None => input.prompt.clone(),
         };
+        if !resume_session && !attachments.is_empty() {
+            prompt_content.push_str("\n\nAttached files:");
+            for att in &attachments {
+                prompt_content.push_str(&format!("\n- {} ({})", att.path, att.mime));
+            }
+        }
         if let Err(e) = tokio::fs::write(&prompt_path, &prompt_content).await {
             return ExecutionOutcome::Failed {
                 error: format!("failed to write prompt: {e}"),
+                retryable: false,
             };
         }
 
-        let attachments_json = serde_json::to_string(&input.attachments).unwrap_or_default();
+        let attachments_json = serde_json::to_string(&attachments).unwrap_or_default();
 
         let workspace_abs = Self::canonical_or(&workspace);
         let media_abs = Self::canonical_or(&self.config.media_dir);
         let sessions_abs = Self::canonical_or(&self.config.sessions_dir);
 
-        let container_name = format!("yui-job-{}", input.job_id.as_simple());
+        let container_name = container_name(input.job_id);
 
-        let mut cmd = Command::new("docker");
+        // a leftover container from a run that was given up on would block the name
+        self.remove_container(&container_name).await;
+
+        // detached and without --rm: the container outlives this process, so a
+        // restarted runtime can reattach and still read its output
+        let mut cmd = self.command();
         cmd.arg("run")
-            .arg("--rm")
+            .arg("--detach")
             .arg("--name")
             .arg(&container_name)
             .arg("-v")
-            .arg(format!("{}:/workspace", workspace_abs.display()))
+            .arg(self.volume(&workspace_abs, "/workspace", false))
             .arg("-v")
-            .arg(format!("{}:/storage/media:ro", media_abs.display()))
+            .arg(self.volume(&media_abs, "/storage/media", true))
             .arg("-v")
-            .arg(format!("{}:/storage/sessions", sessions_abs.display()))
+            .arg(self.volume(&sessions_abs, "/storage/sessions", false))
             .arg("-e")
             .arg(format!("YUI_JOB_ID={}", input.job_id))
             .arg("-e")
@@ -167,11 +444,29 @@ impl AgentExecutor {
             .arg("-e")
             .arg(format!("YUI_ATTACHMENTS_JSON={attachments_json}"))
             .arg("-e")
+            .arg(format!("YUI_MAX_TURNS={}", input.limits.max_turns))
+            .arg("-e")
             .arg("IS_SANDBOX=1");
 
+        if let Some(ref project) = project {
+            let target = format!("/workspace/{PROJECT_MOUNT}");
+            cmd.arg("-v")
+                .arg(self.volume(project, &target, false))
+                .arg("-e")
+                .arg(format!("YUI_PROJECT_DIR={target}"));
+        }
         if let Some(ref session_id) = input.session_id {
             cmd.arg("-e").arg(format!("YUI_SESSION_ID={session_id}"));
         }
+        if resume_session {
+            cmd.arg("-e").arg("YUI_RESUME_SESSION=1");
+        }
+        if let Some(ref token) = input.agent_token {
+            cmd.arg("-e")
+                .arg(format!("YUI_API_URL={}", self.config.agent_api_url))
+                .arg("-e")
+                .arg(format!("YUI_API_TOKEN={token}"));
+        }
 
         // mount Claude auth credentials for the non-root yui user
         // on macOS, credentials live in keychain so we extract to a temp dir
@@ -184,26 +479,168 @@ impl AgentExecutor {
             if creds_written {
                 let auth_abs = Self::canonical_or(&auth_dir);
                 cmd.arg("-v")
-                    .arg(format!("{}:/mnt/claude-auth:ro", auth_abs.display()));
+                    .arg(self.volume(&auth_abs, "/mnt/claude-auth", true));
             }
         }
 
-        // resource limits
-        cmd.arg("--memory=2g").arg("--cpus=2");
+        let env_file_path = match self.stage_secrets(input.job_id, &input.secrets).await {
+            Ok(staged) => {
+                if let Some(ref path) = staged.env_file {
+                    cmd.arg("--env-file").arg(path);
+                }
+                if let Some(ref files) = staged.files_dir {
+                    cmd.arg("-v")
+                        .arg(self.volume(&Self::canonical_or(files), "/run/secrets", true))
+                        .arg("-e")
+                        .arg("YUI_SECRETS_DIR=/run/secrets");
+                }
+                staged.env_file
+            }
+            Err(e) => {
+                self.remove_secrets(input.job_id).await;
+                return ExecutionOutcome::Failed {
+                    error: format!("failed to stage secrets: {e}"),
+                    retryable: false,
+                };
+            }
+        };
+
+        cmd.arg(format!("--memory={}m", input.limits.memory_mb))
+            .arg(format!("--cpus={}", input.limits.cpus));
+        if self.config.disk_quota && !self.config.podman {
+            cmd.arg("--storage-opt")
+                .arg(format!("size={}m", input.limits.disk_mb));
+        }
+        let egress = egress_config();
+        for (name, value) in input
+            .egress
+            .proxy_env(&egress.proxy_url, input.agent_token.as_deref())
+        {
+            cmd.arg("-e").arg(format!("{name}={value}"));
+        }
+        // Linux engines only resolve the host name when it is mapped explicitly
+        if input.egress.is_restricted() && egress.proxy_url.contains("host.docker.internal") {
+            cmd.arg("--add-host=host.docker.internal:host-gateway");
+        }
+        let egress_network = egress
+            .network
+            .as_deref()
+            .filter(|_| input.egress.is_restricted());
+        cmd.args(self.configured_run_args(egress_network));
 
         cmd.arg(&self.config.docker_image);
 
+        // `run --detach` returns once the container is up; a hung daemon
+        // must not hold the run forever
+        cmd.kill_on_drop(true);
+        let start_timeout = std::time::Duration::from_secs(self.config.start_timeout_secs);
+        let started = tokio::time::timeout(start_timeout, cmd.output()).await;
+        // the engine has read the env file once `run` returns; it is not kept
+        // around on disk for the whole run
+        if let Some(path) = env_file_path {
+            let _ = tokio::fs::remove_file(path).await;
+        }
+        if !matches!(started, Ok(Ok(ref out)) if out.status.success()) {
+            self.remove_secrets(input.job_id).await;
+        }
+        match started {
+            Err(_) => {
+                self.remove_container(&container_name).await;
+                return ExecutionOutcome::Failed {
+                    error: format!(
+                        "container did not start within {}s",
+                        self.config.start_timeout_secs
+                    ),
+                    retryable: true,
+                };
+            }
+            Ok(Ok(out)) if out.status.success() => {}
+            Ok(Ok(out)) => {
+                return ExecutionOutcome::Failed {
+                    error: format!(
+                        "failed to start container: {}",
+                        String::from_utf8_lossy(&out.stderr).trim()
+                    ),
+                    retryable: true,
+                };
+            }
+            Ok(Err(e)) => {
+                return ExecutionOutcome::Failed {
+                    error: format!("failed to spawn {}: {e}", self.config.container_cli),
+                    retryable: false,
+                };
+            }
+        }
+
+        self.follow(input.job_id, FrameOffset::default(), project, events)
+            .await
+    }
+
+    /// Reads the container's frames until it exits, then removes it. Also used
+    /// to reattach to a container started by a previous runtime process: the
+    /// log is read from the start, and the part `seen` covers only sends the
+    /// frames that decide the outcome again, not logs, steps or progress.
+    /// `project` is the persistent workspace mounted at `/workspace/project`.
+    pub async fn follow(
+        &self,
+        job_id: Uuid,
+        seen: FrameOffset,
+        project: Option<PathBuf>,
+        events: EventSender,
+    ) -> ExecutionOutcome {
+        let container_name = container_name(job_id);
+        let outcome = self
+            .read_frames(job_id, &container_name, seen, project.as_deref(), events)
+            .await;
+        self.remove_container(&container_name).await;
+        self.remove_secrets(job_id).await;
+        outcome
+    }
+
+    async fn read_frames(
+        &self,
+        job_id: Uuid,
+        container_name: &str,
+        seen: FrameOffset,
+        project: Option<&Path>,
+        events: EventSender,
+    ) -> ExecutionOutcome {
+        let workspace = format!("{}/{}", self.config.workspace_dir, job_id);
+
+        let mut cmd = self.command();
+        cmd.arg("logs").arg("--follow").arg(container_name);
         cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
 
         let mut child = match cmd.spawn() {
             Ok(c) => c,
             Err(e) => {
                 return ExecutionOutcome::Failed {
-                    error: format!("failed to spawn docker: {e}"),
+                    error: format!("failed to follow container logs: {e}"),
+                    retryable: true,
                 };
             }
         };
 
+        let logs = LogSink::new(events.clone(), self.config.max_log_kb * 1024);
+
+        // stderr is drained alongside stdout so neither pipe stalls the other
+        let stderr_task = child.stderr.take().map(|stderr| {
+            let logs = logs.clone();
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(stderr).lines();
+                let mut count = 0u64;
+                while let Ok(Some(line)) = lines.next_line().await {
+                    count += 1;
+                    if count <= seen.stderr {
+                        logs.replayed(&line);
+                        continue;
+                    }
+                    logs.send(LogStream::Stderr, line);
+                    logs.seen(LogStream::Stderr, count);
+                }
+            })
+        });
+
         let mut final_output = None;
         let mut final_attachments: Vec<serde_json::Value> = vec![];
         let mut ask_question = None;
@@ -213,21 +650,49 @@ impl AgentExecutor {
             let mut lines = reader.lines();
 
             let idle_timeout = tokio::time::Duration::from_secs(self.config.idle_timeout_secs);
+            let mut count = 0u64;
 
             loop {
                 let line_result = tokio::time::timeout(idle_timeout, lines.next_line()).await;
 
                 match line_result {
                     Ok(Ok(Some(line))) => {
+                        count += 1;
+                        // frames the runtime already stored only matter for the
+                        // outcome; session ids are stored again, which is harmless
+                        let replayed = count <= seen.stdout;
                         match serde_json::from_str::<ContainerFrame>(&line) {
-                            Ok(ContainerFrame::Session { .. }) => {}
+                            Ok(ContainerFrame::Session { session_id }) => {
+                                let _ = events.send(ExecutorEvent::Session { session_id });
+                            }
+                            Ok(ContainerFrame::Log { line: text, .. }) if replayed => {
+                                logs.replayed(&text);
+                            }
+                            Ok(ContainerFrame::Step { .. } | ContainerFrame::Progress { .. })
+                                if replayed => {}
                             Ok(ContainerFrame::Log { stream, line: text }) => {
-                                let _ = log_tx.send((stream, text));
+                                logs.send(parse_log_stream(&stream), text);
+                            }
+                            Ok(ContainerFrame::Step {
+                                tool,
+                                input,
+                                output,
+                                duration_ms,
+                            }) => {
+                                let _ = events.send(ExecutorEvent::Step(StepReport {
+                                    tool_name: tool,
+                                    input_summary: input,
+                                    output_summary: output,
+                                    duration_ms,
+                                }));
+                            }
+                            Ok(ContainerFrame::Progress { message }) => {
+                                let _ = events.send(ExecutorEvent::Progress { message });
                             }
                             Ok(ContainerFrame::AskUser { question }) => {
                                 ask_question = Some(question);
                                 // kill container after receiving ask_user
-                                let _ = kill_container(&container_name).await;
+                                let _ = self.kill_container(container_name).await;
                                 break;
                             }
                             Ok(ContainerFrame::Final {
@@ -237,14 +702,21 @@ impl AgentExecutor {
                                 final_output = Some(output);
                                 final_attachments = attachments;
                             }
-                            Ok(ContainerFrame::Error { message, .. }) => {
-                                return ExecutionOutcome::Failed { error: message };
+                            Ok(ContainerFrame::Error { message, retryable }) => {
+                                return ExecutionOutcome::Failed {
+                                    error: message,
+                                    retryable,
+                                };
                             }
+                            Err(_) if replayed => logs.replayed(&line),
                             Err(_) => {
                                 // plain log line
-                                let _ = log_tx.send(("stdout".to_string(), line));
+                                logs.send(LogStream::Stdout, line);
                             }
                         }
+                        if !replayed {
+                            logs.seen(LogStream::Stdout, count);
+                        }
                     }
                     Ok(Ok(None)) => break,
                     Ok(Err(e)) => {
@@ -253,27 +725,24 @@ impl AgentExecutor {
                     }
                     Err(_) => {
                         // idle timeout
-                        let _ = kill_container(&container_name).await;
+                        let _ = self.kill_container(container_name).await;
                         return ExecutionOutcome::Failed {
                             error: format!(
                                 "container idle timeout after {}s",
                                 self.config.idle_timeout_secs
                             ),
+                            retryable: true,
                         };
                     }
                 }
             }
         }
 
-        if let Some(stderr) = child.stderr.take() {
-            let reader = BufReader::new(stderr);
-            let mut lines = reader.lines();
-            while let Ok(Some(line)) = lines.next_line().await {
-                let _ = log_tx.send(("stderr".to_string(), line));
-            }
+        if let Some(task) = stderr_task {
+            let _ = task.await;
         }
 
-        let exit_status = child.wait().await;
+        let _ = child.wait().await;
 
         if let Some(question) = ask_question {
             return ExecutionOutcome::Paused { question };
@@ -281,7 +750,7 @@ impl AgentExecutor {
 
         if let Some(output) = final_output {
             let resolved = self
-                .collect_output_files(&workspace, &final_attachments)
+                .collect_output_files(&workspace, project, &final_attachments)
                 .await;
             return ExecutionOutcome::Completed {
                 output,
@@ -289,29 +758,140 @@ impl AgentExecutor {
             };
         }
 
-        match exit_status {
-            Ok(status) if status.success() => ExecutionOutcome::Completed {
+        match self.container_exit_code(container_name).await {
+            Ok(0) => ExecutionOutcome::Completed {
                 output: "task completed (no structured output)".to_string(),
                 attachments: vec![],
             },
-            Ok(status) => {
-                let code = status.code().unwrap_or(-1);
-                ExecutionOutcome::Failed {
-                    error: format!("container exited with code {code}"),
-                }
-            }
+            Ok(code) => ExecutionOutcome::Failed {
+                error: format!("container exited with code {code}"),
+                retryable: false,
+            },
             Err(e) => ExecutionOutcome::Failed {
                 error: format!("failed to wait for container: {e}"),
+                retryable: true,
             },
         }
     }
 }
 
+/// Host-side secret material for one run.
+#[derive(Debug, Default)]
+struct StagedSecrets {
+    env_file: Option<PathBuf>,
+    files_dir: Option<PathBuf>,
+}
+
 impl AgentExecutor {
+    fn job_secrets_dir(&self, job_id: Uuid) -> PathBuf {
+        Path::new(&self.config.secrets_dir).join(job_id.to_string())
+    }
+
+    /// Writes `env` secrets to an env file for `run --env-file` and `file`
+    /// secrets (plus env ones too long for one line) to a directory mounted
+    /// at `/run/secrets`. Everything is private to the runtime's user, so the
+    /// container has to run as that uid to read the files.
+    async fn stage_secrets(
+        &self,
+        job_id: Uuid,
+        secrets: &[SecretValue],
+    ) -> std::io::Result<StagedSecrets> {
+        let mut staged = StagedSecrets::default();
+        if secrets.is_empty() {
+            return Ok(staged);
+        }
+        let dir = self.job_secrets_dir(job_id);
+        create_private_dir(&dir).await?;
+
+        let env = env_file(secrets);
+        if !env.is_empty() {
+            let path = dir.join("env");
+            write_private(&path, &env).await?;
+            staged.env_file = Some(path);
+        }
+
+        let files: Vec<&SecretValue> = secrets
+            .iter()
+            .filter(|s| matches!(s.inject_as, SecretInjection::File) || s.value.contains('\n'))
+            .collect();
+        if !files.is_empty() {
+            let files_dir = dir.join("files");
+            create_private_dir(&files_dir).await?;
+            for secret in files {
+                write_private(&files_dir.join(&secret.name), &secret.value).await?;
+            }
+            staged.files_dir = Some(files_dir);
+        }
+        Ok(staged)
+    }
+
+    /// Stops a run for good: the container goes and so do its secrets, without
+    /// waiting for the task following it to notice.
+    pub async fn cancel(&self, job_id: Uuid) {
+        self.remove_container(&container_name(job_id)).await;
+        self.remove_secrets(job_id).await;
+    }
+
+    async fn remove_secrets(&self, job_id: Uuid) {
+        let dir = self.job_secrets_dir(job_id);
+        if let Err(e) = tokio::fs::remove_dir_all(&dir).await
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            tracing::warn!(job_id = %job_id, error = %e, "failed to remove run secrets");
+        }
+    }
+
+    /// Rewrites input attachments to paths the container can open, copying them
+    /// into `{workspace}/inputs` when enabled and they fit the size limit.
+    async fn stage_attachments(
+        &self,
+        workspace: &str,
+        attachments: &[Attachment],
+    ) -> Vec<Attachment> {
+        let inputs_dir = format!("{workspace}/inputs");
+        if self.config.copy_attachments
+            && !attachments.is_empty()
+            && let Err(e) = tokio::fs::create_dir_all(&inputs_dir).await
+        {
+            tracing::warn!(error = %e, "failed to create inputs dir");
+        }
+
+        let max_bytes = self.config.max_attachment_mb * 1024 * 1024;
+        let mut taken = HashSet::new();
+        let mut staged = vec![];
+        for att in attachments {
+            let size = match tokio::fs::metadata(&att.path).await {
+                Ok(meta) => meta.len(),
+                Err(e) => {
+                    tracing::warn!(path = %att.path, error = %e, "input attachment not found on host");
+                    continue;
+                }
+            };
+
+            let mut container_path = media_container_path(&self.config.media_dir, &att.path);
+            if self.config.copy_attachments && size <= max_bytes {
+                let file_name = input_file_name(att, &mut taken);
+                match tokio::fs::copy(&att.path, format!("{inputs_dir}/{file_name}")).await {
+                    Ok(_) => container_path = format!("/workspace/inputs/{file_name}"),
+                    Err(e) => {
+                        tracing::warn!(path = %att.path, error = %e, "failed to copy input attachment");
+                    }
+                }
+            }
+
+            staged.push(Attachment {
+                path: container_path,
+                ..att.clone()
+            });
+        }
+        staged
+    }
+
     /// Copy output files from workspace to storage/media/ and return outbox-ready attachment entries.
     async fn collect_output_files(
         &self,
         workspace: &str,
+        project: Option<&Path>,
         container_attachments: &[serde_json::Value],
     ) -> Vec<serde_json::Value> {
         let mut result = vec![];
@@ -321,6 +901,9 @@ impl AgentExecutor {
             tracing::warn!(error = %e, "failed to create media dir");
             return result;
         }
+        let roots: Vec<&Path> = std::iter::once(Path::new(workspace))
+            .chain(project)
+            .collect();
 
         for att in container_attachments {
             let container_path = match att["path"].as_str() {
@@ -331,8 +914,13 @@ impl AgentExecutor {
             let mime = att["mime"].as_str().unwrap_or("application/octet-stream");
             let ftype = att["type"].as_str().unwrap_or("document");
 
-            // container path /workspace/foo.mp4 -> host path {workspace}/foo.mp4
-            let host_path = container_path.replacen("/workspace/", &format!("{workspace}/"), 1);
+            // container path /workspace/foo.mp4 -> host path {workspace}/foo.mp4,
+            // and /workspace/project/... into the mounted persistent workspace
+            let project_prefix = format!("/workspace/{PROJECT_MOUNT}/");
+            let host_path = match project.zip(container_path.strip_prefix(&project_prefix)) {
+                Some((project, rest)) => project.join(rest).display().to_string(),
+                None => container_path.replacen("/workspace/", &format!("{workspace}/"), 1),
+            };
 
             if !Path::new(&host_path).exists() {
                 tracing::warn!(path = %host_path, "output file not found on host");
@@ -342,7 +930,8 @@ impl AgentExecutor {
             let dest_name = format!("{}_{name}", uuid::Uuid::new_v4().as_simple());
             let dest_path = format!("{media_dir}/{dest_name}");
 
-            match tokio::fs::copy(&host_path, &dest_path).await {
+            // the container could have left a symlink to anywhere on the host
+            match copy_agent_file(&roots, Path::new(&host_path), Path::new(&dest_path)).await {
                 Ok(size) => {
                     tracing::info!(src = %host_path, dst = %dest_path, size, "copied output file to media");
                     result.push(serde_json::json!({
@@ -411,23 +1000,165 @@ async fn write_claude_credentials(auth_dir: &str) -> bool {
     false
 }
 
-async fn kill_container(name: &str) -> bool {
-    Command::new("docker")
-        .args(["kill", name])
-        .output()
-        .await
-        .map(|o| o.status.success())
-        .unwrap_or(false)
+/// Where a file under the host media dir shows up inside the container.
+fn media_container_path(media_dir: &str, host_path: &str) -> String {
+    let relative = Path::new(host_path)
+        .strip_prefix(media_dir)
+        .ok()
+        .map(Path::to_path_buf)
+        .or_else(|| Path::new(host_path).file_name().map(PathBuf::from))
+        .unwrap_or_default();
+    format!("/storage/media/{}", relative.display())
+}
+
+/// A bare, unique file name for an input copied into the workspace. Names come
+/// from the sender, so only their last component is kept.
+fn input_file_name(att: &Attachment, taken: &mut HashSet<String>) -> String {
+    let base = att
+        .name
+        .as_deref()
+        .and_then(|name| Path::new(name).file_name())
+        .or_else(|| Path::new(&att.path).file_name())
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "file".to_string());
+
+    let mut candidate = base.clone();
+    let mut n = 1;
+    while !taken.insert(candidate.clone()) {
+        candidate = format!("{n}_{base}");
+        n += 1;
+    }
+    candidate
+}
+
+pub fn container_name(job_id: Uuid) -> String {
+    format!("yui-job-{}", job_id.as_simple())
+}
+
+impl AgentExecutor {
+    /// Force-removes a container, killing it first if it is still running.
+    pub async fn remove_container(&self, name: &str) -> bool {
+        self.command()
+            .args(["rm", "--force", name])
+            .output()
+            .await
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+    }
+
+    pub async fn container_exists(&self, name: &str) -> anyhow::Result<bool> {
+        let output = self
+            .command()
+            .args(["inspect", "--format", "{{.State.Status}}", name])
+            .output()
+            .await?;
+        if output.status.success() {
+            return Ok(true);
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if is_missing_container_error(&stderr) {
+            return Ok(false);
+        }
+        anyhow::bail!(
+            "{} inspect failed: {}",
+            self.config.container_cli,
+            stderr.trim()
+        )
+    }
+
+    /// Blocks until the container stops and returns its exit code.
+    async fn container_exit_code(&self, name: &str) -> anyhow::Result<i64> {
+        let output = self.command().args(["wait", name]).output().await?;
+        if !output.status.success() {
+            anyhow::bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().parse()?)
+    }
+
+    async fn kill_container(&self, name: &str) -> bool {
+        self.command()
+            .args(["kill", name])
+            .output()
+            .await
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+    }
+}
+
+/// Docker says "No such container", Podman "no such container".
+fn is_missing_container_error(stderr: &str) -> bool {
+    let stderr = stderr.to_ascii_lowercase();
+    stderr.contains("no such object") || stderr.contains("no such container")
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::services::resource_profile;
+    use std::os::unix::fs::PermissionsExt;
 
     fn parse_container_frame(line: &str) -> Option<ContainerFrame> {
         serde_json::from_str(line).ok()
     }
 
+    #[test]
+    fn parses_step_frame_with_missing_fields() {
+        let frame: ContainerFrame =
+            serde_json::from_str(r#"{"type":"step","tool":"Bash","duration_ms":42}"#).unwrap();
+        let ContainerFrame::Step {
+            tool,
+            input,
+            output,
+            duration_ms,
+        } = frame
+        else {
+            panic!("expected step frame");
+        };
+        assert_eq!(tool.as_deref(), Some("Bash"));
+        assert!(input.is_none() && output.is_none());
+        assert_eq!(duration_ms, Some(42));
+    }
+
+    #[test]
+    fn input_file_names_are_bare_and_unique() {
+        let att = |name: Option<&str>, path: &str| Attachment {
+            kind: "document".to_string(),
+            path: path.to_string(),
+            mime: "application/pdf".to_string(),
+            name: name.map(str::to_string),
+        };
+        let mut taken = HashSet::new();
+
+        let traversal = att(Some("../../etc/report.pdf"), "storage/media/a.pdf");
+        let duplicate = att(Some("report.pdf"), "storage/media/b.pdf");
+        let unnamed = att(None, "storage/media/c.pdf");
+
+        assert_eq!(input_file_name(&traversal, &mut taken), "report.pdf");
+        assert_eq!(input_file_name(&duplicate, &mut taken), "1_report.pdf");
+        assert_eq!(input_file_name(&unnamed, &mut taken), "c.pdf");
+    }
+
+    #[test]
+    fn media_paths_map_into_the_container_mount() {
+        assert_eq!(
+            media_container_path("storage/media", "storage/media/abc_photo.jpg"),
+            "/storage/media/abc_photo.jpg"
+        );
+        assert_eq!(
+            media_container_path("storage/media", "/elsewhere/voice.ogg"),
+            "/storage/media/voice.ogg"
+        );
+    }
+
+    #[test]
+    fn parses_progress_frame() {
+        let frame: ContainerFrame =
+            serde_json::from_str(r#"{"type":"progress","message":"running tests now"}"#).unwrap();
+        assert!(
+            matches!(frame, ContainerFrame::Progress { message } if message == "running tests now")
+        );
+    }
+
     #[test]
     fn parses_session_frame() {
         let frame: ContainerFrame =
@@ -478,6 +1209,106 @@ mod tests {
         assert_eq!(config.start_timeout_secs, 60);
         assert_eq!(config.idle_timeout_secs, 300);
         assert_eq!(config.max_attachment_mb, 100);
+        assert!(config.copy_attachments);
+        assert!(!config.disk_quota);
+        assert_eq!(config.container_cli, "docker");
+        assert!(config.network.is_none());
+        assert_eq!(config.max_log_kb, 1024);
+    }
+
+    #[test]
+    fn log_sink_truncates_once_past_cap() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let sink = LogSink::new(tx, 10);
+        sink.send(LogStream::Stdout, "12345".to_string());
+        sink.send(LogStream::Stderr, "67890".to_string());
+        sink.send(LogStream::Stdout, "over".to_string());
+        sink.send(LogStream::Stdout, "dropped".to_string());
+        drop(sink);
+
+        let mut received = vec![];
+        while let Ok(ExecutorEvent::Log { stream, line }) = rx.try_recv() {
+            received.push((stream, line));
+        }
+        assert_eq!(received.len(), 3);
+        assert!(matches!(received[1].0, LogStream::Stderr));
+        assert!(received[2].1.starts_with("[log output truncated"));
+    }
+
+    #[test]
+    fn container_log_stream_defaults_to_stdout() {
+        assert!(matches!(parse_log_stream("stderr"), LogStream::Stderr));
+        assert!(matches!(parse_log_stream("stdout"), LogStream::Stdout));
+        assert!(matches!(parse_log_stream("progress"), LogStream::Stdout));
+    }
+
+    #[test]
+    fn recognizes_missing_container_errors() {
+        assert!(is_missing_container_error(
+            "Error: No such object: yui-job-0195f3a0b6c8"
+        ));
+        assert!(is_missing_container_error(
+            "Error response from daemon: No such container: yui-job-0195f3a0b6c8"
+        ));
+        assert!(is_missing_container_error(
+            "Error: no such container yui-job-0195f3a0b6c8"
+        ));
+        assert!(!is_missing_container_error(
+            "Cannot connect to the Docker daemon at unix:///var/run/docker.sock"
+        ));
+    }
+
+    #[test]
+    fn configured_run_args_follow_config() {
+        let executor = AgentExecutor::new(ExecutionConfig {
+            network: Some("none".to_string()),
+            user: Some("1000:1000".to_string()),
+            security_opts: vec!["no-new-privileges".to_string()],
+            extra_mounts: vec!["/srv/cache:/cache:ro".to_string()],
+            env_passthrough: vec!["YUI_TEST_UNSET_PASSTHROUGH_VAR".to_string()],
+            extra_run_args: vec!["--pids-limit=256".to_string()],
+            ..ExecutionConfig::default()
+        });
+
+        assert_eq!(
+            executor.configured_run_args(None),
+            vec![
+                "--network=none",
+                "--user=1000:1000",
+                "--security-opt=no-new-privileges",
+                "-v",
+                "/srv/cache:/cache:ro",
+                "--pids-limit=256",
+            ]
+        );
+        assert_eq!(
+            executor.configured_run_args(Some("yui-egress"))[0],
+            "--network=yui-egress"
+        );
+    }
+
+    #[test]
+    fn podman_volumes_are_relabelled() {
+        let docker = AgentExecutor::new(ExecutionConfig::default());
+        let podman = AgentExecutor::new(ExecutionConfig {
+            container_cli: "podman".to_string(),
+            podman: true,
+            ..ExecutionConfig::default()
+        });
+        let host = Path::new("/srv/media");
+
+        assert_eq!(
+            docker.volume(host, "/storage/media", true),
+            "/srv/media:/storage/media:ro"
+        );
+        assert_eq!(
+            docker.volume(host, "/workspace", false),
+            "/srv/media:/workspace"
+        );
+        assert_eq!(
+            podman.volume(host, "/storage/media", true),
+            "/srv/media:/storage/media:ro,z"
+        );
     }
 
     #[test]
@@ -486,4 +1317,75 @@ mod tests {
         let resolved = AgentExecutor::canonical_or(missing);
         assert_eq!(resolved, std::path::PathBuf::from(missing));
     }
+
+    #[tokio::test]
+    async fn stages_env_and_file_secrets_per_job() {
+        let dir = std::env::temp_dir().join(format!("yui-secrets-{}", Uuid::new_v4()));
+        let executor = AgentExecutor::new(ExecutionConfig {
+            secrets_dir: dir.display().to_string(),
+            ..ExecutionConfig::default()
+        });
+        let job_id = Uuid::new_v4();
+        let secret = |name: &str, value: &str, inject_as| SecretValue {
+            name: name.to_string(),
+            value: value.to_string(),
+            inject_as,
+        };
+        let secrets = [
+            secret("GITHUB_TOKEN", "ghp_abcdef", SecretInjection::Env),
+            secret(
+                "DEPLOY_KEY",
+                "-----BEGIN KEY-----\nabc",
+                SecretInjection::Env,
+            ),
+            secret("KUBECONFIG", "apiVersion: v1", SecretInjection::File),
+        ];
+
+        let staged = executor.stage_secrets(job_id, &secrets).await.unwrap();
+        let env = tokio::fs::read_to_string(staged.env_file.unwrap())
+            .await
+            .unwrap();
+        let files = staged.files_dir.unwrap();
+
+        assert_eq!(env, "GITHUB_TOKEN=ghp_abcdef\n");
+        let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&dir.join(job_id.to_string())), 0o700);
+        assert_eq!(mode(&files), 0o700);
+        assert_eq!(mode(&files.join("KUBECONFIG")), 0o600);
+        assert!(files.join("DEPLOY_KEY").exists() && files.join("KUBECONFIG").exists());
+        assert!(!files.join("GITHUB_TOKEN").exists());
+
+        executor.remove_secrets(job_id).await;
+        assert!(!dir.join(job_id.to_string()).exists());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn restricted_runs_are_refused_without_an_egress_network() {
+        let executor = AgentExecutor::new(ExecutionConfig::default());
+        let (events, _) = tokio::sync::mpsc::unbounded_channel();
+        let input = ExecutionInput {
+            job_id: Uuid::new_v4(),
+            trace_id: Uuid::new_v4(),
+            prompt: "check the feed".to_string(),
+            attachments: vec![],
+            limits: resource_profile(None).1,
+            session_id: None,
+            resume_input: None,
+            agent_token: None,
+            secrets: vec![],
+            project_dir: None,
+            egress: EgressRules {
+                policy: crate::schema::EgressPolicy::None,
+                allow: vec!["api.anthropic.com".to_string()],
+            },
+        };
+
+        let outcome = executor.execute(input, events).await;
+        assert!(matches!(
+            outcome,
+            ExecutionOutcome::Failed { ref error, retryable: false }
+                if error.contains("YUI_EGRESS_NETWORK")
+        ));
+    }
 }
@@ -1,6 +1,11 @@
+use crate::schema::Attachment;
+use crate::services::agent_tools::{AgentTools, AgentToolsConfig, ToolOutcome};
+use crate::services::{EgressRules, ResourceLimits, SecretValue, metrics};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Mutex;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
 use uuid::Uuid;
 
 const OPENROUTER_URL: &str = "https://openrouter.ai/api/v1/chat/completions";
@@ -10,18 +15,91 @@ const RUNNER_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
 pub struct RunnerStartInput {
     pub job_id: Uuid,
     pub prompt: String,
+    /// Agent session from an earlier run of the same job, for backends that can resume one.
+    pub session_id: Option<String>,
+    /// The user's answer when the job is resuming after `AskUser`.
+    pub resume_input: Option<String>,
+    /// Files from the job's source messages, as stored on `messages.attachments`.
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    pub limits: ResourceLimits,
+    /// Bearer token for the agent callback API, valid while this run lasts.
+    #[serde(default)]
+    pub agent_token: Option<String>,
+    /// Vault entries this job is allowed to use, already decrypted.
+    #[serde(default)]
+    pub secrets: Vec<SecretValue>,
+    /// Host directory of the job's persistent workspace, linked into its
+    /// workspace as `project`.
+    #[serde(default)]
+    pub project_dir: Option<PathBuf>,
+    /// Network access the run is held to.
+    #[serde(default)]
+    pub egress: EgressRules,
+}
+
+impl RunnerStartInput {
+    /// The prompt with the user's answer appended, for backends that start over on resume.
+    pub fn prompt_with_resume(&self) -> String {
+        match self.resume_input {
+            Some(ref input) => format!("{}\n\nUser response: {input}", self.prompt),
+            None => self.prompt.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct RunnerHandle {
     pub run_id: Uuid,
     pub job_id: Uuid,
+    /// Backend-side identity of the run (e.g. the container name), persisted on
+    /// the job so a restarted runtime can find it again.
+    pub container_id: Option<String>,
+}
+
+/// Longest input/output summary kept per agent step.
+const STEP_SUMMARY_CHARS: usize = 500;
+
+/// One tool call or LLM turn, stored as a row in `agent_steps`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StepReport {
+    /// `None` for a plain model turn without a tool call.
+    pub tool_name: Option<String>,
+    pub input_summary: Option<String>,
+    pub output_summary: Option<String>,
+    pub duration_ms: Option<i32>,
+}
+
+/// Cuts `text` to the step summary limit on a char boundary.
+pub fn summarize_step(text: &str) -> String {
+    match text.char_indices().nth(STEP_SUMMARY_CHARS) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// How much of a run's output the runtime has stored, counted from the start
+/// of the run: stdout frames and stderr lines.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FrameOffset {
+    pub stdout: u64,
+    pub stderr: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum RunnerEvent {
     Stdout(String),
     Stderr(String),
+    /// Everything before this event is covered by the offset; stored after
+    /// those events so a reattach never skips output that was lost.
+    Seen(FrameOffset),
+    Session {
+        session_id: String,
+    },
+    Step(StepReport),
+    Progress {
+        message: String,
+    },
     AskUser {
         question: String,
     },
@@ -32,14 +110,32 @@ pub enum RunnerEvent {
     },
     Failed {
         error: String,
+        #[serde(default)]
+        retryable: bool,
     },
 }
 
 #[async_trait::async_trait]
 pub trait AgentRunnerService: Send + Sync {
+    /// Stored in `jobs.runner_backend`; only runs started by the same backend are reattached.
+    fn backend(&self) -> &'static str;
     async fn start(&self, input: RunnerStartInput) -> anyhow::Result<RunnerHandle>;
     async fn poll(&self, handle: &RunnerHandle) -> anyhow::Result<Vec<RunnerEvent>>;
     async fn cancel(&self, handle: &RunnerHandle) -> anyhow::Result<()>;
+
+    /// Resumes tracking a run started by a previous runtime process. Returns
+    /// `false` when the run no longer exists and the job has to be re-queued.
+    /// The run's output is replayed from the start, and `seen` is how much of
+    /// it the previous process already stored. `project_dir` is the job's
+    /// persistent workspace, as it was passed to `start`.
+    async fn reattach(
+        &self,
+        _handle: &RunnerHandle,
+        _seen: FrameOffset,
+        _project_dir: Option<PathBuf>,
+    ) -> anyhow::Result<bool> {
+        Ok(false)
+    }
 }
 
 pub struct OpenRouterAgentRunner {
@@ -47,10 +143,19 @@ pub struct OpenRouterAgentRunner {
     model: String,
     provider_only: Option<String>,
     client: reqwest::Client,
+    tools: AgentToolsConfig,
+    /// Total tokens one run may spend across all of its model calls.
+    max_tokens_per_run: u64,
 }
 
 impl OpenRouterAgentRunner {
-    pub fn new(api_key: String, model: String, provider_only: Option<String>) -> Self {
+    pub fn new(
+        api_key: String,
+        model: String,
+        provider_only: Option<String>,
+        tools: AgentToolsConfig,
+        max_tokens_per_run: u64,
+    ) -> Self {
         let client = reqwest::Client::builder()
             .timeout(RUNNER_TIMEOUT)
             .build()
@@ -60,6 +165,8 @@ impl OpenRouterAgentRunner {
             model,
             provider_only,
             client,
+            tools,
+            max_tokens_per_run,
         }
     }
 
@@ -72,188 +179,538 @@ impl OpenRouterAgentRunner {
         let provider_only = std::env::var("OPENROUTER_PROVIDER_ONLY")
             .ok()
             .or_else(|| Some("fireworks".to_string()));
-        Self::new(api_key, model, provider_only)
+        let max_tokens_per_run = std::env::var("YUI_AGENT_MAX_TOKENS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(200_000);
+        Self::new(
+            api_key,
+            model,
+            provider_only,
+            AgentToolsConfig::from_env(),
+            max_tokens_per_run,
+        )
     }
 }
 
-#[derive(Clone)]
-enum ORRun {
-    Pending(String),
-    Running,
-    Done(ORResult),
+struct ORRun {
+    events: UnboundedReceiver<RunnerEvent>,
+    task: tokio::task::JoinHandle<()>,
 }
 
-#[derive(Clone)]
-enum ORResult {
-    Completed(String),
-    AskUser(String),
-    Failed(String),
-}
+/// A single model call that takes longer than this tells the user it is still going.
+const SLOW_RESPONSE_PROGRESS: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Completion tokens allowed per model call.
+const MAX_TOKENS_PER_CALL: u32 = 4096;
 
 static OR_RUNS: std::sync::LazyLock<Mutex<HashMap<Uuid, ORRun>>> =
     std::sync::LazyLock::new(|| Mutex::new(HashMap::new()));
 
-const RUNNER_SYSTEM_PROMPT: &str = r#"You are Yui's task execution engine. You receive enriched prompts and produce results.
-
-Respond with a JSON object in one of these formats:
-1. Task completed: {"status":"completed","output":"your detailed response here"}
-2. Need user input: {"status":"ask_user","question":"your specific question here"}
+const RUNNER_SYSTEM_PROMPT: &str = r#"You are Yui's task execution engine. You receive enriched prompts and carry them out with the tools you are given.
 
 Rules:
-- ONLY output valid JSON, nothing else
-- If the task explicitly asks you to ask a clarification question, use "ask_user" status FIRST
-- After receiving user input (shown as "User response: ..."), complete the task with "completed" status
+- Use tools to get real data instead of guessing. If no tool can get fresh data (weather, prices, current events), say clearly that your answer may be outdated
+- Work in small steps. When you call tools, briefly say what you are doing next in plain text alongside the calls
+- Only call ask_user when the task cannot continue without the user, or when the task explicitly asks you to ask a clarification question first
+- After receiving user input (shown as "User response: ..."), complete the task
+- When you are done, reply with the final answer as plain text and no tool calls
 - Include any tokens, identifiers, or exact strings mentioned in the task verbatim in your output
 - Be thorough but concise
-- For real-time data queries (weather, stock prices, ISS location, current time): provide the best answer you can. If your knowledge is outdated, say so clearly.
 - The "Relevant history" section contains previous conversation messages. Use them for context, recall questions, and to understand what the user previously discussed.
-- Do NOT use markdown formatting in your output. Plain text only, suitable for WhatsApp messages.
-- Never mention file paths, container internals, or system details in your output."#;
-
-async fn call_openrouter(
-    client: &reqwest::Client,
-    api_key: &str,
-    model: &str,
-    provider_only: Option<&str>,
-    prompt: &str,
-) -> ORResult {
-    let mut body = serde_json::json!({
-        "model": model,
-        "messages": [
-            {"role": "system", "content": RUNNER_SYSTEM_PROMPT},
-            {"role": "user", "content": prompt}
-        ],
-        "temperature": 0.3,
-        "max_tokens": 2048,
-        "response_format": {"type": "json_object"}
-    });
-    if let Some(provider) = provider_only {
-        body["provider"] = serde_json::json!({
-            "only": [provider]
+- Do NOT use markdown formatting in your final answer. Plain text only, suitable for WhatsApp messages.
+- Never mention file paths, workspaces, tools, or system details in your final answer. Use send_file to share a file."#;
+
+/// One assistant message, kept verbatim so tool calls can be replayed to the model.
+struct ModelTurn {
+    message: serde_json::Value,
+    total_tokens: u64,
+}
+
+#[derive(Clone)]
+struct ModelClient {
+    client: reqwest::Client,
+    /// The chat completions endpoint, OpenRouter's outside of tests.
+    url: String,
+    api_key: String,
+    model: String,
+    provider_only: Option<String>,
+}
+
+impl ModelClient {
+    /// Error message and whether the request is worth sending again.
+    async fn call(
+        &self,
+        messages: &[serde_json::Value],
+        tools: &[serde_json::Value],
+    ) -> Result<ModelTurn, (String, bool)> {
+        let mut body = serde_json::json!({
+            "model": self.model,
+            "messages": messages,
+            "temperature": 0.3,
+            "max_tokens": MAX_TOKENS_PER_CALL,
         });
+        if !tools.is_empty() {
+            body["tools"] = serde_json::json!(tools);
+        }
+        if let Some(ref provider) = self.provider_only {
+            body["provider"] = serde_json::json!({
+                "only": [provider]
+            });
+        }
+
+        let response = self
+            .client
+            .post(&self.url)
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Content-Type", "application/json")
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| (format!("HTTP error: {e}"), true))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            let retryable =
+                status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS;
+            return Err((format!("OpenRouter {status}: {body}"), retryable));
+        }
+
+        let mut chat_resp: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| (format!("response parse error: {e}"), false))?;
+        let message = chat_resp["choices"][0]["message"].take();
+        if !message.is_object() {
+            return Err(("no message in LLM response".to_string(), false));
+        }
+        Ok(ModelTurn {
+            message,
+            total_tokens: chat_resp["usage"]["total_tokens"].as_u64().unwrap_or(0),
+        })
     }
+}
 
-    let response = match client
-        .post(OPENROUTER_URL)
-        .header("Authorization", format!("Bearer {api_key}"))
-        .header("Content-Type", "application/json")
-        .json(&body)
-        .send()
-        .await
-    {
-        Ok(r) => r,
-        Err(e) => return ORResult::Failed(format!("HTTP error: {e}")),
-    };
-
-    let status = response.status();
-    if !status.is_success() {
-        let body = response.text().await.unwrap_or_default();
-        return ORResult::Failed(format!("OpenRouter {status}: {body}"));
+/// Calls the model and tool registry in turns until the model answers without
+/// tool calls, a tool asks the user something, or a turn or token limit is hit.
+/// Steps and progress are sent as they happen; the terminal event is returned.
+async fn run_agent_loop(
+    model: ModelClient,
+    mut tools: AgentTools,
+    prompt: String,
+    max_turns: i32,
+    max_tokens: u64,
+    tx: &UnboundedSender<RunnerEvent>,
+) -> RunnerEvent {
+    if let Err(e) = tools.prepare().await {
+        return RunnerEvent::Failed {
+            error: format!("failed to create workspace: {e}"),
+            retryable: false,
+        };
     }
 
-    let chat_resp: serde_json::Value = match response.json().await {
-        Ok(v) => v,
-        Err(e) => return ORResult::Failed(format!("response parse error: {e}")),
-    };
-
-    let content = match chat_resp["choices"][0]["message"]["content"].as_str() {
-        Some(c) => c,
-        None => return ORResult::Failed("no content in LLM response".to_string()),
-    };
-
-    if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(content) {
-        match parsed["status"].as_str() {
-            Some("ask_user") => {
-                let question = parsed["question"]
-                    .as_str()
-                    .unwrap_or("clarification needed");
-                return ORResult::AskUser(question.to_string());
+    let definitions = tools.definitions();
+    let mut messages = vec![
+        serde_json::json!({"role": "system", "content": RUNNER_SYSTEM_PROMPT}),
+        serde_json::json!({"role": "user", "content": prompt}),
+    ];
+    let mut tokens_used = 0;
+    let mut slow_notified = false;
+
+    for turn in 1..=max_turns.max(1) {
+        let started = std::time::Instant::now();
+        let result = {
+            let call = model.call(&messages, &definitions);
+            tokio::pin!(call);
+            tokio::select! {
+                result = &mut call => result,
+                _ = tokio::time::sleep(SLOW_RESPONSE_PROGRESS), if !slow_notified => {
+                    slow_notified = true;
+                    let _ = tx.send(RunnerEvent::Progress {
+                        message: "still working on it, waiting for the model to finish"
+                            .to_string(),
+                    });
+                    call.await
+                }
             }
-            Some("completed") => {
-                let output = parsed["output"].as_str().unwrap_or(content);
-                return ORResult::Completed(output.to_string());
+        };
+        metrics::record_llm_call("agent", result.is_ok());
+        let reply = match result {
+            Ok(reply) => reply,
+            Err((error, retryable)) => return RunnerEvent::Failed { error, retryable },
+        };
+        tokens_used += reply.total_tokens;
+
+        let content = reply.message["content"]
+            .as_str()
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+        let tool_calls = reply.message["tool_calls"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        let called: Vec<&str> = tool_calls
+            .iter()
+            .filter_map(|call| call["function"]["name"].as_str())
+            .collect();
+        let turn_output = if called.is_empty() {
+            content.clone()
+        } else {
+            format!("calls {}", called.join(", "))
+        };
+        let _ = tx.send(RunnerEvent::Step(StepReport {
+            tool_name: None,
+            input_summary: (turn == 1).then(|| summarize_step(&prompt)),
+            output_summary: Some(summarize_step(&turn_output)),
+            duration_ms: i32::try_from(started.elapsed().as_millis()).ok(),
+        }));
+
+        if tool_calls.is_empty() {
+            let output = if content.is_empty() {
+                "task completed (no output)".to_string()
+            } else {
+                content
+            };
+            return RunnerEvent::Completed {
+                output,
+                attachments: tools.into_sent_files(),
+            };
+        }
+        if !content.is_empty() {
+            let _ = tx.send(RunnerEvent::Progress { message: content });
+        }
+
+        messages.push(reply.message);
+        for call in &tool_calls {
+            let name = call["function"]["name"].as_str().unwrap_or_default();
+            let arguments = call["function"]["arguments"].as_str().unwrap_or("{}");
+            let started = std::time::Instant::now();
+            let output = match tools.call(name, arguments).await {
+                ToolOutcome::Output(output) => output,
+                ToolOutcome::AskUser(question) => return RunnerEvent::AskUser { question },
+            };
+            for line in tools.take_blocked() {
+                let _ = tx.send(RunnerEvent::Stderr(line));
             }
-            _ => {}
+            let _ = tx.send(RunnerEvent::Step(StepReport {
+                tool_name: Some(name.to_string()),
+                input_summary: Some(summarize_step(arguments)),
+                output_summary: Some(summarize_step(&output)),
+                duration_ms: i32::try_from(started.elapsed().as_millis()).ok(),
+            }));
+            messages.push(serde_json::json!({
+                "role": "tool",
+                "tool_call_id": call["id"],
+                "content": output,
+            }));
+        }
+
+        if tokens_used >= max_tokens {
+            return RunnerEvent::Failed {
+                error: format!("token budget of {max_tokens} used up after {turn} turns"),
+                retryable: false,
+            };
         }
     }
 
-    // fallback: treat raw content as output
-    ORResult::Completed(content.to_string())
+    RunnerEvent::Failed {
+        error: format!("turn limit of {max_turns} reached without an answer"),
+        retryable: false,
+    }
 }
 
 #[async_trait::async_trait]
 impl AgentRunnerService for OpenRouterAgentRunner {
+    fn backend(&self) -> &'static str {
+        "openrouter"
+    }
+
     async fn start(&self, input: RunnerStartInput) -> anyhow::Result<RunnerHandle> {
         let handle = RunnerHandle {
             run_id: Uuid::new_v4(),
             job_id: input.job_id,
+            container_id: None,
+        };
+
+        let model = ModelClient {
+            client: self.client.clone(),
+            url: OPENROUTER_URL.to_string(),
+            api_key: self.api_key.clone(),
+            model: self.model.clone(),
+            provider_only: self.provider_only.clone(),
         };
+        let tools = AgentTools::new(self.tools.clone(), input.job_id, input.project_dir.clone())
+            .with_egress(input.egress.clone(), input.agent_token.clone());
+        let prompt = input.prompt_with_resume();
+        let max_turns = input.limits.max_turns;
+        let max_tokens = self.max_tokens_per_run;
+
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let task = tokio::spawn(async move {
+            let outcome = run_agent_loop(model, tools, prompt, max_turns, max_tokens, &tx).await;
+            let _ = tx.send(outcome);
+        });
         OR_RUNS
             .lock()
             .unwrap()
-            .insert(handle.run_id, ORRun::Pending(input.prompt));
+            .insert(handle.run_id, ORRun { events: rx, task });
         Ok(handle)
     }
 
     async fn poll(&self, handle: &RunnerHandle) -> anyhow::Result<Vec<RunnerEvent>> {
-        let state = {
-            let runs = OR_RUNS.lock().unwrap();
-            runs.get(&handle.run_id).cloned()
+        let mut runs = OR_RUNS.lock().unwrap();
+        let Some(run) = runs.get_mut(&handle.run_id) else {
+            return Ok(vec![]);
         };
 
-        match state {
-            Some(ORRun::Pending(prompt)) => {
-                {
-                    let mut runs = OR_RUNS.lock().unwrap();
-                    runs.insert(handle.run_id, ORRun::Running);
-                }
-
-                let run_id = handle.run_id;
-                let client = self.client.clone();
-                let api_key = self.api_key.clone();
-                let model = self.model.clone();
-                let provider_only = self.provider_only.clone();
-
-                tokio::spawn(async move {
-                    let result = call_openrouter(
-                        &client,
-                        &api_key,
-                        &model,
-                        provider_only.as_deref(),
-                        &prompt,
-                    )
-                    .await;
-                    let mut runs = OR_RUNS.lock().unwrap();
-                    runs.insert(run_id, ORRun::Done(result));
-                });
-
-                Ok(vec![RunnerEvent::Stdout(
-                    "sending prompt to LLM...".to_string(),
-                )])
-            }
-            Some(ORRun::Running) => Ok(vec![]),
-            Some(ORRun::Done(result)) => {
-                let mut runs = OR_RUNS.lock().unwrap();
+        let mut events = vec![];
+        while let Ok(event) = run.events.try_recv() {
+            let terminal = matches!(
+                event,
+                RunnerEvent::Completed { .. }
+                    | RunnerEvent::AskUser { .. }
+                    | RunnerEvent::Failed { .. }
+            );
+            events.push(event);
+            if terminal {
                 runs.remove(&handle.run_id);
-                drop(runs);
-
-                match result {
-                    ORResult::Completed(output) => Ok(vec![RunnerEvent::Completed {
-                        output,
-                        attachments: vec![],
-                    }]),
-                    ORResult::AskUser(question) => Ok(vec![RunnerEvent::AskUser { question }]),
-                    ORResult::Failed(error) => Ok(vec![RunnerEvent::Failed { error }]),
-                }
+                break;
             }
-            None => Ok(vec![]),
         }
+        Ok(events)
     }
 
     async fn cancel(&self, handle: &RunnerHandle) -> anyhow::Result<()> {
-        let mut runs = OR_RUNS.lock().unwrap();
-        runs.remove(&handle.run_id);
+        if let Some(run) = OR_RUNS.lock().unwrap().remove(&handle.run_id) {
+            run.task.abort();
+        }
         Ok(())
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::sync::Arc;
+
+    /// Stands in for OpenRouter: every call gets the next scripted reply, and
+    /// the request bodies are kept so the messages sent can be checked.
+    #[derive(Default)]
+    struct FakeModel {
+        replies: Mutex<VecDeque<(u16, serde_json::Value)>>,
+        requests: Mutex<Vec<serde_json::Value>>,
+    }
+
+    async fn fake_model(replies: Vec<(u16, serde_json::Value)>) -> (ModelClient, Arc<FakeModel>) {
+        let fake = Arc::new(FakeModel {
+            replies: Mutex::new(replies.into()),
+            ..FakeModel::default()
+        });
+        let state = fake.clone();
+        let app =
+            axum::Router::new().fallback(move |axum::Json(body): axum::Json<serde_json::Value>| {
+                let state = state.clone();
+                async move {
+                    state.requests.lock().unwrap().push(body);
+                    let (status, reply) = state
+                        .replies
+                        .lock()
+                        .unwrap()
+                        .pop_front()
+                        .expect("no reply scripted for this call");
+                    (
+                        axum::http::StatusCode::from_u16(status).unwrap(),
+                        axum::Json(reply),
+                    )
+                }
+            });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/chat/completions", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let model = ModelClient {
+            client: reqwest::Client::new(),
+            url,
+            api_key: "test".to_string(),
+            model: "test-model".to_string(),
+            provider_only: None,
+        };
+        (model, fake)
+    }
+
+    fn answer(text: &str) -> (u16, serde_json::Value) {
+        let message = serde_json::json!({"role": "assistant", "content": text});
+        (
+            200,
+            serde_json::json!({"choices": [{"message": message}], "usage": {"total_tokens": 10}}),
+        )
+    }
+
+    fn tool_call(
+        id: &str,
+        name: &str,
+        arguments: serde_json::Value,
+        tokens: u64,
+    ) -> (u16, serde_json::Value) {
+        let message = serde_json::json!({
+            "role": "assistant",
+            "content": "",
+            "tool_calls": [{
+                "id": id,
+                "type": "function",
+                "function": {"name": name, "arguments": arguments.to_string()},
+            }],
+        });
+        (
+            200,
+            serde_json::json!({"choices": [{"message": message}], "usage": {"total_tokens": tokens}}),
+        )
+    }
+
+    /// Runs the loop against `model` in a fresh workspace, returning the
+    /// terminal event and what was sent along the way.
+    async fn run(
+        model: ModelClient,
+        max_turns: i32,
+        max_tokens: u64,
+    ) -> (RunnerEvent, Vec<RunnerEvent>, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("yui-agent-loop-{}", Uuid::new_v4()));
+        let config = AgentToolsConfig {
+            workspace_dir: dir.display().to_string(),
+            media_dir: dir.join("media").display().to_string(),
+            http_allowlist: vec![],
+            shell_image: None,
+            container_cli: "docker".to_string(),
+            shell_timeout_secs: 5,
+        };
+        let job_id = Uuid::new_v4();
+        let tools = AgentTools::new(config, job_id, None);
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let outcome = run_agent_loop(
+            model,
+            tools,
+            "write the notes".to_string(),
+            max_turns,
+            max_tokens,
+            &tx,
+        )
+        .await;
+        let mut events = vec![];
+        while let Ok(event) = rx.try_recv() {
+            events.push(event);
+        }
+        (outcome, events, dir.join(job_id.to_string()))
+    }
+
+    #[tokio::test]
+    async fn tool_results_are_threaded_back_to_the_model() {
+        let (model, fake) = fake_model(vec![
+            tool_call(
+                "call_1",
+                "write_file",
+                serde_json::json!({"path": "notes.txt", "content": "hello"}),
+                10,
+            ),
+            answer("the notes are written"),
+        ])
+        .await;
+
+        let (outcome, events, workspace) = run(model, 5, 1_000).await;
+
+        assert!(matches!(
+            outcome,
+            RunnerEvent::Completed { ref output, .. } if output == "the notes are written"
+        ));
+        assert_eq!(
+            std::fs::read_to_string(workspace.join("notes.txt")).unwrap(),
+            "hello"
+        );
+        let requests = fake.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        let messages = requests[1]["messages"].as_array().unwrap();
+        let result = messages.last().unwrap();
+        assert_eq!(result["role"], "tool");
+        assert_eq!(result["tool_call_id"], "call_1");
+        assert_eq!(result["content"], "wrote 5 bytes to notes.txt");
+        assert_eq!(
+            messages[messages.len() - 2]["tool_calls"][0]["id"],
+            "call_1"
+        );
+        assert!(events.iter().any(|event| matches!(
+            event,
+            RunnerEvent::Step(StepReport { tool_name: Some(name), .. }) if name == "write_file"
+        )));
+        let _ = std::fs::remove_dir_all(workspace.parent().unwrap());
+    }
+
+    #[tokio::test]
+    async fn ask_user_pauses_the_run() {
+        let (model, _fake) = fake_model(vec![tool_call(
+            "call_1",
+            "ask_user",
+            serde_json::json!({"question": "which folder?"}),
+            10,
+        )])
+        .await;
+
+        let (outcome, _, workspace) = run(model, 5, 1_000).await;
+
+        assert!(matches!(
+            outcome,
+            RunnerEvent::AskUser { ref question } if question == "which folder?"
+        ));
+        let _ = std::fs::remove_dir_all(workspace.parent().unwrap());
+    }
+
+    #[tokio::test]
+    async fn runs_stop_at_the_turn_limit_and_the_token_budget() {
+        let read = || tool_call("call", "read_file", serde_json::json!({"path": "x"}), 10);
+        let (model, fake) = fake_model(vec![read(), read(), read()]).await;
+        let (outcome, _, workspace) = run(model, 2, 1_000).await;
+        assert!(matches!(
+            outcome,
+            RunnerEvent::Failed { ref error, retryable: false }
+                if error == "turn limit of 2 reached without an answer"
+        ));
+        assert_eq!(fake.requests.lock().unwrap().len(), 2);
+        let _ = std::fs::remove_dir_all(workspace.parent().unwrap());
+
+        let costly = || tool_call("call", "read_file", serde_json::json!({"path": "x"}), 600);
+        let (model, fake) = fake_model(vec![costly(), costly(), costly()]).await;
+        let (outcome, _, workspace) = run(model, 10, 1_000).await;
+        assert!(matches!(
+            outcome,
+            RunnerEvent::Failed { ref error, retryable: false }
+                if error == "token budget of 1000 used up after 2 turns"
+        ));
+        assert_eq!(fake.requests.lock().unwrap().len(), 2);
+        let _ = std::fs::remove_dir_all(workspace.parent().unwrap());
+    }
+
+    #[tokio::test]
+    async fn only_server_side_model_failures_are_retryable() {
+        for (status, retryable) in [(503, true), (429, true), (400, false)] {
+            let (model, _fake) =
+                fake_model(vec![(status, serde_json::json!({"error": "nope"}))]).await;
+            let (outcome, _, workspace) = run(model, 5, 1_000).await;
+            assert!(
+                matches!(outcome, RunnerEvent::Failed { retryable: r, .. } if r == retryable),
+                "{status}"
+            );
+            let _ = std::fs::remove_dir_all(workspace.parent().unwrap());
+        }
+    }
+
+    #[test]
+    fn step_summary_is_cut_on_a_char_boundary() {
+        let short = "ran ls";
+        assert_eq!(summarize_step(short), short);
+
+        let long = "é".repeat(STEP_SUMMARY_CHARS + 10);
+        let summary = summarize_step(&long);
+        assert_eq!(summary.chars().count(), STEP_SUMMARY_CHARS + 3);
+        assert!(summary.ends_with("..."));
+    }
+}
@@ -0,0 +1,718 @@
+use crate::services::egress::{EgressRules, egress_config};
+use crate::services::workspace::{
+    PROJECT_MOUNT, copy_agent_file, create_confined, open_confined, prepare_project,
+};
+use std::ffi::OsStr;
+use std::path::{Component, Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::process::Command;
+use uuid::Uuid;
+
+/// Longest tool output handed back to the model; the rest is cut with a note.
+const MAX_TOOL_OUTPUT_CHARS: usize = 20_000;
+const HTTP_FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+/// Most of a response body `http_fetch` reads. The model only sees the first
+/// `MAX_TOOL_OUTPUT_CHARS` of it, so the rest is not downloaded at all.
+const MAX_FETCH_BYTES: usize = 256 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct AgentToolsConfig {
+    pub workspace_dir: String,
+    pub media_dir: String,
+    /// Hosts `http_fetch` may reach. `*.example.com` matches subdomains and a
+    /// lone `*` allows everything.
+    pub http_allowlist: Vec<String>,
+    /// Image `shell` runs each command in, as a throwaway container with the
+    /// workspace mounted. There is no host fallback, so without one the tool
+    /// is not offered.
+    pub shell_image: Option<String>,
+    pub container_cli: String,
+    pub shell_timeout_secs: u64,
+}
+
+impl AgentToolsConfig {
+    pub fn from_env() -> Self {
+        Self {
+            workspace_dir: std::env::var("YUI_WORKSPACE_DIR")
+                .unwrap_or_else(|_| "storage/workspaces".to_string()),
+            media_dir: std::env::var("YUI_MEDIA_DIR")
+                .unwrap_or_else(|_| "storage/media".to_string()),
+            http_allowlist: std::env::var("YUI_AGENT_HTTP_ALLOWLIST")
+                .map(|v| {
+                    v.split(',')
+                        .map(str::trim)
+                        .filter(|h| !h.is_empty())
+                        .map(str::to_ascii_lowercase)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            shell_image: shell_image(),
+            container_cli: std::env::var("YUI_CONTAINER_CLI")
+                .unwrap_or_else(|_| "docker".to_string()),
+            shell_timeout_secs: std::env::var("YUI_AGENT_SHELL_TIMEOUT_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(60),
+        }
+    }
+}
+
+/// The image for `shell` when it is turned on. Asking for it without one is
+/// a configuration error rather than a reason to run commands on the host.
+fn shell_image() -> Option<String> {
+    let enabled = std::env::var("YUI_AGENT_SHELL")
+        .map(|v| v == "1" || v == "true")
+        .unwrap_or(false);
+    if !enabled {
+        return None;
+    }
+    let image = std::env::var("YUI_AGENT_SHELL_IMAGE")
+        .ok()
+        .filter(|image| !image.is_empty());
+    if image.is_none() {
+        tracing::error!("YUI_AGENT_SHELL is set without YUI_AGENT_SHELL_IMAGE, shell stays off");
+    }
+    image
+}
+
+/// What a tool call hands back to the agent loop.
+#[derive(Debug, Clone)]
+pub enum ToolOutcome {
+    /// Fed back to the model as the tool result.
+    Output(String),
+    /// Ends the run and pauses the job until the user answers.
+    AskUser(String),
+}
+
+/// The tools one agent run may call, bound to that run's workspace.
+pub struct AgentTools {
+    config: AgentToolsConfig,
+    client: reqwest::Client,
+    workspace: PathBuf,
+    /// Persistent workspace reached through `project/`, if the job has one.
+    project_dir: Option<PathBuf>,
+    /// Files queued by `send_file`, delivered with the final answer.
+    sent_files: Vec<serde_json::Value>,
+    egress: EgressRules,
+    agent_token: Option<String>,
+    /// Requests the egress policy refused since the last `take_blocked`.
+    blocked: Vec<String>,
+}
+
+impl AgentTools {
+    pub fn new(config: AgentToolsConfig, job_id: Uuid, project_dir: Option<PathBuf>) -> Self {
+        let workspace = PathBuf::from(&config.workspace_dir).join(job_id.to_string());
+        // redirects are not followed, since the target could be off the allowlist
+        let client = reqwest::Client::builder()
+            .timeout(HTTP_FETCH_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .expect("failed to build reqwest client");
+        Self {
+            config,
+            client,
+            workspace,
+            project_dir,
+            sent_files: vec![],
+            egress: EgressRules::default(),
+            agent_token: None,
+            blocked: vec![],
+        }
+    }
+
+    /// Holds `http_fetch` to the run's egress rules and points `shell` at the
+    /// egress proxy.
+    pub fn with_egress(mut self, egress: EgressRules, agent_token: Option<String>) -> Self {
+        self.egress = egress;
+        self.agent_token = agent_token;
+        self
+    }
+
+    /// Log lines for requests the egress policy refused.
+    pub fn take_blocked(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.blocked)
+    }
+
+    pub async fn prepare(&mut self) -> std::io::Result<()> {
+        tokio::fs::create_dir_all(&self.workspace).await?;
+        if let Some(ref project) = self.project_dir {
+            self.project_dir = Some(prepare_project(project).await?);
+        }
+        Ok(())
+    }
+
+    pub fn into_sent_files(self) -> Vec<serde_json::Value> {
+        self.sent_files
+    }
+
+    /// Tool schemas in the OpenAI `tools` format.
+    pub fn definitions(&self) -> Vec<serde_json::Value> {
+        let mut tools = vec![
+            tool(
+                "read_file",
+                "Read a text file from the task workspace.",
+                serde_json::json!({
+                    "path": { "type": "string", "description": "Path relative to the workspace" }
+                }),
+                &["path"],
+            ),
+            tool(
+                "write_file",
+                "Create or overwrite a file in the task workspace.",
+                serde_json::json!({
+                    "path": { "type": "string", "description": "Path relative to the workspace" },
+                    "content": { "type": "string" }
+                }),
+                &["path", "content"],
+            ),
+            tool(
+                "ask_user",
+                "Ask the user a question and stop until they answer. Only use it when the task cannot continue without them.",
+                serde_json::json!({ "question": { "type": "string" } }),
+                &["question"],
+            ),
+            tool(
+                "send_file",
+                "Send a file from the workspace to the user along with your final answer.",
+                serde_json::json!({
+                    "path": { "type": "string", "description": "Path relative to the workspace" },
+                    "mime": { "type": "string", "description": "MIME type, e.g. image/png" }
+                }),
+                &["path"],
+            ),
+        ];
+        if !self.config.http_allowlist.is_empty() {
+            tools.push(tool(
+                "http_fetch",
+                "Fetch a URL with GET and return the status and body text.",
+                serde_json::json!({ "url": { "type": "string" } }),
+                &["url"],
+            ));
+        }
+        if self.config.shell_image.is_some() {
+            tools.push(tool(
+                "shell",
+                "Run a shell command in the task workspace and return its exit code and output.",
+                serde_json::json!({ "command": { "type": "string" } }),
+                &["command"],
+            ));
+        }
+        tools
+    }
+
+    /// Runs one tool call. Failures are reported to the model as output so it
+    /// can recover instead of ending the run.
+    pub async fn call(&mut self, name: &str, arguments: &str) -> ToolOutcome {
+        let args: serde_json::Value = match serde_json::from_str(arguments) {
+            Ok(v) => v,
+            Err(e) => return ToolOutcome::Output(format!("error: invalid arguments: {e}")),
+        };
+        let arg = |key: &str| args[key].as_str().unwrap_or_default().to_string();
+
+        let result = match name {
+            "read_file" => self.read_file(&arg("path")).await,
+            "write_file" => self.write_file(&arg("path"), &arg("content")).await,
+            "send_file" => self.send_file(&arg("path"), args["mime"].as_str()).await,
+            "http_fetch" if !self.config.http_allowlist.is_empty() => {
+                self.http_fetch(&arg("url")).await
+            }
+            "shell" if self.config.shell_image.is_some() => self.shell(&arg("command")).await,
+            "ask_user" => return ToolOutcome::AskUser(arg("question")),
+            _ => Err(format!("unknown tool {name}")),
+        };
+        ToolOutcome::Output(truncate_output(match result {
+            Ok(output) => output,
+            Err(e) => format!("error: {e}"),
+        }))
+    }
+
+    fn resolve(&self, path: &str) -> Result<PathBuf, String> {
+        resolve_in_workspace(&self.workspace, self.project_dir.as_deref(), path)
+            .ok_or_else(|| format!("{path} is outside the workspace"))
+    }
+
+    /// Where file tools may reach. Anything the agent or an earlier run left
+    /// in them, links included, is checked against these before it is used.
+    fn roots(&self) -> Vec<&Path> {
+        std::iter::once(self.workspace.as_path())
+            .chain(self.project_dir.as_deref())
+            .collect()
+    }
+
+    async fn read_file(&self, path: &str) -> Result<String, String> {
+        let resolved = self.resolve(path)?;
+        let mut file = open_confined(&self.roots(), &resolved)
+            .await
+            .map_err(|e| format!("cannot read {path}: {e}"))?;
+        let mut content = String::new();
+        file.read_to_string(&mut content)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(content)
+    }
+
+    async fn write_file(&self, path: &str, content: &str) -> Result<String, String> {
+        let resolved = self.resolve(path)?;
+        let mut file = create_confined(&self.roots(), &resolved)
+            .await
+            .map_err(|e| format!("cannot write {path}: {e}"))?;
+        file.write_all(content.as_bytes())
+            .await
+            .map_err(|e| e.to_string())?;
+        file.flush().await.map_err(|e| e.to_string())?;
+        Ok(format!("wrote {} bytes to {path}", content.len()))
+    }
+
+    async fn send_file(&mut self, path: &str, mime: Option<&str>) -> Result<String, String> {
+        let source = self.resolve(path)?;
+        let name = source
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .ok_or_else(|| format!("{path} is not a file"))?;
+        tokio::fs::create_dir_all(&self.config.media_dir)
+            .await
+            .map_err(|e| e.to_string())?;
+        let dest = format!(
+            "{}/{}_{name}",
+            self.config.media_dir,
+            Uuid::new_v4().as_simple()
+        );
+        copy_agent_file(&self.roots(), &source, Path::new(&dest))
+            .await
+            .map_err(|e| format!("cannot send {path}: {e}"))?;
+
+        let mime = mime.unwrap_or("application/octet-stream");
+        let kind = if mime.starts_with("image/") {
+            "image"
+        } else {
+            "document"
+        };
+        self.sent_files.push(serde_json::json!({
+            "type": kind,
+            "path": dest,
+            "name": name,
+            "mime": mime,
+        }));
+        Ok(format!("{name} will be sent with your answer"))
+    }
+
+    async fn http_fetch(&mut self, url: &str) -> Result<String, String> {
+        let parsed = reqwest::Url::parse(url).map_err(|e| e.to_string())?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(format!("unsupported scheme {}", parsed.scheme()));
+        }
+        let host = parsed.host_str().unwrap_or_default();
+        if !host_allowed(&self.config.http_allowlist, host) {
+            return Err(format!("{host} is not on the allowlist"));
+        }
+        if !self.egress.allows(host) {
+            self.blocked.push(format!(
+                "egress blocked: GET {host}:{}",
+                parsed.port_or_known_default().unwrap_or_default()
+            ));
+            return Err(format!("{host} is blocked by this task's network policy"));
+        }
+
+        let mut response = self
+            .client
+            .get(parsed)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        let status = response.status();
+        if status.is_redirection() {
+            let location = response
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default();
+            return Ok(format!("HTTP {status}, redirected to {location}"));
+        }
+        let mut body = vec![];
+        let mut truncated = false;
+        while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+            let room = MAX_FETCH_BYTES - body.len();
+            if chunk.len() > room {
+                body.extend_from_slice(&chunk[..room]);
+                truncated = true;
+                break;
+            }
+            body.extend_from_slice(&chunk);
+        }
+        let mut body = String::from_utf8_lossy(&body).into_owned();
+        if truncated {
+            body.push_str("\n[body truncated]");
+        }
+        Ok(format!("HTTP {status}\n\n{body}"))
+    }
+
+    /// Runs the command in a throwaway container, never on the host. It
+    /// gets the workspace and project mounts and the run's network policy.
+    async fn shell(&self, command: &str) -> Result<String, String> {
+        let Some(ref image) = self.config.shell_image else {
+            return Err("unknown tool shell".to_string());
+        };
+        let egress = egress_config();
+        let restricted = self.egress.is_restricted();
+        let network = match egress.network.as_deref() {
+            _ if !restricted => None,
+            Some(network) => Some(network),
+            None => {
+                return Err(
+                    "shell is unavailable, this task's network policy needs an egress network"
+                        .to_string(),
+                );
+            }
+        };
+        let workspace = tokio::fs::canonicalize(&self.workspace)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let name = format!("yui-shell-{}", Uuid::new_v4().as_simple());
+        let mut cmd = Command::new(&self.config.container_cli);
+        cmd.args(["run", "--rm", "--name", &name, "--workdir", "/workspace"])
+            .arg("-v")
+            .arg(format!("{}:/workspace", workspace.display()));
+        if let Some(ref project) = self.project_dir {
+            cmd.arg("-v")
+                .arg(format!("{}:/workspace/{PROJECT_MOUNT}", project.display()));
+        }
+        if let Some(network) = network {
+            cmd.arg(format!("--network={network}"));
+        }
+        for (name, value) in self
+            .egress
+            .proxy_env(&egress.proxy_url, self.agent_token.as_deref())
+        {
+            cmd.arg("-e").arg(format!("{name}={value}"));
+        }
+        cmd.arg(image)
+            .args(["sh", "-c", command])
+            .stdin(Stdio::null())
+            .kill_on_drop(true);
+
+        let timeout = Duration::from_secs(self.config.shell_timeout_secs);
+        let output = match tokio::time::timeout(timeout, cmd.output()).await {
+            Ok(result) => result.map_err(|e| e.to_string())?,
+            Err(_) => {
+                // killing the client leaves the container running
+                let _ = Command::new(&self.config.container_cli)
+                    .args(["rm", "-f", &name])
+                    .output()
+                    .await;
+                return Err(format!(
+                    "timed out after {}s",
+                    self.config.shell_timeout_secs
+                ));
+            }
+        };
+        Ok(format!(
+            "exit code: {}\nstdout:\n{}\nstderr:\n{}",
+            output.status.code().unwrap_or(-1),
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        ))
+    }
+}
+
+fn tool(
+    name: &str,
+    description: &str,
+    properties: serde_json::Value,
+    required: &[&str],
+) -> serde_json::Value {
+    serde_json::json!({
+        "type": "function",
+        "function": {
+            "name": name,
+            "description": description,
+            "parameters": {
+                "type": "object",
+                "properties": properties,
+                "required": required,
+                "additionalProperties": false
+            }
+        }
+    })
+}
+
+/// Joins a model-supplied relative path onto the workspace, refusing anything
+/// that could climb out of it. Paths under `project/` land in the persistent
+/// workspace when the job has one.
+pub(crate) fn resolve_in_workspace(
+    workspace: &Path,
+    project: Option<&Path>,
+    path: &str,
+) -> Option<PathBuf> {
+    let relative = Path::new(path.trim_start_matches("/workspace/"));
+    let mut parts = vec![];
+    for component in relative.components() {
+        match component {
+            Component::Normal(part) => parts.push(part),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    let (root, rest) = match (project, parts.split_first()) {
+        (Some(project), Some((first, rest))) if *first == OsStr::new(PROJECT_MOUNT) => {
+            (project, rest)
+        }
+        _ => (workspace, &parts[..]),
+    };
+    let mut resolved = root.to_path_buf();
+    resolved.extend(rest);
+    (resolved != workspace).then_some(resolved)
+}
+
+pub(crate) fn host_allowed(allowlist: &[String], host: &str) -> bool {
+    let host = host.to_ascii_lowercase();
+    allowlist
+        .iter()
+        .any(|entry| match entry.strip_prefix("*.") {
+            Some(domain) => host == domain || host.ends_with(&format!(".{domain}")),
+            None => entry == "*" || *entry == host,
+        })
+}
+
+fn truncate_output(output: String) -> String {
+    match output.char_indices().nth(MAX_TOOL_OUTPUT_CHARS) {
+        Some((end, _)) => format!("{}\n[output truncated]", &output[..end]),
+        None => output,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(dir: &Path) -> AgentToolsConfig {
+        AgentToolsConfig {
+            workspace_dir: dir.display().to_string(),
+            media_dir: dir.join("media").display().to_string(),
+            http_allowlist: vec![],
+            shell_image: None,
+            container_cli: "docker".to_string(),
+            shell_timeout_secs: 5,
+        }
+    }
+
+    #[test]
+    fn workspace_paths_cannot_escape() {
+        let workspace = Path::new("/srv/workspaces/job");
+
+        let project = Some(Path::new("/srv/projects/forge-v2"));
+
+        assert_eq!(
+            resolve_in_workspace(workspace, None, "out/report.md"),
+            Some(PathBuf::from("/srv/workspaces/job/out/report.md"))
+        );
+        assert_eq!(
+            resolve_in_workspace(workspace, None, "/workspace/chart.png"),
+            Some(PathBuf::from("/srv/workspaces/job/chart.png"))
+        );
+        assert_eq!(
+            resolve_in_workspace(workspace, project, "/workspace/project/notes.md"),
+            Some(PathBuf::from("/srv/projects/forge-v2/notes.md"))
+        );
+        assert_eq!(
+            resolve_in_workspace(workspace, None, "project/notes.md"),
+            Some(PathBuf::from("/srv/workspaces/job/project/notes.md"))
+        );
+        assert_eq!(
+            resolve_in_workspace(workspace, project, "project/../../x"),
+            None
+        );
+        assert_eq!(
+            resolve_in_workspace(workspace, None, "../other/secrets"),
+            None
+        );
+        assert_eq!(resolve_in_workspace(workspace, None, "/etc/passwd"), None);
+        assert_eq!(resolve_in_workspace(workspace, None, "."), None);
+    }
+
+    #[test]
+    fn allowlist_matches_hosts_and_subdomains() {
+        let allowlist = vec![
+            "api.open-meteo.com".to_string(),
+            "*.wikipedia.org".to_string(),
+        ];
+
+        assert!(host_allowed(&allowlist, "api.open-meteo.com"));
+        assert!(host_allowed(&allowlist, "en.wikipedia.org"));
+        assert!(host_allowed(&allowlist, "wikipedia.org"));
+        assert!(!host_allowed(&allowlist, "evilwikipedia.org"));
+        assert!(!host_allowed(&allowlist, "open-meteo.com"));
+        assert!(host_allowed(&["*".to_string()], "example.com"));
+    }
+
+    #[tokio::test]
+    async fn file_tools_stay_in_the_workspace() {
+        let dir = std::env::temp_dir().join(format!("yui-tools-{}", Uuid::new_v4()));
+        let job_id = Uuid::new_v4();
+        let mut tools = AgentTools::new(config(&dir), job_id, None);
+        tools.prepare().await.unwrap();
+
+        let write = r#"{"path":"notes/todo.txt","content":"buy milk"}"#;
+        assert!(matches!(
+            tools.call("write_file", write).await,
+            ToolOutcome::Output(_)
+        ));
+        let read = tools
+            .call("read_file", r#"{"path":"notes/todo.txt"}"#)
+            .await;
+        assert!(matches!(read, ToolOutcome::Output(ref text) if text == "buy milk"));
+        let escape = tools
+            .call("read_file", r#"{"path":"../../etc/passwd"}"#)
+            .await;
+        assert!(matches!(escape, ToolOutcome::Output(ref text) if text.starts_with("error:")));
+        let shell = tools.call("shell", r#"{"command":"ls"}"#).await;
+        assert!(matches!(
+            shell,
+            ToolOutcome::Output(ref text) if text == "error: unknown tool shell"
+        ));
+
+        // links an agent planted, to a file or a directory outside, are
+        // refused by every file tool, and nothing outside is touched
+        let outside = dir.join("outside");
+        tokio::fs::create_dir_all(&outside).await.unwrap();
+        tokio::fs::write(outside.join("secret.txt"), "secret")
+            .await
+            .unwrap();
+        let workspace = dir.join(job_id.to_string());
+        tokio::fs::symlink(outside.join("secret.txt"), workspace.join("leak"))
+            .await
+            .unwrap();
+        tokio::fs::symlink(&outside, workspace.join("escape"))
+            .await
+            .unwrap();
+        for (tool, args) in [
+            ("read_file", r#"{"path":"leak"}"#),
+            ("read_file", r#"{"path":"escape/secret.txt"}"#),
+            ("write_file", r#"{"path":"leak","content":"x"}"#),
+            (
+                "write_file",
+                r#"{"path":"escape/secret.txt","content":"x"}"#,
+            ),
+            (
+                "write_file",
+                r#"{"path":"escape/new/file.txt","content":"x"}"#,
+            ),
+            ("send_file", r#"{"path":"leak"}"#),
+        ] {
+            let outcome = tools.call(tool, args).await;
+            assert!(
+                matches!(outcome, ToolOutcome::Output(ref text) if text.starts_with("error:")),
+                "{tool} {args}"
+            );
+        }
+        assert_eq!(
+            tokio::fs::read_to_string(outside.join("secret.txt"))
+                .await
+                .unwrap(),
+            "secret"
+        );
+        assert!(!outside.join("new").exists());
+        assert!(tools.into_sent_files().is_empty());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn http_fetch_respects_the_egress_policy() {
+        let config = AgentToolsConfig {
+            http_allowlist: vec!["*".to_string()],
+            ..config(&std::env::temp_dir())
+        };
+        let egress = EgressRules {
+            policy: crate::schema::EgressPolicy::Allowlist,
+            allow: vec!["api.open-meteo.com".to_string()],
+        };
+        let mut tools = AgentTools::new(config, Uuid::new_v4(), None).with_egress(egress, None);
+
+        let fetch = tools
+            .call("http_fetch", r#"{"url":"https://example.com/upload"}"#)
+            .await;
+        assert!(matches!(
+            fetch,
+            ToolOutcome::Output(ref text) if text.contains("blocked by this task's network policy")
+        ));
+        assert_eq!(
+            tools.take_blocked(),
+            vec!["egress blocked: GET example.com:443"]
+        );
+        assert!(tools.take_blocked().is_empty());
+    }
+
+    #[tokio::test]
+    async fn http_fetch_stops_reading_at_the_byte_cap() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0; 1024];
+            let _ = socket.read(&mut request).await;
+            let _ = socket
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-type: text/plain\r\n\r\n")
+                .await;
+            // far more than the cap, and no length, so only the cap ends it
+            let chunk = vec![b'a'; 64 * 1024];
+            for _ in 0..64 {
+                if socket.write_all(&chunk).await.is_err() {
+                    break;
+                }
+            }
+        });
+        let config = AgentToolsConfig {
+            http_allowlist: vec!["*".to_string()],
+            ..config(&std::env::temp_dir())
+        };
+        let mut tools = AgentTools::new(config, Uuid::new_v4(), None);
+
+        let body = tools
+            .http_fetch(&format!("http://{addr}/big"))
+            .await
+            .unwrap();
+        assert!(body.starts_with("HTTP 200 OK"));
+        assert!(body.ends_with("[body truncated]"));
+        assert!(body.len() < MAX_FETCH_BYTES + 100);
+    }
+
+    #[tokio::test]
+    async fn shell_runs_in_a_container_not_on_the_host() {
+        let dir = std::env::temp_dir().join(format!("yui-tools-{}", Uuid::new_v4()));
+        // `echo` stands in for the container CLI and prints what it was asked to run
+        let config = AgentToolsConfig {
+            shell_image: Some("yui-agent".to_string()),
+            container_cli: "echo".to_string(),
+            ..config(&dir)
+        };
+        let job_id = Uuid::new_v4();
+        let mut tools = AgentTools::new(config.clone(), job_id, None);
+        tools.prepare().await.unwrap();
+        let workspace = dir.join(job_id.to_string()).canonicalize().unwrap();
+
+        let ToolOutcome::Output(output) = tools.call("shell", r#"{"command":"ls"}"#).await else {
+            panic!("shell should not ask the user");
+        };
+        assert!(output.contains("run --rm --name yui-shell-"), "{output}");
+        assert!(output.contains(&format!("-v {}:/workspace ", workspace.display())));
+        assert!(output.contains("yui-agent sh -c ls"));
+
+        let egress = EgressRules {
+            policy: crate::schema::EgressPolicy::None,
+            allow: vec![],
+        };
+        let mut tools = AgentTools::new(config, job_id, None).with_egress(egress, None);
+        if egress_config().network.is_none() {
+            let refused = tools.call("shell", r#"{"command":"ls"}"#).await;
+            assert!(matches!(
+                refused,
+                ToolOutcome::Output(ref text) if text.contains("needs an egress network")
+            ));
+        }
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
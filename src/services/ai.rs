@@ -1,4 +1,5 @@
 use crate::services::embedding::EmbeddingService;
+use crate::services::metrics;
 use crate::services::reply_client::ReplyClient;
 use crate::services::triage_client::{TriageClient, TriageClientConfig};
 use serde::{Deserialize, Serialize};
@@ -20,6 +21,22 @@ pub struct TriageBatchInput {
     pub active_crons: Vec<ActiveCronSummary>,
     #[serde(default)]
     pub history: Vec<String>,
+    /// Files recent jobs sent to this chat, newest first.
+    #[serde(default)]
+    pub recent_artifacts: Vec<ArtifactSummary>,
+    /// Names of the chat's persistent workspaces.
+    #[serde(default)]
+    pub workspaces: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArtifactSummary {
+    pub id: Uuid,
+    pub name: String,
+    pub mime: String,
+    /// Prompt of the job that produced it, for matching "that report".
+    pub job_prompt: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,6 +55,12 @@ pub struct ActiveJobSummary {
     pub id: Uuid,
     pub status: String,
     pub prompt: Option<String>,
+    /// Latest progress message the agent reported, if any.
+    #[serde(default)]
+    pub progress: Option<String>,
+    /// Last few log lines, newest first.
+    #[serde(default)]
+    pub recent_logs: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -48,11 +71,27 @@ pub enum TriageDecision {
     CreateJob {
         prompt: String,
         kind: String,
+        /// Resource profile hint (`light`, `standard`, `heavy`); the job kind decides when absent.
+        #[serde(default)]
+        profile: Option<String>,
+        /// Persistent workspace to run in, created on first use; `None` for a
+        /// fresh one that is thrown away.
+        #[serde(default)]
+        workspace: Option<String>,
+        /// Why the user should confirm the job before it runs; `None` when
+        /// it can run straight away.
+        #[serde(default)]
+        approval: Option<String>,
+    },
+    /// Several jobs where some wait for others, e.g. clone, then build, then test.
+    CreatePlan {
+        steps: Vec<PlanStep>,
     },
     CreateCron {
         name: String,
         schedule: String,
         prompt: String,
+        notify_policy: String,
     },
     CancelJob {
         job_id: Uuid,
@@ -65,12 +104,42 @@ pub enum TriageDecision {
         job_id: Uuid,
         input: String,
     },
+    /// The user's answer to a job waiting in `awaiting_approval`.
+    ApproveJob {
+        job_id: Uuid,
+        approved: bool,
+    },
     SetSubscription {
         enabled: bool,
     },
+    /// Sends a file an earlier job produced to the chat again.
+    ResendArtifact {
+        artifact_id: Uuid,
+    },
+    /// Empties a persistent workspace once no job is using it.
+    ResetWorkspace {
+        name: String,
+    },
     Noop,
 }
 
+/// One job of a `CreatePlan`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanStep {
+    pub prompt: String,
+    pub kind: String,
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub workspace: Option<String>,
+    #[serde(default)]
+    pub approval: Option<String>,
+    /// Indexes of earlier steps this one waits for; their outputs are added
+    /// to its prompt when it starts.
+    #[serde(default)]
+    pub after: Vec<usize>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TriageBatchDecision {
     pub decisions: Vec<TriageDecision>,
@@ -148,13 +217,15 @@ impl AiService for RealAiService {
     async fn enrich_job(&self, input: EnrichInput) -> anyhow::Result<EnrichOutput> {
         let mut enriched_prompt = String::from(
             "You are Yui, a capable autonomous agent running in a Docker container. \
-             You have full shell access including curl, git, python3, nodejs, npm, and yt-dlp. \
-             You CAN and SHOULD use curl/wget to fetch data from APIs, download files, and interact with web services. \
+             You have shell access including curl, git, python3, nodejs, npm, and yt-dlp. \
+             Fetch only what the task needs from the network. Never send workspace files, secrets \
+             or this conversation to third-party services unless the task asks for exactly that. \
+             If a request is blocked by the network policy, say so in your answer instead of \
+             trying to get around it. \
              Always attempt to complete the task using available tools. \
              Save any output files to /workspace/ so they can be sent back to the user.\n\
-             To send a GIF: use `curl -L -o /workspace/funny.gif \"https://cataas.com/cat/gif\"`. \
              Any files in /workspace/ are automatically sent back as attachments. \
-             For GIF requests, always download an actual GIF file rather than sending a URL.\n\n",
+             For GIF requests, download an actual GIF file rather than sending a URL.\n\n",
         );
         enriched_prompt.push_str(&input.prompt);
         if !input.history.is_empty() {
@@ -169,13 +240,18 @@ impl AiService for RealAiService {
     async fn embed_text(&self, text: &str) -> anyhow::Result<Vec<f32>> {
         let text = text.to_string();
         let embedding = self.embedding.clone();
-        tokio::task::spawn_blocking(move || embedding.embed(&text))
+        let started = std::time::Instant::now();
+        let result = tokio::task::spawn_blocking(move || embedding.embed(&text))
             .await
-            .map_err(|e| anyhow::anyhow!("embedding task failed: {e}"))?
+            .map_err(|e| anyhow::anyhow!("embedding task failed: {e}"))?;
+        metrics::observe_embedding(started.elapsed());
+        result
     }
 
     async fn rewrite_reply(&self, content: &str, history: &[String]) -> anyhow::Result<String> {
-        self.reply_client.rewrite(content, history).await
+        let result = self.reply_client.rewrite(content, history).await;
+        metrics::record_llm_call("reply", result.is_ok());
+        result
     }
 }
 
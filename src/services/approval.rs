@@ -0,0 +1,121 @@
+/// Prompt fragments that need the user's go-ahead whatever triage decided,
+/// unless `YUI_APPROVAL_PATTERNS` replaces them.
+const DEFAULT_APPROVAL_PATTERNS: &[&str] = &[
+    "delete",
+    "remove all",
+    "rm -rf",
+    "wipe",
+    "drop table",
+    "truncate",
+    "deploy",
+    "production",
+    "force push",
+    "push --force",
+    "send money",
+    "transfer money",
+    "purchase",
+];
+
+pub fn approval_patterns() -> Vec<String> {
+    match std::env::var("YUI_APPROVAL_PATTERNS") {
+        Ok(raw) => raw
+            .split(',')
+            .map(|p| p.trim().to_lowercase())
+            .filter(|p| !p.is_empty())
+            .collect(),
+        Err(_) => DEFAULT_APPROVAL_PATTERNS
+            .iter()
+            .map(|p| p.to_string())
+            .collect(),
+    }
+}
+
+/// How long a job waits for the user's answer before it is cancelled.
+pub fn approval_timeout_mins() -> i32 {
+    std::env::var("YUI_APPROVAL_TIMEOUT_MINS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(60)
+}
+
+/// The first pattern that appears in the prompt as whole words, so
+/// "delete" catches "delete the logs" but not "undeleted".
+pub fn approval_rule<'a>(prompt: &str, patterns: &'a [String]) -> Option<&'a str> {
+    let prompt = prompt.to_lowercase();
+    let boundary = |c: Option<char>| !c.is_some_and(char::is_alphanumeric);
+    patterns
+        .iter()
+        .find(|pattern| {
+            prompt.match_indices(pattern.as_str()).any(|(start, _)| {
+                boundary(prompt[..start].chars().next_back())
+                    && boundary(prompt[start + pattern.len()..].chars().next())
+            })
+        })
+        .map(String::as_str)
+}
+
+/// `Some(true)` for a message that is plainly a yes, `Some(false)` for a
+/// plain no, `None` for anything else. Only a whole-message answer counts, so
+/// "yes, but first check the backups" does not start the job.
+pub fn explicit_answer(text: &str) -> Option<bool> {
+    let answer = text
+        .trim()
+        .trim_end_matches(['.', '!'])
+        .trim()
+        .to_lowercase();
+    match answer.as_str() {
+        "yes" | "y" | "yep" | "yeah" | "yes please" | "go ahead" | "do it" | "approve"
+        | "approved" | "confirm" | "confirmed" | "ok" | "okay" | "sure" | "👍" => Some(true),
+        "no" | "n" | "nope" | "no thanks" | "don't" | "dont" | "do not" | "deny" | "reject"
+        | "cancel" | "stop" | "👎" => Some(false),
+        _ => None,
+    }
+}
+
+pub fn approval_question(prompt: &str, reason: Option<&str>, timeout_mins: i32) -> String {
+    let preview: String = prompt.chars().take(200).collect();
+    let ellipsis = if prompt.chars().count() > 200 {
+        "..."
+    } else {
+        ""
+    };
+    let reason = reason.map(|r| format!(" ({r})")).unwrap_or_default();
+    format!(
+        "Before I start on this{reason}:\n\n{preview}{ellipsis}\n\n\
+         Reply yes to go ahead or no to cancel. I'll drop it if I don't hear back within \
+         {timeout_mins} minutes."
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules_match_whole_words_only() {
+        let patterns: Vec<String> = DEFAULT_APPROVAL_PATTERNS
+            .iter()
+            .map(|p| p.to_string())
+            .collect();
+
+        assert_eq!(
+            approval_rule("Delete all test files", &patterns),
+            Some("delete")
+        );
+        assert_eq!(
+            approval_rule("deploy to production", &patterns),
+            Some("deploy")
+        );
+        assert_eq!(approval_rule("list undeleted files", &patterns), None);
+        assert_eq!(approval_rule("what's the weather", &patterns), None);
+    }
+
+    #[test]
+    fn only_plain_answers_count() {
+        assert_eq!(explicit_answer(" Yes! "), Some(true));
+        assert_eq!(explicit_answer("go ahead."), Some(true));
+        assert_eq!(explicit_answer("no"), Some(false));
+        assert_eq!(explicit_answer("yes, but check the backups first"), None);
+        assert_eq!(explicit_answer("what does it delete?"), None);
+    }
+}
@@ -0,0 +1,262 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+/// Upper bounds (seconds) for the embedding latency histogram.
+const EMBEDDING_BUCKETS: [f64; 9] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5];
+
+#[derive(Default)]
+struct DaemonStats {
+    ticks: u64,
+    items: u64,
+    errors: u64,
+}
+
+#[derive(Default)]
+struct LlmStats {
+    calls: u64,
+    failures: u64,
+}
+
+#[derive(Default)]
+struct Histogram {
+    buckets: [u64; EMBEDDING_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(EMBEDDING_BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+}
+
+#[derive(Default)]
+struct Registry {
+    daemons: BTreeMap<&'static str, DaemonStats>,
+    llm: BTreeMap<&'static str, LlmStats>,
+    embedding: Histogram,
+    active_runs: u64,
+    outbox_backlog: i64,
+    outbox_dead_letters: i64,
+}
+
+/// Process-local, so each instance reports only the work it did itself.
+static REGISTRY: LazyLock<Mutex<Registry>> = LazyLock::new(Default::default);
+
+fn with_registry(f: impl FnOnce(&mut Registry)) {
+    if let Ok(mut registry) = REGISTRY.lock() {
+        f(&mut registry);
+    }
+}
+
+/// Records one loop iteration and passes the tick result through unchanged.
+pub fn observe_tick<E>(daemon: &'static str, result: Result<u32, E>) -> Result<u32, E> {
+    with_registry(|r| {
+        let stats = r.daemons.entry(daemon).or_default();
+        stats.ticks += 1;
+        match &result {
+            Ok(n) => stats.items += u64::from(*n),
+            Err(_) => stats.errors += 1,
+        }
+    });
+    result
+}
+
+pub fn record_items(daemon: &'static str, items: usize) {
+    with_registry(|r| r.daemons.entry(daemon).or_default().items += items as u64);
+}
+
+pub fn record_llm_call(purpose: &'static str, ok: bool) {
+    with_registry(|r| {
+        let stats = r.llm.entry(purpose).or_default();
+        stats.calls += 1;
+        if !ok {
+            stats.failures += 1;
+        }
+    });
+}
+
+pub fn observe_embedding(elapsed: Duration) {
+    with_registry(|r| r.embedding.observe(elapsed.as_secs_f64()));
+}
+
+pub fn set_active_runs(count: usize) {
+    with_registry(|r| r.active_runs = count as u64);
+}
+
+pub fn set_outbox_depth(backlog: i64, dead_letters: i64) {
+    with_registry(|r| {
+        r.outbox_backlog = backlog;
+        r.outbox_dead_letters = dead_letters;
+    });
+}
+
+/// Renders every metric in the Prometheus text exposition format.
+pub fn render() -> String {
+    match REGISTRY.lock() {
+        Ok(registry) => render_registry(&registry),
+        Err(_) => String::new(),
+    }
+}
+
+fn render_registry(r: &Registry) -> String {
+    let mut out = String::new();
+
+    header(
+        &mut out,
+        "yui_daemon_ticks_total",
+        "counter",
+        "Loop iterations per daemon.",
+    );
+    for (daemon, stats) in &r.daemons {
+        let _ = writeln!(
+            out,
+            "yui_daemon_ticks_total{{daemon=\"{daemon}\"}} {}",
+            stats.ticks
+        );
+    }
+    header(
+        &mut out,
+        "yui_daemon_items_total",
+        "counter",
+        "Items processed per daemon.",
+    );
+    for (daemon, stats) in &r.daemons {
+        let _ = writeln!(
+            out,
+            "yui_daemon_items_total{{daemon=\"{daemon}\"}} {}",
+            stats.items
+        );
+    }
+    header(
+        &mut out,
+        "yui_daemon_tick_errors_total",
+        "counter",
+        "Failed loop iterations per daemon.",
+    );
+    for (daemon, stats) in &r.daemons {
+        let _ = writeln!(
+            out,
+            "yui_daemon_tick_errors_total{{daemon=\"{daemon}\"}} {}",
+            stats.errors
+        );
+    }
+
+    header(
+        &mut out,
+        "yui_runtime_active_runs",
+        "gauge",
+        "Agent runs currently tracked by the runtime daemon.",
+    );
+    let _ = writeln!(out, "yui_runtime_active_runs {}", r.active_runs);
+    header(
+        &mut out,
+        "yui_outbox_backlog",
+        "gauge",
+        "Undelivered outbox rows still eligible for delivery.",
+    );
+    let _ = writeln!(out, "yui_outbox_backlog {}", r.outbox_backlog);
+    header(
+        &mut out,
+        "yui_outbox_dead_letters",
+        "gauge",
+        "Undelivered outbox rows that exhausted their attempts.",
+    );
+    let _ = writeln!(out, "yui_outbox_dead_letters {}", r.outbox_dead_letters);
+
+    header(
+        &mut out,
+        "yui_llm_calls_total",
+        "counter",
+        "LLM requests per purpose.",
+    );
+    for (purpose, stats) in &r.llm {
+        let _ = writeln!(
+            out,
+            "yui_llm_calls_total{{purpose=\"{purpose}\"}} {}",
+            stats.calls
+        );
+    }
+    header(
+        &mut out,
+        "yui_llm_failures_total",
+        "counter",
+        "Failed LLM requests per purpose.",
+    );
+    for (purpose, stats) in &r.llm {
+        let _ = writeln!(
+            out,
+            "yui_llm_failures_total{{purpose=\"{purpose}\"}} {}",
+            stats.failures
+        );
+    }
+
+    header(
+        &mut out,
+        "yui_embedding_seconds",
+        "histogram",
+        "Time to embed one text.",
+    );
+    for (count, bound) in r.embedding.buckets.iter().zip(EMBEDDING_BUCKETS) {
+        let _ = writeln!(
+            out,
+            "yui_embedding_seconds_bucket{{le=\"{bound}\"}} {count}"
+        );
+    }
+    let _ = writeln!(
+        out,
+        "yui_embedding_seconds_bucket{{le=\"+Inf\"}} {}",
+        r.embedding.count
+    );
+    let _ = writeln!(out, "yui_embedding_seconds_sum {}", r.embedding.sum);
+    let _ = writeln!(out, "yui_embedding_seconds_count {}", r.embedding.count);
+
+    out
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_daemon_counters_with_labels() {
+        let mut registry = Registry::default();
+        let stats = registry.daemons.entry("delivery").or_default();
+        stats.ticks = 3;
+        stats.items = 7;
+        stats.errors = 1;
+
+        let text = render_registry(&registry);
+        assert!(text.contains("# TYPE yui_daemon_ticks_total counter"));
+        assert!(text.contains("yui_daemon_ticks_total{daemon=\"delivery\"} 3"));
+        assert!(text.contains("yui_daemon_items_total{daemon=\"delivery\"} 7"));
+        assert!(text.contains("yui_daemon_tick_errors_total{daemon=\"delivery\"} 1"));
+    }
+
+    #[test]
+    fn embedding_histogram_buckets_are_cumulative() {
+        let mut registry = Registry::default();
+        registry.embedding.observe(0.02);
+        registry.embedding.observe(0.3);
+
+        let text = render_registry(&registry);
+        assert!(text.contains("yui_embedding_seconds_bucket{le=\"0.01\"} 0"));
+        assert!(text.contains("yui_embedding_seconds_bucket{le=\"0.025\"} 1"));
+        assert!(text.contains("yui_embedding_seconds_bucket{le=\"0.5\"} 2"));
+        assert!(text.contains("yui_embedding_seconds_bucket{le=\"+Inf\"} 2"));
+        assert!(text.contains("yui_embedding_seconds_count 2"));
+    }
+}
@@ -1,13 +1,27 @@
 pub mod agent_executor;
 pub mod agent_runner;
+pub mod agent_tools;
 pub mod ai;
+pub mod approval;
+pub mod egress;
 pub mod embedding;
 pub mod media_preprocessor;
+pub mod metrics;
+pub mod process_runner;
 pub mod reply_client;
+pub mod resource_profile;
+pub mod secrets;
 pub mod triage_client;
+pub mod workspace;
 
 pub use agent_executor::*;
 pub use agent_runner::*;
 pub use ai::*;
+pub use approval::*;
+pub use egress::*;
 pub use embedding::*;
 pub use media_preprocessor::*;
+pub use process_runner::*;
+pub use resource_profile::*;
+pub use secrets::*;
+pub use workspace::*;
@@ -0,0 +1,628 @@
+use crate::schema::{Attachment, SecretInjection};
+use crate::services::agent_executor::ContainerFrame;
+use crate::services::agent_runner::{
+    AgentRunnerService, RunnerEvent, RunnerHandle, RunnerStartInput, StepReport,
+};
+use crate::services::egress::egress_config;
+use crate::services::secrets::{create_private_dir, write_private};
+use crate::services::workspace::{PROJECT_MOUNT, copy_agent_file, prepare_project};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use uuid::Uuid;
+
+#[derive(Debug, Clone)]
+pub struct ProcessRunnerConfig {
+    /// Run through `sh -c` inside the job's workspace directory.
+    pub command: String,
+    pub workspace_dir: String,
+    pub media_dir: String,
+    pub idle_timeout_secs: u64,
+    pub agent_api_url: String,
+    /// Host variables the agent gets on top of `INHERITED_ENV`, such as the
+    /// credentials of the CLI it wraps.
+    pub env_passthrough: Vec<String>,
+}
+
+/// The only parts of yui's own environment a process agent starts with. The
+/// rest, the vault key and database credentials among them, stays behind.
+const INHERITED_ENV: &[&str] = &["PATH", "HOME", "USER", "LANG", "TZ", "TMPDIR"];
+
+/// How long a cancelled agent gets to exit on SIGTERM before it is killed.
+const KILL_GRACE: std::time::Duration = std::time::Duration::from_secs(5);
+
+impl ProcessRunnerConfig {
+    pub fn from_env() -> Option<Self> {
+        Some(Self {
+            command: std::env::var("YUI_PROCESS_COMMAND").ok()?,
+            workspace_dir: std::env::var("YUI_WORKSPACE_DIR")
+                .unwrap_or_else(|_| "storage/workspaces".to_string()),
+            media_dir: std::env::var("YUI_MEDIA_DIR")
+                .unwrap_or_else(|_| "storage/media".to_string()),
+            idle_timeout_secs: std::env::var("YUI_PROCESS_TIMEOUT_IDLE_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(300),
+            agent_api_url: std::env::var("YUI_AGENT_API_URL")
+                .unwrap_or_else(|_| "http://127.0.0.1:8090".to_string()),
+            env_passthrough: std::env::var("YUI_PROCESS_ENV")
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+                .collect(),
+        })
+    }
+}
+
+struct ProcessRun {
+    events: UnboundedReceiver<RunnerEvent>,
+    /// The child leads its own process group, so this is also the group id.
+    pid: Option<u32>,
+}
+
+/// Runs the agent as a local subprocess instead of a container. The command
+/// speaks the same JSONL `ContainerFrame` protocol on stdout, with the prompt
+/// at `YUI_PROMPT_PATH`, so any script that runs inside the agent image can
+/// also run here. Nothing is isolated: only use it with agents you trust.
+pub struct ProcessAgentRunner {
+    config: ProcessRunnerConfig,
+    runs: Arc<Mutex<HashMap<Uuid, ProcessRun>>>,
+}
+
+impl ProcessAgentRunner {
+    pub fn new(config: ProcessRunnerConfig) -> Self {
+        Self {
+            config,
+            runs: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn spawn(
+        &self,
+        input: &RunnerStartInput,
+        workspace: &Path,
+        project: Option<&Path>,
+        secrets_dir: Option<&Path>,
+    ) -> anyhow::Result<Child> {
+        let resume_session = input.session_id.is_some() && input.resume_input.is_some();
+        let prompt_path = workspace.join("prompt.txt");
+        let attachments_json = serde_json::to_string(&input.attachments)?;
+
+        let mut cmd = Command::new("sh");
+        cmd.env_clear();
+        let passthrough = self.config.env_passthrough.iter().map(String::as_str);
+        for name in INHERITED_ENV.iter().copied().chain(passthrough) {
+            if let Some(value) = std::env::var_os(name) {
+                cmd.env(name, value);
+            }
+        }
+        cmd.arg("-c")
+            .arg(&self.config.command)
+            .current_dir(workspace)
+            .env("YUI_JOB_ID", input.job_id.to_string())
+            .env("YUI_WORKSPACE", workspace)
+            .env("YUI_PROMPT_PATH", &prompt_path)
+            .env("YUI_ATTACHMENTS_JSON", attachments_json)
+            .env("YUI_MAX_TURNS", input.limits.max_turns.to_string())
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .process_group(0)
+            .kill_on_drop(true);
+        if let Some(ref session_id) = input.session_id {
+            cmd.env("YUI_SESSION_ID", session_id);
+        }
+        if resume_session {
+            cmd.env("YUI_RESUME_SESSION", "1");
+        }
+        if let Some(ref token) = input.agent_token {
+            cmd.env("YUI_API_URL", &self.config.agent_api_url)
+                .env("YUI_API_TOKEN", token);
+        }
+        for secret in &input.secrets {
+            if matches!(secret.inject_as, SecretInjection::Env) {
+                cmd.env(&secret.name, &secret.value);
+            }
+        }
+        if let Some(dir) = secrets_dir {
+            cmd.env("YUI_SECRETS_DIR", dir);
+        }
+        if let Some(project) = project {
+            cmd.env("YUI_PROJECT_DIR", project);
+        }
+        // a host process is not confined to the proxy, it is only pointed at it
+        let proxy_url = egress_config().local_proxy_url();
+        cmd.envs(
+            input
+                .egress
+                .proxy_env(&proxy_url, input.agent_token.as_deref()),
+        );
+        Ok(cmd.spawn()?)
+    }
+}
+
+#[async_trait::async_trait]
+impl AgentRunnerService for ProcessAgentRunner {
+    fn backend(&self) -> &'static str {
+        "process"
+    }
+
+    async fn start(&self, input: RunnerStartInput) -> anyhow::Result<RunnerHandle> {
+        let workspace = PathBuf::from(&self.config.workspace_dir).join(input.job_id.to_string());
+        tokio::fs::create_dir_all(&workspace).await?;
+        let workspace = workspace.canonicalize()?;
+        let project = match input.project_dir {
+            Some(ref project) => Some(prepare_project(project).await?),
+            None => None,
+        };
+
+        // same rule as the container: a resumed session only needs the answer
+        let prompt = match input.resume_input {
+            Some(ref answer) if input.session_id.is_some() => answer.clone(),
+            _ => input.prompt_with_resume(),
+        };
+        tokio::fs::write(workspace.join("prompt.txt"), prompt).await?;
+
+        let secrets_dir = write_secret_files(&input).await?;
+        let spawned = self.spawn(
+            &input,
+            &workspace,
+            project.as_deref(),
+            secrets_dir.as_deref(),
+        );
+        let mut child = match spawned {
+            Ok(child) => child,
+            Err(e) => {
+                if let Some(ref dir) = secrets_dir {
+                    let _ = tokio::fs::remove_dir_all(dir).await;
+                }
+                return Err(e);
+            }
+        };
+        let pid = child.id();
+        let handle = RunnerHandle {
+            run_id: Uuid::new_v4(),
+            job_id: input.job_id,
+            container_id: None,
+        };
+
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        if let Some(stderr) = child.stderr.take() {
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(stderr).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    let _ = tx.send(RunnerEvent::Stderr(line));
+                }
+            });
+        }
+        let config = self.config.clone();
+        tokio::spawn(async move {
+            let outcome =
+                follow_process(&mut child, &config, &workspace, project.as_deref(), &tx).await;
+            if let Some(pid) = pid {
+                // reaping the agent as it exits keeps its group from looking
+                // alive for the whole grace period
+                let _ = tokio::join!(kill_group(pid), child.wait());
+            }
+            if let Some(dir) = secrets_dir {
+                let _ = tokio::fs::remove_dir_all(dir).await;
+            }
+            let _ = tx.send(outcome);
+        });
+
+        self.runs
+            .lock()
+            .unwrap()
+            .insert(handle.run_id, ProcessRun { events: rx, pid });
+        Ok(handle)
+    }
+
+    async fn poll(&self, handle: &RunnerHandle) -> anyhow::Result<Vec<RunnerEvent>> {
+        let mut runs = self.runs.lock().unwrap();
+        let Some(run) = runs.get_mut(&handle.run_id) else {
+            return Ok(vec![]);
+        };
+
+        let mut events = vec![];
+        while let Ok(event) = run.events.try_recv() {
+            let terminal = matches!(
+                event,
+                RunnerEvent::Completed { .. }
+                    | RunnerEvent::AskUser { .. }
+                    | RunnerEvent::Failed { .. }
+            );
+            events.push(event);
+            if terminal {
+                runs.remove(&handle.run_id);
+                break;
+            }
+        }
+        Ok(events)
+    }
+
+    async fn cancel(&self, handle: &RunnerHandle) -> anyhow::Result<()> {
+        let run = self.runs.lock().unwrap().remove(&handle.run_id);
+        if let Some(pid) = run.and_then(|run| run.pid) {
+            kill_group(pid).await;
+        }
+        Ok(())
+    }
+}
+
+/// Writes `file` secrets to a private temp directory outside the workspace,
+/// so they are never collected as outputs. Removed when the process exits.
+async fn write_secret_files(input: &RunnerStartInput) -> anyhow::Result<Option<PathBuf>> {
+    let files: Vec<_> = input
+        .secrets
+        .iter()
+        .filter(|s| matches!(s.inject_as, SecretInjection::File))
+        .collect();
+    if files.is_empty() {
+        return Ok(None);
+    }
+    let dir = std::env::temp_dir().join(format!("yui-secrets-{}", input.job_id));
+    create_private_dir(&dir).await?;
+    for secret in files {
+        write_private(&dir.join(&secret.name), &secret.value).await?;
+    }
+    Ok(Some(dir))
+}
+
+/// Reads protocol frames until the agent finishes, asks a question, fails or
+/// goes quiet, and returns the terminal event. Everything else is forwarded
+/// as it arrives.
+async fn follow_process(
+    child: &mut Child,
+    config: &ProcessRunnerConfig,
+    workspace: &Path,
+    project: Option<&Path>,
+    tx: &UnboundedSender<RunnerEvent>,
+) -> RunnerEvent {
+    let Some(stdout) = child.stdout.take() else {
+        return RunnerEvent::Failed {
+            error: "agent stdout not captured".to_string(),
+            retryable: false,
+        };
+    };
+    let mut lines = BufReader::new(stdout).lines();
+    let idle_timeout = std::time::Duration::from_secs(config.idle_timeout_secs);
+    let mut final_frame = None;
+
+    loop {
+        let line = match tokio::time::timeout(idle_timeout, lines.next_line()).await {
+            Ok(Ok(Some(line))) => line,
+            Ok(Ok(None)) => break,
+            Ok(Err(e)) => {
+                return RunnerEvent::Failed {
+                    error: format!("failed to read agent output: {e}"),
+                    retryable: true,
+                };
+            }
+            Err(_) => {
+                return RunnerEvent::Failed {
+                    error: format!("agent idle timeout after {}s", config.idle_timeout_secs),
+                    retryable: true,
+                };
+            }
+        };
+
+        let event = match serde_json::from_str::<ContainerFrame>(&line) {
+            Ok(ContainerFrame::Session { session_id }) => RunnerEvent::Session { session_id },
+            Ok(ContainerFrame::Log { stream, line }) if stream == "stderr" => {
+                RunnerEvent::Stderr(line)
+            }
+            Ok(ContainerFrame::Log { line, .. }) => RunnerEvent::Stdout(line),
+            Ok(ContainerFrame::Step {
+                tool,
+                input,
+                output,
+                duration_ms,
+            }) => RunnerEvent::Step(StepReport {
+                tool_name: tool,
+                input_summary: input,
+                output_summary: output,
+                duration_ms,
+            }),
+            Ok(ContainerFrame::Progress { message }) => RunnerEvent::Progress { message },
+            Ok(ContainerFrame::AskUser { question }) => return RunnerEvent::AskUser { question },
+            Ok(ContainerFrame::Error { message, retryable }) => {
+                return RunnerEvent::Failed {
+                    error: message,
+                    retryable,
+                };
+            }
+            Ok(ContainerFrame::Final {
+                output,
+                attachments,
+            }) => {
+                final_frame = Some((output, attachments));
+                continue;
+            }
+            Err(_) => RunnerEvent::Stdout(line),
+        };
+        let _ = tx.send(event);
+    }
+
+    let status = child.wait().await;
+    match (final_frame, status) {
+        (Some((output, attachments)), _) => RunnerEvent::Completed {
+            output,
+            attachments: collect_outputs(config, workspace, project, &attachments).await,
+        },
+        (None, Ok(status)) => RunnerEvent::Failed {
+            error: format!("agent exited with {status} without a result"),
+            retryable: false,
+        },
+        (None, Err(e)) => RunnerEvent::Failed {
+            error: format!("failed to wait for agent: {e}"),
+            retryable: true,
+        },
+    }
+}
+
+/// Copies files the agent reported into the media dir so they survive the
+/// workspace. Relative paths are resolved against the workspace, and those
+/// under `project/` against the persistent workspace. Whatever they resolve
+/// to has to stay inside one of the two.
+async fn collect_outputs(
+    config: &ProcessRunnerConfig,
+    workspace: &Path,
+    project: Option<&Path>,
+    attachments: &[serde_json::Value],
+) -> Vec<serde_json::Value> {
+    if let Err(e) = tokio::fs::create_dir_all(&config.media_dir).await {
+        tracing::warn!(error = %e, "failed to create media dir");
+        return vec![];
+    }
+
+    let roots: Vec<&Path> = std::iter::once(workspace).chain(project).collect();
+    let mut collected = vec![];
+    for raw in attachments {
+        let Ok(att) = serde_json::from_value::<Attachment>(raw.clone()) else {
+            continue;
+        };
+        let source = match project.zip(Path::new(&att.path).strip_prefix(PROJECT_MOUNT).ok()) {
+            Some((project, rest)) => project.join(rest),
+            None => workspace.join(&att.path),
+        };
+        let name = att
+            .name
+            .clone()
+            .or_else(|| source.file_name().map(|n| n.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| "file".to_string());
+        let dest = format!("{}/{}_{name}", config.media_dir, Uuid::new_v4().as_simple());
+        match copy_agent_file(&roots, &source, Path::new(&dest)).await {
+            Ok(_) => collected.push(serde_json::json!({
+                "type": att.kind,
+                "path": dest,
+                "name": name,
+                "mime": att.mime,
+            })),
+            Err(e) => {
+                tracing::warn!(path = %source.display(), error = %e, "failed to copy agent output")
+            }
+        }
+    }
+    collected
+}
+
+/// Asks the agent's process group to stop, and kills whatever is left of it
+/// once `KILL_GRACE` has passed.
+async fn kill_group(pid: u32) {
+    let Ok(group) = i32::try_from(pid) else {
+        return;
+    };
+    // SAFETY: kill(2) only sends a signal, to the group the agent leads
+    let signal = |signal| unsafe { libc::kill(-group, signal) } == 0;
+    if !signal(libc::SIGTERM) {
+        return;
+    }
+    let deadline = tokio::time::Instant::now() + KILL_GRACE;
+    while tokio::time::Instant::now() < deadline {
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        if !signal(0) {
+            return;
+        }
+    }
+    signal(libc::SIGKILL);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::{SecretValue, resource_profile};
+
+    fn runner(command: &str, workspace_dir: &Path) -> ProcessAgentRunner {
+        ProcessAgentRunner::new(ProcessRunnerConfig {
+            command: command.to_string(),
+            workspace_dir: workspace_dir.display().to_string(),
+            media_dir: workspace_dir.join("media").display().to_string(),
+            idle_timeout_secs: 5,
+            agent_api_url: "http://127.0.0.1:8090".to_string(),
+            env_passthrough: vec![],
+        })
+    }
+
+    fn start_input(prompt: &str) -> RunnerStartInput {
+        RunnerStartInput {
+            job_id: Uuid::new_v4(),
+            prompt: prompt.to_string(),
+            session_id: None,
+            resume_input: None,
+            attachments: vec![],
+            limits: resource_profile(None).1,
+            agent_token: None,
+            secrets: vec![],
+            project_dir: None,
+            egress: Default::default(),
+        }
+    }
+
+    async fn run_to_end(runner: &ProcessAgentRunner, handle: &RunnerHandle) -> Vec<RunnerEvent> {
+        let mut events = vec![];
+        for _ in 0..100 {
+            events.extend(runner.poll(handle).await.unwrap());
+            if matches!(
+                events.last(),
+                Some(
+                    RunnerEvent::Completed { .. }
+                        | RunnerEvent::AskUser { .. }
+                        | RunnerEvent::Failed { .. }
+                )
+            ) {
+                return events;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        panic!("agent did not finish: {events:?}");
+    }
+
+    #[tokio::test]
+    async fn scripted_agent_speaks_the_container_protocol() {
+        let dir = std::env::temp_dir().join(format!("yui-process-{}", Uuid::new_v4()));
+        let script = r#"
+            echo '{"type":"session","session_id":"s1"}'
+            echo '{"type":"progress","message":"reading the prompt"}'
+            echo "plain output"
+            echo "prompt was: $(cat "$YUI_PROMPT_PATH")"
+            printf '{"type":"final","output":"done","attachments":[]}\n'
+        "#;
+        let runner = runner(script, &dir);
+
+        let handle = runner.start(start_input("count to three")).await.unwrap();
+        let events = run_to_end(&runner, &handle).await;
+
+        assert!(matches!(&events[0], RunnerEvent::Session { session_id } if session_id == "s1"));
+        assert!(events.iter().any(
+            |e| matches!(e, RunnerEvent::Progress { message } if message == "reading the prompt")
+        ));
+        assert!(events.iter().any(
+            |e| matches!(e, RunnerEvent::Stdout(line) if line == "prompt was: count to three")
+        ));
+        assert!(matches!(
+            events.last(),
+            Some(RunnerEvent::Completed { output, .. }) if output == "done"
+        ));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn agent_gets_only_its_own_environment() {
+        let dir = std::env::temp_dir().join(format!("yui-process-{}", Uuid::new_v4()));
+        let runner = runner(
+            r#"env; printf '{"type":"final","output":"done","attachments":[]}\n'"#,
+            &dir,
+        );
+        let mut input = start_input("list the environment");
+        input.secrets = vec![SecretValue {
+            name: "GITHUB_TOKEN".to_string(),
+            value: "ghp_abcdef".to_string(),
+            inject_as: SecretInjection::Env,
+        }];
+
+        let handle = runner.start(input).await.unwrap();
+        let events = run_to_end(&runner, &handle).await;
+
+        let names: Vec<&str> = events
+            .iter()
+            .filter_map(|e| match e {
+                RunnerEvent::Stdout(line) => line.split_once('=').map(|(name, _)| name),
+                _ => None,
+            })
+            .collect();
+        assert!(names.contains(&"GITHUB_TOKEN"));
+        assert!(names.contains(&"YUI_PROMPT_PATH"));
+        // the shell sets these itself
+        let shell_vars = ["PWD", "OLDPWD", "SHLVL", "_"];
+        for (name, _) in std::env::vars() {
+            if !INHERITED_ENV.contains(&name.as_str())
+                && !shell_vars.contains(&name.as_str())
+                && !name.starts_with("YUI_")
+            {
+                assert!(
+                    !names.contains(&name.as_str()),
+                    "{name} leaked to the agent"
+                );
+            }
+        }
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn agent_exiting_without_a_result_fails() {
+        let dir = std::env::temp_dir().join(format!("yui-process-{}", Uuid::new_v4()));
+        let runner = runner("echo working; exit 3", &dir);
+
+        let handle = runner.start(start_input("anything")).await.unwrap();
+        let events = run_to_end(&runner, &handle).await;
+
+        assert!(matches!(
+            events.last(),
+            Some(RunnerEvent::Failed {
+                retryable: false,
+                ..
+            })
+        ));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn outputs_that_link_out_of_the_workspace_are_dropped() {
+        let dir = std::env::temp_dir().join(format!("yui-process-{}", Uuid::new_v4()));
+        let script = r#"
+            echo "chart" > chart.txt
+            ln -s /etc/hostname leak.txt
+            printf '{"type":"final","output":"done","attachments":[%s,%s,%s]}\n' \
+                '{"type":"document","path":"chart.txt","mime":"text/plain"}' \
+                '{"type":"document","path":"leak.txt","mime":"text/plain"}' \
+                '{"type":"document","path":"/etc/hostname","mime":"text/plain"}'
+        "#;
+        let runner = runner(script, &dir);
+
+        let handle = runner.start(start_input("draw")).await.unwrap();
+        let events = run_to_end(&runner, &handle).await;
+
+        let Some(RunnerEvent::Completed { attachments, .. }) = events.last() else {
+            panic!("expected a result, got {events:?}");
+        };
+        assert_eq!(attachments.len(), 1);
+        assert_eq!(attachments[0]["name"], "chart.txt");
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn cancel_stops_the_process_group() {
+        let dir = std::env::temp_dir().join(format!("yui-process-{}", Uuid::new_v4()));
+        // an agent that ignores SIGTERM is killed once the grace period is up
+        let runner = runner("trap '' TERM; echo ready; sleep 30 & wait", &dir);
+
+        let handle = runner.start(start_input("wait")).await.unwrap();
+        let pid = runner.runs.lock().unwrap()[&handle.run_id].pid.unwrap();
+        let alive = || unsafe { libc::kill(pid as i32, 0) } == 0;
+        let mut events = vec![];
+        while events.is_empty() {
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            events = runner.poll(&handle).await.unwrap();
+        }
+        assert!(matches!(&events[0], RunnerEvent::Stdout(line) if line == "ready"));
+        runner.cancel(&handle).await.unwrap();
+
+        for _ in 0..20 {
+            if !alive() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        assert!(!alive(), "agent {pid} survived cancel");
+        assert!(runner.poll(&handle).await.unwrap().is_empty());
+        assert!(runner.runs.lock().unwrap().is_empty());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+
+/// Limits one agent run is held to. Recorded on the job row when it starts.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ResourceLimits {
+    /// Wall-clock budget for the whole run, enforced by the runtime.
+    pub max_wall_secs: i32,
+    pub memory_mb: i32,
+    pub cpus: f64,
+    pub disk_mb: i32,
+    /// Passed to the agent as `YUI_MAX_TURNS`.
+    pub max_turns: i32,
+}
+
+/// Named limit sets triage can pick from with a `profile` hint.
+pub const RESOURCE_PROFILES: &[(&str, ResourceLimits)] = &[
+    (
+        "light",
+        ResourceLimits {
+            max_wall_secs: 5 * 60,
+            memory_mb: 1024,
+            cpus: 1.0,
+            disk_mb: 1024,
+            max_turns: 5,
+        },
+    ),
+    (
+        "standard",
+        ResourceLimits {
+            max_wall_secs: 30 * 60,
+            memory_mb: 2048,
+            cpus: 2.0,
+            disk_mb: 5 * 1024,
+            max_turns: 10,
+        },
+    ),
+    (
+        "heavy",
+        ResourceLimits {
+            max_wall_secs: 2 * 60 * 60,
+            memory_mb: 4096,
+            cpus: 4.0,
+            disk_mb: 20 * 1024,
+            max_turns: 30,
+        },
+    ),
+];
+
+/// The profile for jobs without a hint. `light` is only ever picked on
+/// purpose, so quick-looking jobs aren't cut short by its tighter limits.
+const DEFAULT_PROFILE: &str = "standard";
+
+/// Picks the hinted profile when it names one, otherwise `standard`.
+pub fn resource_profile(hint: Option<&str>) -> (&'static str, ResourceLimits) {
+    profile_for(hint, "", &[])
+}
+
+/// Picks the hinted profile when it names one, then the one
+/// `YUI_PROFILE_BY_KIND` sets for the job's kind, otherwise `standard`.
+pub fn job_profile(hint: Option<&str>, kind: &str) -> (&'static str, ResourceLimits) {
+    static KIND_PROFILES: LazyLock<Vec<(String, String)>> = LazyLock::new(|| {
+        parse_kind_profiles(&std::env::var("YUI_PROFILE_BY_KIND").unwrap_or_default())
+    });
+    profile_for(hint, kind, &KIND_PROFILES)
+}
+
+/// `kind=profile` pairs such as `research=heavy,reminder=light`; entries that
+/// name no profile are dropped.
+fn parse_kind_profiles(spec: &str) -> Vec<(String, String)> {
+    spec.split(',')
+        .filter_map(|entry| {
+            let (kind, profile) = entry.split_once('=')?;
+            let profile = profile.trim();
+            RESOURCE_PROFILES
+                .iter()
+                .any(|(name, _)| *name == profile)
+                .then(|| (kind.trim().to_string(), profile.to_string()))
+        })
+        .collect()
+}
+
+fn profile_for(
+    hint: Option<&str>,
+    kind: &str,
+    kind_profiles: &[(String, String)],
+) -> (&'static str, ResourceLimits) {
+    let by_name = |name: &str| RESOURCE_PROFILES.iter().find(|(n, _)| *n == name).copied();
+    let by_kind = || {
+        kind_profiles
+            .iter()
+            .find(|(k, _)| k == kind)
+            .and_then(|(_, profile)| by_name(profile))
+    };
+    hint.and_then(by_name)
+        .or_else(by_kind)
+        .unwrap_or_else(|| by_name(DEFAULT_PROFILE).expect("built-in profile"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_hint_wins() {
+        let (name, limits) = resource_profile(Some("heavy"));
+        assert_eq!(name, "heavy");
+        assert_eq!(limits.max_turns, 30);
+        assert_eq!(resource_profile(Some("light")).0, "light");
+    }
+
+    #[test]
+    fn unknown_or_missing_hint_falls_back_to_standard() {
+        assert_eq!(resource_profile(Some("huge")).0, "standard");
+        assert_eq!(resource_profile(None).0, "standard");
+    }
+
+    #[test]
+    fn kind_profile_applies_without_a_hint() {
+        let kinds = parse_kind_profiles("research=heavy, reminder = light,chat=huge,broken");
+        assert_eq!(
+            kinds,
+            vec![
+                ("research".to_string(), "heavy".to_string()),
+                ("reminder".to_string(), "light".to_string()),
+            ]
+        );
+        assert_eq!(profile_for(None, "research", &kinds).0, "heavy");
+        assert_eq!(profile_for(Some("bogus"), "reminder", &kinds).0, "light");
+        assert_eq!(
+            profile_for(Some("standard"), "research", &kinds).0,
+            "standard"
+        );
+        assert_eq!(profile_for(None, "chat", &kinds).0, "standard");
+    }
+}
@@ -0,0 +1,248 @@
+use crate::schema::SecretInjection;
+use crate::services::agent_runner::{RunnerEvent, StepReport};
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use tokio::io::AsyncWriteExt;
+
+/// Secrets shorter than this are not redacted; replacing every "1" or "yes"
+/// in the output would do more harm than good.
+const MIN_REDACTED_LEN: usize = 4;
+
+/// A decrypted secret on its way into one run.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SecretValue {
+    pub name: String,
+    pub value: String,
+    pub inject_as: SecretInjection,
+}
+
+impl std::fmt::Debug for SecretValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SecretValue")
+            .field("name", &self.name)
+            .field("inject_as", &self.inject_as)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Bytes of the random nonce stored in front of each sealed value.
+const NONCE_LEN: usize = 12;
+
+/// The vault key, from `YUI_SECRETS_KEY`. Values are sealed and opened in
+/// this process with AES-256-GCM, so neither the key nor a plaintext value is
+/// ever part of a query.
+#[derive(Clone)]
+pub struct SecretsKey(Aes256Gcm);
+
+impl SecretsKey {
+    /// The key is the SHA-256 of the passphrase, which is expected to be long
+    /// and random rather than something memorable.
+    pub fn new(passphrase: &str) -> Self {
+        Self(Aes256Gcm::new(&Sha256::digest(passphrase.as_bytes())))
+    }
+
+    /// A fresh nonce followed by the ciphertext, for `secrets.value_sealed`.
+    pub fn seal(&self, value: &str) -> Vec<u8> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .0
+            .encrypt(&nonce, value.as_bytes())
+            .expect("AES-GCM encryption of an in-memory value");
+        [nonce.as_slice(), &ciphertext].concat()
+    }
+
+    /// `None` when the value was sealed under another key or was tampered with.
+    pub fn open(&self, sealed: &[u8]) -> Option<String> {
+        if sealed.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let plaintext = self.0.decrypt(Nonce::from_slice(nonce), ciphertext).ok()?;
+        String::from_utf8(plaintext).ok()
+    }
+}
+
+/// The vault key, if one is configured. Without it the vault is read-only and
+/// runs get no secrets.
+pub fn secrets_key() -> Option<SecretsKey> {
+    std::env::var("YUI_SECRETS_KEY")
+        .ok()
+        .filter(|key| !key.is_empty())
+        .map(|key| SecretsKey::new(&key))
+}
+
+/// Lines for `docker run --env-file`. Values with a newline cannot be written
+/// in that format and are left to file injection.
+pub fn env_file(secrets: &[SecretValue]) -> String {
+    secrets
+        .iter()
+        .filter(|s| matches!(s.inject_as, SecretInjection::Env) && !s.value.contains('\n'))
+        .map(|s| format!("{}={}\n", s.name, s.value))
+        .collect()
+}
+
+/// Creates a directory for secret material that only this user can enter.
+pub async fn create_private_dir(path: &Path) -> std::io::Result<()> {
+    tokio::fs::create_dir_all(path).await?;
+    tokio::fs::set_permissions(path, std::fs::Permissions::from_mode(0o700)).await
+}
+
+/// Writes secret material only this user can read. The mode is set as the
+/// file is created, so the value is never on disk with wider access.
+pub async fn write_private(path: &Path, contents: &str) -> std::io::Result<()> {
+    let mut file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)
+        .await?;
+    // an earlier file at the same path keeps its own mode
+    file.set_permissions(std::fs::Permissions::from_mode(0o600))
+        .await?;
+    file.write_all(contents.as_bytes()).await?;
+    file.flush().await
+}
+
+/// Replaces secret values in anything a run reports before it reaches logs,
+/// the dashboard or the chat.
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    /// (value, replacement), longest value first so overlapping secrets
+    /// redact whole.
+    values: Vec<(String, String)>,
+}
+
+impl Redactor {
+    pub fn new(secrets: &[SecretValue]) -> Self {
+        let mut values: Vec<(String, String)> = secrets
+            .iter()
+            .filter(|s| s.value.len() >= MIN_REDACTED_LEN)
+            .map(|s| (s.value.clone(), format!("[redacted:{}]", s.name)))
+            .collect();
+        values.sort_by_key(|(value, _)| std::cmp::Reverse(value.len()));
+        Self { values }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn redact(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for (value, replacement) in &self.values {
+            if text.contains(value.as_str()) {
+                text = text.replace(value.as_str(), replacement);
+            }
+        }
+        text
+    }
+
+    pub fn redact_event(&self, event: RunnerEvent) -> RunnerEvent {
+        if self.is_empty() {
+            return event;
+        }
+        let redact_opt = |text: Option<String>| text.map(|t| self.redact(&t));
+        match event {
+            RunnerEvent::Stdout(line) => RunnerEvent::Stdout(self.redact(&line)),
+            RunnerEvent::Stderr(line) => RunnerEvent::Stderr(self.redact(&line)),
+            RunnerEvent::Step(step) => RunnerEvent::Step(StepReport {
+                tool_name: step.tool_name,
+                input_summary: redact_opt(step.input_summary),
+                output_summary: redact_opt(step.output_summary),
+                duration_ms: step.duration_ms,
+            }),
+            RunnerEvent::Progress { message } => RunnerEvent::Progress {
+                message: self.redact(&message),
+            },
+            RunnerEvent::AskUser { question } => RunnerEvent::AskUser {
+                question: self.redact(&question),
+            },
+            RunnerEvent::Completed {
+                output,
+                attachments,
+            } => RunnerEvent::Completed {
+                output: self.redact(&output),
+                attachments,
+            },
+            RunnerEvent::Failed { error, retryable } => RunnerEvent::Failed {
+                error: self.redact(&error),
+                retryable,
+            },
+            event @ (RunnerEvent::Session { .. } | RunnerEvent::Seen(_)) => event,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secret(name: &str, value: &str, inject_as: SecretInjection) -> SecretValue {
+        SecretValue {
+            name: name.to_string(),
+            value: value.to_string(),
+            inject_as,
+        }
+    }
+
+    #[test]
+    fn redacts_values_longest_first_and_skips_short_ones() {
+        let redactor = Redactor::new(&[
+            secret("SHORT", "abc", SecretInjection::Env),
+            secret("TOKEN", "ghp_abcdef", SecretInjection::Env),
+            secret("TOKEN_PREFIX", "ghp_", SecretInjection::Env),
+        ]);
+
+        assert_eq!(
+            redactor.redact("pushed with ghp_abcdef, prefix ghp_ and abc"),
+            "pushed with [redacted:TOKEN], prefix [redacted:TOKEN_PREFIX] and abc"
+        );
+    }
+
+    #[test]
+    fn redacts_completed_output() {
+        let redactor = Redactor::new(&[secret("API_KEY", "sk-live-123", SecretInjection::File)]);
+        let event = redactor.redact_event(RunnerEvent::Completed {
+            output: "used sk-live-123".to_string(),
+            attachments: vec![],
+        });
+
+        assert!(matches!(
+            event,
+            RunnerEvent::Completed { output, .. } if output == "used [redacted:API_KEY]"
+        ));
+    }
+
+    #[test]
+    fn sealed_values_open_only_under_their_key() {
+        let key = SecretsKey::new("correct horse battery staple");
+        let sealed = key.seal("ghp_abcdef");
+
+        assert!(!sealed.windows(10).any(|w| w == b"ghp_abcdef"));
+        assert_ne!(sealed, key.seal("ghp_abcdef"));
+        assert_eq!(key.open(&sealed).as_deref(), Some("ghp_abcdef"));
+        assert_eq!(SecretsKey::new("another key").open(&sealed), None);
+
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert_eq!(key.open(&tampered), None);
+        assert_eq!(key.open(&sealed[..4]), None);
+    }
+
+    #[test]
+    fn env_file_holds_single_line_env_secrets_only() {
+        let secrets = [
+            secret("GITHUB_TOKEN", "ghp_abcdef", SecretInjection::Env),
+            secret("SSH_KEY", "-----BEGIN KEY-----", SecretInjection::File),
+            secret("MULTI", "a\nb", SecretInjection::Env),
+        ];
+
+        assert_eq!(env_file(&secrets), "GITHUB_TOKEN=ghp_abcdef\n");
+        assert!(!format!("{:?}", secrets[0]).contains("ghp_abcdef"));
+    }
+}
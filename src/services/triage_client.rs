@@ -1,4 +1,5 @@
-use crate::services::ai::{TriageBatchDecision, TriageBatchInput, TriageDecision};
+use crate::services::ai::{PlanStep, TriageBatchDecision, TriageBatchInput, TriageDecision};
+use crate::services::metrics;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -132,6 +133,36 @@ struct LlmDecision {
     input: Option<String>,
     #[serde(default)]
     enabled: Option<bool>,
+    #[serde(default)]
+    notify: Option<String>,
+    #[serde(default)]
+    profile: Option<String>,
+    #[serde(default)]
+    steps: Vec<LlmPlanStep>,
+    #[serde(default)]
+    artifact_id: Option<String>,
+    #[serde(default)]
+    workspace: Option<String>,
+    #[serde(default)]
+    approval: Option<String>,
+    #[serde(default)]
+    approved: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LlmPlanStep {
+    #[serde(default)]
+    prompt: String,
+    #[serde(default)]
+    kind: Option<String>,
+    #[serde(default)]
+    profile: Option<String>,
+    #[serde(default)]
+    workspace: Option<String>,
+    #[serde(default)]
+    approval: Option<String>,
+    #[serde(default)]
+    after: Vec<usize>,
 }
 
 impl TriageClient {
@@ -181,7 +212,9 @@ impl TriageClient {
         let mut last_error = None;
 
         for attempt in 0..=MAX_RETRIES {
-            match self.send_request(&request).await {
+            let result = self.send_request(&request).await;
+            metrics::record_llm_call("triage", result.is_ok());
+            match result {
                 Ok(message) => {
                     if let Some(tool_result) = parse_tool_call_result(&message) {
                         if let Some(decisions) = handle_parse_attempt(
@@ -378,11 +411,15 @@ fn triage_tool_definition() -> ToolDefinition {
                                     "enum": [
                                         "reply",
                                         "create_job",
+                                        "create_plan",
                                         "create_cron",
                                         "cancel_job",
                                         "cancel_cron",
                                         "resume_job",
+                                        "approve_job",
                                         "set_subscription",
+                                        "resend_file",
+                                        "reset_workspace",
                                         "noop"
                                     ]
                                 },
@@ -392,9 +429,43 @@ fn triage_tool_definition() -> ToolDefinition {
                                 "name": { "type": "string" },
                                 "schedule": { "type": "string" },
                                 "job_id": { "type": "string" },
+                                "artifact_id": { "type": "string" },
+                                "workspace": { "type": "string" },
+                                "approval": { "type": "string" },
+                                "approved": { "type": "boolean" },
                                 "reason": { "type": "string" },
                                 "input": { "type": "string" },
-                                "enabled": { "type": "boolean" }
+                                "enabled": { "type": "boolean" },
+                                "notify": {
+                                    "type": "string",
+                                    "enum": ["always", "on_change", "noteworthy"]
+                                },
+                                "profile": {
+                                    "type": "string",
+                                    "enum": ["light", "standard", "heavy"]
+                                },
+                                "steps": {
+                                    "type": "array",
+                                    "items": {
+                                        "type": "object",
+                                        "properties": {
+                                            "prompt": { "type": "string" },
+                                            "kind": { "type": "string" },
+                                            "profile": {
+                                                "type": "string",
+                                                "enum": ["light", "standard", "heavy"]
+                                            },
+                                            "workspace": { "type": "string" },
+                                            "approval": { "type": "string" },
+                                            "after": {
+                                                "type": "array",
+                                                "items": { "type": "integer", "minimum": 0 }
+                                            }
+                                        },
+                                        "required": ["prompt"],
+                                        "additionalProperties": false
+                                    }
+                                }
                             },
                             "required": ["action"],
                             "additionalProperties": false
@@ -455,12 +526,16 @@ fn build_system_prompt() -> String {
 
 Each decision must be one of:
 - {"action":"reply","text":"..."} - send a chat reply directly
-- {"action":"create_job","prompt":"...","kind":"action"} - create a new background task
-- {"action":"create_cron","name":"short_name","schedule":"cron_expr","prompt":"..."} - schedule recurring task
+- {"action":"create_job","prompt":"...","kind":"action","profile":"light|standard|heavy","workspace":"project-name","approval":"..."} - create a new background task
+- {"action":"create_plan","steps":[{"prompt":"...","kind":"action","profile":"...","after":[0]}]} - create several tasks where later ones wait for earlier ones
+- {"action":"create_cron","name":"short_name","schedule":"cron_expr","prompt":"...","notify":"always|on_change|noteworthy"} - schedule recurring task
 - {"action":"cancel_job","job_id":"uuid","reason":"..."} - cancel an active job
 - {"action":"cancel_cron","name":"..."} - cancel a scheduled task
 - {"action":"resume_job","job_id":"uuid","input":"..."} - resume a paused job with user input
+- {"action":"approve_job","job_id":"uuid","approved":true|false} - answer for a job that is awaiting_approval
 - {"action":"set_subscription","enabled":true|false} - toggle subscription
+- {"action":"resend_file","artifact_id":"uuid"} - send a file from "Recent files" again
+- {"action":"reset_workspace","name":"..."} - empty a persistent workspace from "Workspaces"
 - {"action":"noop"} - do nothing
 
 Rules:
@@ -473,14 +548,26 @@ Rules:
 7. If the user wants to unsubscribe/subscribe, use set_subscription.
 8. CANCEL CRON: When cancelling a cron, use the EXACT name from the "Active crons" list. Match user intent to the closest cron name.
 9. CONTEXT RECALL: If the user asks "what did I say" or "what was the token" or similar recall questions, look at the conversation history provided and reply directly with the exact information. The history section contains previous messages for this chat.
-10. ATTACHMENTS: If a message has [audio] marker, the user sent a voice note. Create an action job with prompt that mentions transcribing the audio and executing any tasks mentioned. If a message has [image] marker, create an action job for image analysis.
+10. CRON NOTIFY: For watcher-style crons ("check if the site is up", "tell me if the price drops", "let me know when X changes") set notify="on_change" when the user only cares about differences, or notify="noteworthy" when they only want to hear about specific conditions. Reminders and periodic reports use notify="always" (the default).
+11. ATTACHMENTS: If a message has [audio] marker, the user sent a voice note. Create an action job with prompt that mentions transcribing the audio and executing any tasks mentioned. If a message has [image] marker, create an action job for image analysis.
+12. JOB PROFILE: Optional on create_job. Use profile="light" for a quick lookup (weather, current time, a single web fetch), profile="heavy" for long or resource-hungry work (building or testing a codebase, processing large files or video, deep research across many sources). Omit it otherwise.
+13. STATUS QUESTIONS: If the user asks how a task is going ("how's it going?", "any update?", "is it done yet?"), reply directly from the progress and latest output of the matching active job. Keep it short and plain, never paste raw log lines. If nothing has been reported yet, say it is still working on it.
+14. PLANS: When a request is several distinct tasks and a later one needs the result of an earlier one ("clone the repo and install the dependencies, then run the tests"), use create_plan. Each step is a self-contained prompt; "after" lists the zero-based indexes of earlier steps it waits for, and their results are handed to it. Steps without "after" run in parallel. A single task, even a long one, stays a create_job.
+15. RESEND FILES: If the user asks for a file they already got again ("send me that file again", "resend the report"), use resend_file with the matching id from "Recent files". Do not create a job to regenerate it. If nothing there matches, reply that you can't find it.
+16. WORKSPACES: Jobs start in an empty directory that is thrown away afterwards. Set "workspace" on create_job (or on plan steps) only when the user wants work kept for later tasks ("clone forge-v2 and keep it around", "work on my blog project"), or refers to something an earlier task left in one of the "Workspaces" ("now run its tests"). Use a short project name, and the exact name from "Workspaces" when one matches. Omit it for one-off tasks. If the user asks to wipe or start a project over, use reset_workspace with that name.
+17. APPROVAL: Set "approval" on create_job (or on plan steps) to a few words on why, when the task is destructive, irreversible or reaches beyond this chat: deleting files or data, deploying, pushing to shared branches or production, sending messages or email to other people, spending money ("deletes the test files", "deploys to production"). The user is asked to confirm before it runs. Omit it for everything else.
+18. APPROVAL ANSWERS: A job marked [awaiting_approval] is waiting for a yes or no. If the user says yes, use approve_job with approved=true; if they say no or to cancel it, approved=false. Anything else is routed as usual and the job keeps waiting. Never approve a job the user did not clearly confirm.
 
 EXAMPLES of correct routing:
 - "iss location every minute for 5 mins" -> create_cron name="iss_location" schedule="* * * * *" prompt="Get the current ISS location using the API at http://api.open-notify.org/iss-now.json and report latitude, longitude, and UTC timestamp AUTO_STOP_AFTER=5"
 - "remind me to drink water every hour" -> create_cron schedule="0 * * * *" prompt="Send a reminder to drink water"
+- "check every 5 minutes if example.com is up and tell me if it goes down" -> create_cron name="example_uptime" schedule="*/5 * * * *" prompt="Check whether https://example.com responds with HTTP 200 and report whether it is up or down" notify="on_change"
 - "tell me weather in new york" -> create_job (needs real-time data, use web API)
 - "what time is it" -> create_job (needs current time from system)
 - "clone this repo and count lines" -> create_job
+- "delete all test files in my blog project" -> create_job workspace="blog" approval="deletes files"; then "yes" -> approve_job approved=true
+- "clone forge-v2 and keep it around" -> create_job workspace="forge-v2"; later "now run its tests" -> create_job workspace="forge-v2" prompt="Run the test suite of the forge-v2 checkout and report failures"
+- "clone github.com/acme/api, install its dependencies, then run the test suite" -> create_plan steps=[{"prompt":"Clone https://github.com/acme/api and install its dependencies"},{"prompt":"Run the test suite of the acme/api checkout and report failures","after":[0]}]
 - "what's 2+2" -> reply "4"
 - "remember this token: ALPHA-991" -> reply "got it, saved ALPHA-991"
 - "what token did i ask you to remember?" -> reply with the token from conversation history"#.to_string()
@@ -508,9 +595,43 @@ fn build_user_prompt(input: &TriageBatchInput) -> String {
                 "  - {} [{}]: {}",
                 job.id, job.status, prompt_preview
             ));
+            if let Some(ref progress) = job.progress {
+                parts.push(format!("      progress: {progress}"));
+            }
+            if !job.recent_logs.is_empty() {
+                let lines: Vec<String> = job
+                    .recent_logs
+                    .iter()
+                    .map(|line| line.chars().take(120).collect())
+                    .collect();
+                parts.push(format!("      latest output: {}", lines.join(" | ")));
+            }
+        }
+    }
+
+    if !input.recent_artifacts.is_empty() {
+        parts.push("Recent files:".to_string());
+        for artifact in &input.recent_artifacts {
+            let job_preview = artifact
+                .job_prompt
+                .as_deref()
+                .map(|p| p.chars().take(60).collect::<String>())
+                .unwrap_or_default();
+            parts.push(format!(
+                "  - {} name=\"{}\" mime={} sent={} from: {}",
+                artifact.id,
+                artifact.name,
+                artifact.mime,
+                artifact.created_at.format("%Y-%m-%d %H:%M"),
+                job_preview
+            ));
         }
     }
 
+    if !input.workspaces.is_empty() {
+        parts.push(format!("Workspaces: {}", input.workspaces.join(", ")));
+    }
+
     if !input.active_crons.is_empty() {
         parts.push("Active crons:".to_string());
         for cron in &input.active_crons {
@@ -557,13 +678,37 @@ fn convert_decision(d: LlmDecision) -> anyhow::Result<TriageDecision> {
         "create_job" => Ok(TriageDecision::CreateJob {
             prompt: d.prompt.unwrap_or_default(),
             kind: d.kind.unwrap_or_else(|| "action".to_string()),
+            profile: d.profile,
+            workspace: d.workspace.filter(|name| !name.trim().is_empty()),
+            approval: d.approval.filter(|reason| !reason.trim().is_empty()),
         }),
+        "create_plan" => {
+            let steps: Vec<PlanStep> = d
+                .steps
+                .into_iter()
+                .enumerate()
+                .map(|(i, step)| PlanStep {
+                    prompt: step.prompt,
+                    kind: step.kind.unwrap_or_else(|| "action".to_string()),
+                    profile: step.profile,
+                    workspace: step.workspace.filter(|name| !name.trim().is_empty()),
+                    approval: step.approval.filter(|reason| !reason.trim().is_empty()),
+                    // only earlier steps, so the plan cannot contain a cycle
+                    after: step.after.into_iter().filter(|&dep| dep < i).collect(),
+                })
+                .collect();
+            if steps.is_empty() || steps.iter().any(|step| step.prompt.trim().is_empty()) {
+                anyhow::bail!("create_plan needs steps with prompts");
+            }
+            Ok(TriageDecision::CreatePlan { steps })
+        }
         "create_cron" => Ok(TriageDecision::CreateCron {
             name: d
                 .name
                 .unwrap_or_else(|| format!("cron_{}", Uuid::new_v4().as_simple())),
             schedule: d.schedule.unwrap_or_default(),
             prompt: d.prompt.unwrap_or_default(),
+            notify_policy: d.notify.unwrap_or_else(|| "always".to_string()),
         }),
         "cancel_job" => Ok(TriageDecision::CancelJob {
             job_id: parse_job_id_or_new(d.job_id.as_deref()),
@@ -576,9 +721,33 @@ fn convert_decision(d: LlmDecision) -> anyhow::Result<TriageDecision> {
             job_id: parse_job_id_or_new(d.job_id.as_deref()),
             input: d.input.unwrap_or_default(),
         }),
+        // an unreadable job id or answer must not approve anything
+        "approve_job" => Ok(TriageDecision::ApproveJob {
+            job_id: d
+                .job_id
+                .as_deref()
+                .and_then(|id| id.parse().ok())
+                .ok_or_else(|| anyhow::anyhow!("approve_job needs a job_id"))?,
+            approved: d
+                .approved
+                .ok_or_else(|| anyhow::anyhow!("approve_job needs approved"))?,
+        }),
         "set_subscription" => Ok(TriageDecision::SetSubscription {
             enabled: d.enabled.unwrap_or(true),
         }),
+        "resend_file" => Ok(TriageDecision::ResendArtifact {
+            artifact_id: d
+                .artifact_id
+                .as_deref()
+                .and_then(|id| id.parse().ok())
+                .ok_or_else(|| anyhow::anyhow!("resend_file needs an artifact_id"))?,
+        }),
+        "reset_workspace" => Ok(TriageDecision::ResetWorkspace {
+            name: d
+                .name
+                .filter(|name| !name.trim().is_empty())
+                .ok_or_else(|| anyhow::anyhow!("reset_workspace needs a name"))?,
+        }),
         "noop" => Ok(TriageDecision::Noop),
         other => anyhow::bail!("unknown action: {other}"),
     }
@@ -611,6 +780,9 @@ fn fallback_decision(input: &TriageBatchInput) -> TriageBatchDecision {
         decisions: vec![TriageDecision::CreateJob {
             prompt: combined_text,
             kind: "action".to_string(),
+            profile: None,
+            workspace: None,
+            approval: None,
         }],
     }
 }
@@ -643,6 +815,89 @@ mod tests {
         assert_eq!(result.decisions.len(), 1);
     }
 
+    #[test]
+    fn plan_steps_only_wait_for_earlier_steps() {
+        let json = r#"{"decisions":[{"action":"create_plan","steps":[
+            {"prompt":"clone and install","after":[1]},
+            {"prompt":"run the tests","after":[0, 1, 5],"profile":"heavy"}
+        ]}]}"#;
+        let result = parse_triage_response(json).unwrap();
+        let TriageDecision::CreatePlan { steps } = &result.decisions[0] else {
+            panic!("expected a plan");
+        };
+        assert!(steps[0].after.is_empty());
+        assert_eq!(steps[1].after, vec![0]);
+        assert_eq!(steps[1].kind, "action");
+        assert_eq!(steps[1].profile.as_deref(), Some("heavy"));
+
+        let empty = r#"{"decisions":[{"action":"create_plan","steps":[]}]}"#;
+        assert!(parse_triage_response(empty).unwrap().decisions.is_empty());
+    }
+
+    #[test]
+    fn resend_file_requires_an_artifact_id() {
+        let id = Uuid::new_v4();
+        let json = format!(
+            r#"{{"decisions":[{{"action":"resend_file","artifact_id":"{id}"}},{{"action":"resend_file"}}]}}"#
+        );
+        let result = parse_triage_response(&json).unwrap();
+        assert_eq!(result.decisions.len(), 1);
+        assert!(matches!(
+            result.decisions[0],
+            TriageDecision::ResendArtifact { artifact_id } if artifact_id == id
+        ));
+    }
+
+    #[test]
+    fn workspace_names_are_kept_and_reset_needs_one() {
+        let json = r#"{"decisions":[
+            {"action":"create_job","prompt":"run the tests","workspace":"forge-v2"},
+            {"action":"create_job","prompt":"weather","workspace":" "},
+            {"action":"reset_workspace","name":"forge-v2"},
+            {"action":"reset_workspace"}
+        ]}"#;
+        let result = parse_triage_response(json).unwrap();
+        assert_eq!(result.decisions.len(), 3);
+        assert!(matches!(
+            &result.decisions[0],
+            TriageDecision::CreateJob { workspace: Some(name), .. } if name == "forge-v2"
+        ));
+        assert!(matches!(
+            &result.decisions[1],
+            TriageDecision::CreateJob {
+                workspace: None,
+                ..
+            }
+        ));
+        assert!(matches!(
+            &result.decisions[2],
+            TriageDecision::ResetWorkspace { name } if name == "forge-v2"
+        ));
+    }
+
+    #[test]
+    fn approvals_need_a_job_and_an_answer() {
+        let id = Uuid::new_v4();
+        let json = format!(
+            r#"{{"decisions":[
+                {{"action":"create_job","prompt":"delete the test files","approval":"deletes files"}},
+                {{"action":"approve_job","job_id":"{id}","approved":true}},
+                {{"action":"approve_job","job_id":"{id}"}},
+                {{"action":"approve_job","approved":true}}
+            ]}}"#
+        );
+        let result = parse_triage_response(&json).unwrap();
+        assert_eq!(result.decisions.len(), 2);
+        assert!(matches!(
+            &result.decisions[0],
+            TriageDecision::CreateJob { approval: Some(reason), .. } if reason == "deletes files"
+        ));
+        assert!(matches!(
+            result.decisions[1],
+            TriageDecision::ApproveJob { job_id, approved: true } if job_id == id
+        ));
+    }
+
     #[test]
     fn fallback_creates_job_from_messages() {
         let input = TriageBatchInput {
@@ -657,6 +912,8 @@ mod tests {
             active_jobs: vec![],
             active_crons: vec![],
             history: vec![],
+            recent_artifacts: vec![],
+            workspaces: vec![],
         };
         let result = fallback_decision(&input);
         assert_eq!(result.decisions.len(), 1);
@@ -679,6 +936,8 @@ mod tests {
             active_jobs: vec![],
             active_crons: vec![],
             history: vec![],
+            recent_artifacts: vec![],
+            workspaces: vec![],
         };
         let result = fallback_decision(&input);
         assert!(matches!(&result.decisions[0], TriageDecision::Noop));
@@ -699,14 +958,20 @@ mod tests {
                 id: Uuid::new_v4(),
                 status: "running".to_string(),
                 prompt: Some("existing task".to_string()),
+                progress: Some("cloned the repo, running tests now".to_string()),
+                recent_logs: vec!["test result: ok".to_string()],
             }],
             active_crons: vec![],
             history: vec![],
+            recent_artifacts: vec![],
+            workspaces: vec![],
         };
         let prompt = build_user_prompt(&input);
         assert!(prompt.contains("test_chat"));
         assert!(prompt.contains("running"));
         assert!(prompt.contains("hello"));
+        assert!(prompt.contains("progress: cloned the repo, running tests now"));
+        assert!(prompt.contains("latest output: test result: ok"));
     }
 
     #[test]
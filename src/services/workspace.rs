@@ -0,0 +1,249 @@
+use std::os::unix::fs::MetadataExt;
+use std::path::{Component, Path, PathBuf};
+use uuid::Uuid;
+
+/// Where the persistent workspace appears inside a job's own workspace.
+pub const PROJECT_MOUNT: &str = "project";
+
+/// Where persistent workspaces live on the host, one directory per workspace id.
+pub fn projects_dir() -> PathBuf {
+    PathBuf::from(
+        std::env::var("YUI_PROJECTS_DIR").unwrap_or_else(|_| "storage/projects".to_string()),
+    )
+}
+
+pub fn project_path(workspace_id: Uuid) -> PathBuf {
+    projects_dir().join(workspace_id.to_string())
+}
+
+/// Default quota for new persistent workspaces.
+pub fn workspace_quota_mb() -> i32 {
+    std::env::var("YUI_WORKSPACE_QUOTA_MB")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(2048)
+}
+
+/// Creates the persistent workspace if needed and returns its absolute path.
+/// Runs get it as a bind mount or a path of its own, never as a link in the
+/// job's workspace, which the agent could repoint.
+pub async fn prepare_project(project: &Path) -> std::io::Result<PathBuf> {
+    tokio::fs::create_dir_all(project).await?;
+    tokio::fs::canonicalize(project).await
+}
+
+/// Opens a file whose path came from an agent or was recorded from one. The
+/// agent decides what is on disk, so `path` has to be a regular file, not a
+/// symlink, and has to resolve under one of `roots`. The file that gets opened
+/// is checked to be the one that passed, so it cannot be swapped in between.
+pub async fn open_confined(roots: &[&Path], path: &Path) -> std::io::Result<tokio::fs::File> {
+    let checked = tokio::fs::symlink_metadata(path).await?;
+    if !checked.file_type().is_file() {
+        return Err(refused("not a regular file"));
+    }
+    let resolved = tokio::fs::canonicalize(path).await?;
+    if !within(roots, &resolved).await {
+        return Err(refused("outside the directories it may be read from"));
+    }
+
+    let file = tokio::fs::File::open(&resolved).await?;
+    let opened = file.metadata().await?;
+    if opened.dev() != checked.dev() || opened.ino() != checked.ino() {
+        return Err(refused("changed while it was being opened"));
+    }
+    Ok(file)
+}
+
+/// Opens a file an agent asked to write, creating it and any missing parent
+/// directories, under the same rules as `open_confined`: no directory on the
+/// way down from the root and not the file itself may be a symlink. The file
+/// is only truncated once the one that got opened is known to be inside.
+pub async fn create_confined(roots: &[&Path], path: &Path) -> std::io::Result<tokio::fs::File> {
+    let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+        return Err(refused("not a file path"));
+    };
+    let Some((root, relative)) = roots
+        .iter()
+        .find_map(|root| Some((*root, parent.strip_prefix(root).ok()?)))
+    else {
+        return Err(refused("outside the directories it may be written to"));
+    };
+    let mut dir = root.to_path_buf();
+    for component in relative.components() {
+        let Component::Normal(part) = component else {
+            return Err(refused("outside the directories it may be written to"));
+        };
+        dir.push(part);
+        match tokio::fs::symlink_metadata(&dir).await {
+            Ok(meta) if meta.file_type().is_dir() => {}
+            Ok(_) => return Err(refused("a parent is not a directory")),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                tokio::fs::create_dir(&dir).await?
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    let parent = tokio::fs::canonicalize(&dir).await?;
+    if !within(roots, &parent).await {
+        return Err(refused("outside the directories it may be written to"));
+    }
+
+    let target = parent.join(name);
+    let file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        // emptied below, once the file that got opened has been checked
+        .truncate(false)
+        .custom_flags(libc::O_NOFOLLOW)
+        .open(&target)
+        .await
+        .map_err(|e| match e.raw_os_error() {
+            Some(libc::ELOOP) => refused("a symlink"),
+            _ => e,
+        })?;
+    let opened = file.metadata().await?;
+    if !opened.file_type().is_file() {
+        return Err(refused("not a regular file"));
+    }
+    // a parent swapped for a link after it was checked shows up here
+    let resolved = tokio::fs::canonicalize(&target).await?;
+    let checked = tokio::fs::symlink_metadata(&resolved).await?;
+    if !within(roots, &resolved).await
+        || opened.dev() != checked.dev()
+        || opened.ino() != checked.ino()
+    {
+        return Err(refused("changed while it was being opened"));
+    }
+    file.set_len(0).await?;
+    Ok(file)
+}
+
+/// Whether a canonical path lies under one of `roots`.
+async fn within(roots: &[&Path], resolved: &Path) -> bool {
+    for root in roots {
+        if let Ok(root) = tokio::fs::canonicalize(root).await
+            && resolved.starts_with(&root)
+        {
+            return true;
+        }
+    }
+    false
+}
+
+/// Copies a file an agent pointed at out of its workspace, under the same
+/// rules as `open_confined`.
+pub async fn copy_agent_file(roots: &[&Path], source: &Path, dest: &Path) -> std::io::Result<u64> {
+    let mut file = open_confined(roots, source).await?;
+    let mut out = tokio::fs::File::create(dest).await?;
+    tokio::io::copy(&mut file, &mut out).await
+}
+
+/// Leaves the path out, since the message can go back to the agent.
+fn refused(reason: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::PermissionDenied, reason)
+}
+
+/// Total size of the regular files under `path`. Symlinks are not followed,
+/// so a link out of the workspace does not count against its quota.
+pub async fn dir_size(path: &Path) -> std::io::Result<u64> {
+    let mut total = 0;
+    let mut pending = vec![path.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let mut entries = match tokio::fs::read_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let file_type = entry.file_type().await?;
+            if file_type.is_dir() {
+                pending.push(entry.path());
+            } else if file_type.is_file() {
+                total += entry.metadata().await?.len();
+            }
+        }
+    }
+    Ok(total)
+}
+
+/// Empties a persistent workspace, keeping the directory itself.
+pub async fn clear_dir(path: &Path) -> std::io::Result<()> {
+    match tokio::fs::remove_dir_all(path).await {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    tokio::fs::create_dir_all(path).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn prepared_project_is_measured_and_clears() {
+        let dir = std::env::temp_dir().join(format!("yui-project-{}", Uuid::new_v4()));
+        let workspace = dir.join("job");
+        let project = dir.join("projects/forge-v2");
+        tokio::fs::create_dir_all(&workspace).await.unwrap();
+
+        let prepared = prepare_project(&project).await.unwrap();
+        assert!(prepared.is_absolute());
+        tokio::fs::write(prepared.join("notes.md"), "12345")
+            .await
+            .unwrap();
+        // preparing again keeps what an earlier run left
+        prepare_project(&project).await.unwrap();
+        assert_eq!(dir_size(&project).await.unwrap(), 5);
+
+        // a link out of the job workspace is not followed when measuring it
+        tokio::fs::symlink(&prepared, workspace.join(PROJECT_MOUNT))
+            .await
+            .unwrap();
+        assert_eq!(dir_size(&workspace).await.unwrap(), 0);
+
+        clear_dir(&project).await.unwrap();
+        assert_eq!(dir_size(&project).await.unwrap(), 0);
+        assert!(project.is_dir());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn agent_files_are_copied_only_from_inside_the_workspace() {
+        let dir = std::env::temp_dir().join(format!("yui-copy-{}", Uuid::new_v4()));
+        let workspace = dir.join("job");
+        let outside = dir.join("host-secret");
+        tokio::fs::create_dir_all(workspace.join("out"))
+            .await
+            .unwrap();
+        tokio::fs::write(workspace.join("out/report.md"), "report")
+            .await
+            .unwrap();
+        tokio::fs::write(&outside, "secret").await.unwrap();
+        tokio::fs::symlink(&outside, workspace.join("leak"))
+            .await
+            .unwrap();
+        tokio::fs::symlink(&dir, workspace.join("up"))
+            .await
+            .unwrap();
+        let roots = [workspace.as_path()];
+        let dest = dir.join("copied");
+
+        assert_eq!(
+            copy_agent_file(&roots, &workspace.join("out/report.md"), &dest)
+                .await
+                .unwrap(),
+            6
+        );
+        for source in ["leak", "up/host-secret", "../host-secret", "out"] {
+            let err = copy_agent_file(&roots, &workspace.join(source), &dest)
+                .await
+                .unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied, "{source}");
+        }
+        assert_eq!(tokio::fs::read_to_string(&dest).await.unwrap(), "report");
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}